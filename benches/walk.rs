@@ -0,0 +1,54 @@
+//! Sequential (`walkdir`) vs parallel walk over a synthetic tree.
+//!
+//! `cargo bench --bench walk`; set `FILE_RS_BENCH_ROOT` to walk a real tree
+//! (e.g. a network mount) instead.
+
+use std::fs;
+use std::path::Path;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use file_rs::fsutil::filter::Filter;
+use file_rs::fsutil::iter::{FindIter, WalkOptions};
+
+/// `width^depth` directories with `files` files each.
+fn build_tree(dir: &Path, depth: u32, width: u32, files: u32) {
+    for f in 0..files {
+        fs::write(dir.join(format!("f{f}.log")), b"x").unwrap();
+    }
+    if depth == 0 {
+        return;
+    }
+    for w in 0..width {
+        let sub = dir.join(format!("d{w}"));
+        fs::create_dir(&sub).unwrap();
+        build_tree(&sub, depth - 1, width, files);
+    }
+}
+
+fn walk(c: &mut Criterion) {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = match std::env::var("FILE_RS_BENCH_ROOT") {
+        Ok(r) => r,
+        Err(_) => {
+            build_tree(tmp.path(), 4, 6, 20);
+            tmp.path().to_string_lossy().into_owned()
+        }
+    };
+    let filter = Filter { exts: vec!["log".into()], include_hidden: true, ..Filter::default() };
+
+    let mut g = c.benchmark_group("walk");
+    g.sample_size(20);
+    g.bench_function("walkdir", |b| b.iter(|| FindIter::new(&root, filter.clone()).count()));
+    for threads in [2, 0] {
+        let opts = WalkOptions { threads: Some(threads), ..WalkOptions::default() };
+        g.bench_function(format!("parallel/threads={threads}"), |b| {
+            b.iter(|| FindIter::parallel(&root, filter.clone(), opts.clone()).count())
+        });
+    }
+    let sorted = WalkOptions { threads: Some(0), sorted: true, ..WalkOptions::default() };
+    g.bench_function("parallel/sorted", |b| b.iter(|| FindIter::parallel(&root, filter.clone(), sorted.clone()).count()));
+    g.finish();
+}
+
+criterion_group!(benches, walk);
+criterion_main!(benches);
@@ -0,0 +1,61 @@
+use clap::Args;
+use std::path::PathBuf;
+use tracing::{error, info};
+
+use super::plan::{changed_paths, Executor, Plan};
+use super::undo;
+
+#[derive(Args, Debug)]
+pub struct ApplyArgs {
+    /// Plan file written with `--plan`
+    pub plan: String,
+}
+
+pub fn run(args: ApplyArgs) {
+    let path = PathBuf::from(&args.plan);
+    let plan = match Plan::read(&path) {
+        Ok(p) => p,
+        Err(e) => {
+            error!(target: "file-rs", action="apply", plan=%path.display(), error=%e, "Failed to read plan");
+            std::process::exit(1);
+        }
+    };
+
+    // All-or-nothing precondition: nothing runs if the tree moved on since planning.
+    let changed = changed_paths(&plan);
+    if !changed.is_empty() {
+        for (p, why) in &changed {
+            error!(target: "file-rs", action="apply", path=%p.display(), reason=%why, "Changed since plan");
+        }
+        error!(target: "file-rs", action="apply", plan=%path.display(), changed=changed.len(), "Refusing to apply plan");
+        std::process::exit(1);
+    }
+
+    let journal = undo::open_journal(false).expect("journal is opened when not in dry-run");
+    let mut exec = Executor::journaled(journal);
+    let mut failures = 0usize;
+    for planned in &plan.ops {
+        let op = &planned.op;
+        match exec.execute(op) {
+            Ok(()) => info!(target: "file-rs", action=op.kind(), dry_run=false, op=%op, "Applied"),
+            Err(e) => {
+                failures += 1;
+                error!(target: "file-rs", action=op.kind(), op=%op, error=%e, "Failed to apply");
+            }
+        }
+    }
+    for (dst, entries, res) in exec.finish() {
+        match res {
+            Ok(()) => info!(target: "file-rs", action="compress", dst=%dst.display(), entries, "Archive written"),
+            Err(e) => {
+                failures += 1;
+                error!(target: "file-rs", action="compress", dst=%dst.display(), error=%e, "Failed to finish archive");
+            }
+        }
+    }
+
+    info!(target: "file-rs", action="apply", plan=%path.display(), ops=plan.ops.len(), failures, "Apply done");
+    if failures > 0 {
+        std::process::exit(1);
+    }
+}
@@ -1,14 +1,84 @@
-use clap::Args;
-
-#[derive(Args, Debug)]
-pub struct CompressArgs {
-    /// Source path to compress
-    pub src: String,
-
-    /// Destination archive path
-    pub dst: String,
-}
-
-pub fn run(args: CompressArgs) {
-    println!("[compress] {} -> {}", args.src, args.dst);
-}
\ No newline at end of file
+use clap::Args;
+use std::path::{Path, PathBuf};
+use tracing::{error, info};
+
+use super::fsutil::archive::{entry_name, ArchiveFormat, ArchiveWriter};
+use super::fsutil::input::{iter_stdin_lines, iter_stdin_nul};
+
+#[derive(Args, Debug)]
+pub struct CompressArgs {
+    /// Source path to compress (`-` reads a list of paths from stdin)
+    pub src: String,
+
+    /// Destination archive path (.tar, .tar.gz, .tgz, .tar.zst, .zip)
+    pub dst: String,
+
+    /// Store entries relative to this directory
+    /// (default: parent of `src`, or `.` when reading from stdin)
+    #[arg(long)]
+    pub base: Option<String>,
+
+    /// Compression level (gzip/zip 0-9, zstd 1-22)
+    #[arg(long)]
+    pub level: Option<i64>,
+
+    /// Read NUL-delimited input from stdin (for find --print0 compatibility)
+    #[arg(long)]
+    pub stdin0: bool,
+}
+
+pub fn run(args: CompressArgs) {
+    let (sources, default_base): (Vec<PathBuf>, PathBuf) = if args.src == "-" {
+        let list: Vec<String> = if args.stdin0 {
+            iter_stdin_nul().collect()
+        } else {
+            match iter_stdin_lines().collect() {
+                Ok(lines) => lines,
+                Err(e) => {
+                    error!(target: "file-rs", action="compress", error=%e, "Failed to read paths from stdin");
+                    std::process::exit(1);
+                }
+            }
+        };
+        (list.into_iter().map(PathBuf::from).collect(), PathBuf::from("."))
+    } else {
+        let src = PathBuf::from(&args.src);
+        let parent = src.parent().map(Path::to_path_buf).unwrap_or_default();
+        (vec![src], parent)
+    };
+    let base = args.base.as_ref().map(PathBuf::from).unwrap_or(default_base);
+    let dst = PathBuf::from(&args.dst);
+
+    if ArchiveFormat::from_path(&dst).is_none() {
+        error!(target: "file-rs", action="compress", dst=%dst.display(), "Unknown archive extension");
+        std::process::exit(2);
+    }
+
+    let mut writer = match ArchiveWriter::create(&dst, args.level) {
+        Ok(w) => w,
+        Err(e) => {
+            error!(target: "file-rs", action="compress", dst=%dst.display(), error=%e, "Failed to create archive");
+            std::process::exit(1);
+        }
+    };
+
+    let mut failures = 0usize;
+    for p in sources {
+        let res = entry_name(&p, &base).and_then(|name| writer.append(&p, &name));
+        if let Err(e) = res {
+            failures += 1;
+            error!(target: "file-rs", action="compress", src=%p.display(), error=%e, "Failed to add");
+        }
+    }
+
+    let entries = writer.entries();
+    if let Err(e) = writer.finish() {
+        error!(target: "file-rs", action="compress", dst=%dst.display(), error=%e, "Failed to finish archive");
+        std::process::exit(1);
+    }
+    info!(target: "file-rs", action="compress", dst=%dst.display(), entries, failures, "Archive written");
+
+    if failures > 0 {
+        std::process::exit(1);
+    }
+}
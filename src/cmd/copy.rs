@@ -0,0 +1,311 @@
+use clap::Args;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use tracing::{error, info};
+
+use super::fsutil::file::{copy_tree, CopyOptions};
+use super::fsutil::journal::{Fingerprint, JournalOp};
+use super::fsutil::input::{iter_stdin_lines, iter_stdin_nul};
+use super::mv::{resolve_conflict, OnConflict};
+use super::plan::{self, Op, PlannedOp, RunMode};
+use super::undo;
+
+#[derive(Args, Debug)]
+pub struct CopyArgs {
+    /// Source path (if omitted, will read from stdin)
+    #[arg(short, long)]
+    pub src: Option<String>,
+
+    /// Destination directory (created if missing)
+    pub dst: String,
+
+    /// Read NUL-delimited input from stdin (for find --print0 compatibility)
+    #[arg(long)]
+    pub stdin0: bool,
+
+    /// What to do when the destination already exists
+    #[arg(long, value_enum, default_value_t = OnConflict::Fail)]
+    pub on_conflict: OnConflict,
+
+    /// Do not preserve timestamps and permission bits
+    #[arg(long)]
+    pub no_preserve: bool,
+
+    /// Preserve uid/gid (usually requires root)
+    #[arg(long)]
+    pub preserve_owner: bool,
+
+    /// Preserve extended attributes
+    #[arg(long)]
+    pub preserve_xattrs: bool,
+
+    #[command(flatten)]
+    pub mode: RunMode,
+
+    /// Write the planned copies to this JSON file instead of running them
+    #[arg(long, value_name = "FILE")]
+    pub plan: Option<String>,
+}
+
+impl CopyArgs {
+    fn copy_options(&self) -> CopyOptions {
+        CopyOptions {
+            times: !self.no_preserve,
+            mode: !self.no_preserve,
+            owner: self.preserve_owner,
+            xattrs: self.preserve_xattrs,
+            sync: false,
+        }
+    }
+}
+
+/// Result of a single copy request.
+#[derive(Debug, PartialEq, Eq)]
+pub enum CopyOutcome {
+    Copied { dst: PathBuf, bytes: u64 },
+    WouldCopy(PathBuf),
+    Skipped(PathBuf),
+}
+
+pub fn run(args: CopyArgs) {
+    let opts = args.copy_options();
+    let sources: Vec<String> = if let Some(src) = &args.src {
+        vec![src.clone()]
+    } else if args.stdin0 {
+        iter_stdin_nul().collect()
+    } else {
+        match iter_stdin_lines().collect() {
+            Ok(lines) => lines,
+            Err(e) => {
+                error!(target: "file-rs", action="copy", error=%e, "Failed to read paths from stdin");
+                std::process::exit(1);
+            }
+        }
+    };
+
+    let dst_dir = PathBuf::from(&args.dst);
+    let mut failures = 0usize;
+    let mut total_bytes = 0u64;
+
+    if let Some(plan) = &args.plan {
+        let mut ops = Vec::new();
+        for s in sources {
+            let p = PathBuf::from(&s);
+            match plan_copy(&p, &dst_dir, args.on_conflict, &opts) {
+                Ok(Some(op)) => ops.push(op),
+                Ok(None) => {
+                    info!(target: "file-rs", action="copy", src=%p.display(), "Skipped (destination exists)");
+                }
+                Err(e) => {
+                    failures += 1;
+                    error!(target: "file-rs", action="copy", src=%p.display(), error=%e, "Failed to plan copy");
+                }
+            }
+        }
+        plan::write_or_exit(plan, ops, failures);
+        return;
+    }
+
+    let dry_run = args.mode.dry_run();
+    let journal = undo::open_journal(dry_run);
+    for s in sources {
+        let p = PathBuf::from(&s);
+        match copy_path(&p, &dst_dir, args.on_conflict, &opts, dry_run) {
+            Ok(CopyOutcome::WouldCopy(dst)) => {
+                info!(target: "file-rs", action="copy", dry_run=true, src=%p.display(), dst=%dst.display(), "Would copy");
+            }
+            Ok(CopyOutcome::Copied { dst, bytes }) => {
+                total_bytes += bytes;
+                info!(target: "file-rs", action="copy", dry_run=false, src=%p.display(), dst=%dst.display(), bytes, "Copied");
+                if !undo::record(journal.as_ref(), JournalOp::Copy, &p, Some(&dst), Fingerprint::of(&dst)) {
+                    failures += 1;
+                }
+            }
+            Ok(CopyOutcome::Skipped(dst)) => {
+                info!(target: "file-rs", action="copy", src=%p.display(), dst=%dst.display(), "Skipped (destination exists)");
+            }
+            Err(e) => {
+                failures += 1;
+                error!(target: "file-rs", action="copy", src=%p.display(), error=%e, "Failed to copy");
+            }
+        }
+    }
+
+    info!(target: "file-rs", action="copy", bytes=total_bytes, failures, "Copy done");
+    if failures > 0 {
+        std::process::exit(1);
+    }
+}
+
+/// Copy `src` (file or tree) into directory `dst_dir`, keeping its file name
+/// and applying `on_conflict` when that name is already taken.
+pub fn copy_path(
+    src: &Path,
+    dst_dir: &Path,
+    on_conflict: OnConflict,
+    opts: &CopyOptions,
+    dry_run: bool,
+) -> io::Result<CopyOutcome> {
+    let name = src
+        .file_name()
+        .ok_or_else(|| io::Error::other("source has no file name"))?;
+    let dst = match resolve_conflict(&dst_dir.join(name), on_conflict)? {
+        Some(dst) => dst,
+        None => return Ok(CopyOutcome::Skipped(dst_dir.join(name))),
+    };
+
+    check_not_into_itself(src, dst_dir)?;
+    if dry_run {
+        return Ok(CopyOutcome::WouldCopy(dst));
+    }
+
+    fs::create_dir_all(dst_dir)?;
+    let bytes = copy_tree(src, &dst, opts)?;
+    Ok(CopyOutcome::Copied { dst, bytes })
+}
+
+/// Canonical form of `path`, which need not exist yet: its deepest existing
+/// ancestor is canonicalized and the missing components are appended.
+/// Refuse to copy directory `src` into `dst_dir` when that lies inside it.
+pub fn check_not_into_itself(src: &Path, dst_dir: &Path) -> io::Result<()> {
+    let dst_dir = if dst_dir.as_os_str().is_empty() { Path::new(".") } else { dst_dir };
+    if fs::symlink_metadata(src)?.is_dir() && canonical_target(dst_dir)?.starts_with(fs::canonicalize(src)?) {
+        return Err(io::Error::other("cannot copy a directory into itself"));
+    }
+    Ok(())
+}
+
+fn canonical_target(path: &Path) -> io::Result<PathBuf> {
+    let mut missing = Vec::new();
+    let mut cur = path;
+    loop {
+        match fs::canonicalize(cur) {
+            Ok(base) => return Ok(missing.iter().rev().fold(base, |p, c| p.join(c))),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                let (Some(parent), Some(name)) = (cur.parent(), cur.file_name()) else { return Err(e) };
+                missing.push(name);
+                cur = if parent.as_os_str().is_empty() { Path::new(".") } else { parent };
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Resolve the copy of `src` into `dst_dir` into a plan entry. `None` means skip.
+pub fn plan_copy(src: &Path, dst_dir: &Path, on_conflict: OnConflict, opts: &CopyOptions) -> io::Result<Option<PlannedOp>> {
+    let name = src
+        .file_name()
+        .ok_or_else(|| io::Error::other("source has no file name"))?;
+    let Some(dst) = resolve_conflict(&dst_dir.join(name), on_conflict)? else {
+        return Ok(None);
+    };
+    check_not_into_itself(src, dst_dir)?;
+    let overwrite = on_conflict == OnConflict::Overwrite;
+    let op = Op::Copy { src: src.to_path_buf(), dst, overwrite, preserve: *opts };
+    PlannedOp::checked(op).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use filetime::FileTime;
+    use tempfile::tempdir;
+
+    #[test]
+    fn copies_tree_and_counts_bytes() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(src.join("sub")).unwrap();
+        fs::write(src.join("a.txt"), b"hello").unwrap();
+        fs::write(src.join("sub/b.txt"), b"abc").unwrap();
+        let out = dir.path().join("out");
+
+        let res = copy_path(&src, &out, OnConflict::Fail, &CopyOptions::default(), false).unwrap();
+        assert_eq!(res, CopyOutcome::Copied { dst: out.join("src"), bytes: 8 });
+        assert_eq!(fs::read(out.join("src/a.txt")).unwrap(), b"hello");
+        assert_eq!(fs::read(out.join("src/sub/b.txt")).unwrap(), b"abc");
+        assert!(src.join("a.txt").exists());
+    }
+
+    #[test]
+    fn preserves_mtime_and_mode() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("a.txt");
+        fs::write(&src, b"x").unwrap();
+        let old = FileTime::from_unix_time(1_000_000_000, 0);
+        filetime::set_file_mtime(&src, old).unwrap();
+        let mut perms = fs::metadata(&src).unwrap().permissions();
+        perms.set_readonly(true);
+        fs::set_permissions(&src, perms).unwrap();
+        let out = dir.path().join("out");
+
+        copy_path(&src, &out, OnConflict::Fail, &CopyOptions::default(), false).unwrap();
+        let meta = fs::metadata(out.join("a.txt")).unwrap();
+        assert_eq!(FileTime::from_last_modification_time(&meta), old);
+        assert!(meta.permissions().readonly());
+    }
+
+    #[test]
+    fn dry_run_does_not_copy() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("a.txt");
+        fs::write(&src, b"x").unwrap();
+        let out = dir.path().join("out");
+
+        let res = copy_path(&src, &out, OnConflict::Fail, &CopyOptions::default(), true).unwrap();
+        assert_eq!(res, CopyOutcome::WouldCopy(out.join("a.txt")));
+        assert!(!out.exists());
+    }
+
+    #[test]
+    fn refuses_copy_into_itself() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir(&src).unwrap();
+
+        let err = copy_path(&src, &src.join("inner/deeper"), OnConflict::Fail, &CopyOptions::default(), false);
+        assert!(err.is_err());
+        assert!(!src.join("inner").exists());
+        assert!(copy_path(&src, &src.join("inner"), OnConflict::Fail, &CopyOptions::default(), true).is_err());
+    }
+
+    #[test]
+    fn plan_and_apply_refuse_copy_into_itself() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir(&src).unwrap();
+
+        assert!(plan_copy(&src, &src.join("inner"), OnConflict::Fail, &CopyOptions::default()).is_err());
+
+        // A hand-edited (or stale) plan must not recurse either.
+        let op = Op::Copy { src: src.clone(), dst: src.join("inner/src"), overwrite: false, preserve: CopyOptions::default() };
+        assert!(plan::Executor::default().execute(&op).is_err());
+        assert!(!src.join("inner").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn overwrite_replaces_symlinks_instead_of_following_them() {
+        use std::os::unix::fs::symlink;
+        let dir = tempdir().unwrap();
+        let elsewhere = dir.path().join("elsewhere.txt");
+        fs::write(&elsewhere, b"keep").unwrap();
+        let out = dir.path().join("out");
+        fs::create_dir(&out).unwrap();
+        symlink(&elsewhere, out.join("a.txt")).unwrap();
+        symlink(&elsewhere, out.join("link")).unwrap();
+
+        let src = dir.path().join("a.txt");
+        fs::write(&src, b"new").unwrap();
+        copy_path(&src, &out, OnConflict::Overwrite, &CopyOptions::default(), false).unwrap();
+        assert!(!fs::symlink_metadata(out.join("a.txt")).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read(out.join("a.txt")).unwrap(), b"new");
+        assert_eq!(fs::read(&elsewhere).unwrap(), b"keep");
+
+        let link = dir.path().join("link");
+        symlink("a.txt", &link).unwrap();
+        copy_path(&link, &out, OnConflict::Overwrite, &CopyOptions::default(), false).unwrap();
+        assert_eq!(fs::read_link(out.join("link")).unwrap(), Path::new("a.txt"));
+    }
+}
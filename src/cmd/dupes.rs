@@ -0,0 +1,294 @@
+use clap::Args;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use tracing::{error, info};
+
+use super::find;
+use super::fsutil::archive::entry_name;
+use super::fsutil::dupes::{find_duplicates, DupGroup, Keep};
+use super::mv::{self, OnConflict};
+use super::plan::{self, Executor, Op, PlannedOp, RunMode};
+use super::undo;
+
+#[derive(Args, Debug)]
+pub struct DupesArgs {
+    /// Which files to compare
+    #[command(flatten)]
+    pub find: find::FindArgs,
+
+    /// Which copy of each group to keep: oldest, newest or shortest (path); it is listed first
+    #[arg(long, value_name = "RULE", default_value = "oldest")]
+    pub keep: Keep,
+
+    /// Print groups as one JSON array of {size, sha256, files} objects
+    #[arg(long)]
+    pub json: bool,
+
+    /// Delete every copy but the kept one
+    #[arg(long, group = "resolve")]
+    pub delete: bool,
+
+    /// With `--delete`, move the copies to the freedesktop trash instead
+    #[arg(long, requires = "delete")]
+    pub trash: bool,
+
+    /// Replace every copy but the kept one with a hard link to it
+    #[arg(long, group = "resolve")]
+    pub link: bool,
+
+    /// Move every copy but the kept one below this directory, keeping paths relative to the root
+    #[arg(long, value_name = "DIR", group = "resolve")]
+    pub quarantine: Option<String>,
+
+    #[command(flatten)]
+    pub mode: RunMode,
+
+    /// Write the planned resolution to this JSON file instead of running it
+    #[arg(long, value_name = "FILE", requires = "resolve")]
+    pub plan: Option<String>,
+}
+
+/// What happens to the copies that are not kept.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Resolve {
+    Delete { trash: bool },
+    Link,
+    Quarantine(PathBuf),
+}
+
+impl Resolve {
+    fn from_args(args: &DupesArgs) -> Option<Self> {
+        if args.delete {
+            Some(Self::Delete { trash: args.trash })
+        } else if args.link {
+            Some(Self::Link)
+        } else {
+            args.quarantine.as_ref().map(|d| Self::Quarantine(PathBuf::from(d)))
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct GroupRecord<'a> {
+    size: u64,
+    sha256: &'a str,
+    files: Vec<String>,
+}
+
+pub fn run(args: DupesArgs) {
+    let dry_run = args.mode.dry_run();
+    let resolve = Resolve::from_args(&args);
+
+    let filter = find::build_filter(&args.find);
+    let mut found = find::find_with_options(&args.find.root, filter, find::walk_options(&args.find));
+    let mut infos = Vec::new();
+    for res in find::selected(&mut found, &args.find) {
+        match res {
+            Ok(info) => infos.push(info),
+            Err(e) => find::log_skipped(&e, "dupes"),
+        }
+    }
+    let mut skipped = find::summarize_skipped(&found, "dupes");
+
+    let mut dupes = find_duplicates(infos);
+    for (path, e) in &dupes.errors {
+        error!(target: "file-rs", action="dupes", path=%path.display(), error=%e, "Failed to read");
+    }
+    skipped += dupes.errors.len();
+    for g in &mut dupes.groups {
+        g.sort_by(args.keep);
+    }
+    let copies: usize = dupes.groups.iter().map(|g| g.files.len() - 1).sum();
+    let wasted: u64 = dupes.groups.iter().map(DupGroup::wasted).sum();
+    info!(target: "file-rs", action="dupes", groups=dupes.groups.len(), copies, wasted, "Duplicates found");
+
+    match print_groups(io::stdout().lock(), &dupes.groups, args.json) {
+        // The reader went away (e.g. `| head`); nothing left to report to.
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return,
+        Err(e) => {
+            error!(target: "file-rs", action="dupes", error=%e, "Failed to write results");
+            std::process::exit(1);
+        }
+        Ok(()) => {}
+    }
+
+    let mut failures = if args.find.strict { skipped } else { 0 };
+    let Some(resolve) = resolve else {
+        if failures > 0 {
+            std::process::exit(1);
+        }
+        return;
+    };
+
+    let base = PathBuf::from(&args.find.root);
+    let mut reserved = HashSet::new();
+    let mut planned = Vec::new();
+    for g in &dupes.groups {
+        match plan_group(g, &resolve, &base, &mut reserved) {
+            Ok(ops) => planned.extend(ops),
+            Err(e) => {
+                failures += 1;
+                error!(target: "file-rs", action="dupes", path=%g.files[0].path.display(), error=%e, "Failed to plan");
+            }
+        }
+    }
+
+    if let Some(plan) = &args.plan {
+        plan::write_or_exit(plan, planned, failures);
+        return;
+    }
+
+    let mut exec = match undo::open_journal(dry_run) {
+        Some(journal) => Executor::journaled(journal),
+        None => Executor::default(),
+    };
+    for planned in &planned {
+        let op = &planned.op;
+        if dry_run {
+            info!(target: "file-rs", action=op.kind(), dry_run=true, op=%op, "Would run");
+            continue;
+        }
+        match exec.execute(op) {
+            Ok(()) => info!(target: "file-rs", action=op.kind(), dry_run=false, op=%op, "Done"),
+            Err(e) => {
+                failures += 1;
+                error!(target: "file-rs", action=op.kind(), op=%op, error=%e, "Failed");
+            }
+        }
+    }
+
+    info!(target: "file-rs", action="dupes", dry_run, failures, "Dupes done");
+    if failures > 0 {
+        std::process::exit(1);
+    }
+}
+
+/// Groups separated by blank lines, or one JSON array.
+fn print_groups<W: Write>(mut out: W, groups: &[DupGroup], json: bool) -> io::Result<()> {
+    if json {
+        let records: Vec<GroupRecord> = groups
+            .iter()
+            .map(|g| GroupRecord {
+                size: g.size,
+                sha256: &g.sha256,
+                files: g.files.iter().map(|i| i.path.to_string_lossy().into_owned()).collect(),
+            })
+            .collect();
+        serde_json::to_writer_pretty(&mut out, &records).map_err(io::Error::other)?;
+        writeln!(out)?;
+        return out.flush();
+    }
+    for (i, g) in groups.iter().enumerate() {
+        if i > 0 {
+            writeln!(out)?;
+        }
+        for info in &g.files {
+            out.write_all(info.path.as_os_str().as_encoded_bytes())?;
+            writeln!(out)?;
+        }
+    }
+    out.flush()
+}
+
+/// Operations for every copy in `g` except the first (kept) one.
+/// Quarantine destinations already handed out are tracked in `reserved`.
+fn plan_group(
+    g: &DupGroup,
+    resolve: &Resolve,
+    base: &Path,
+    reserved: &mut HashSet<PathBuf>,
+) -> io::Result<Vec<PlannedOp>> {
+    let keep = &g.files[0].path;
+    let mut ops = Vec::new();
+    for info in &g.files[1..] {
+        let path = info.path.clone();
+        let op = match resolve {
+            Resolve::Delete { trash: true } => Op::Trash { path },
+            Resolve::Delete { trash: false } => Op::Rm { path, recursive: false },
+            Resolve::Link => Op::Link { path, target: keep.clone() },
+            Resolve::Quarantine(dir) => {
+                let wanted = dir.join(entry_name(&path, base)?);
+                let taken = |p: &Path| reserved.contains(p) || fs::symlink_metadata(p).is_ok();
+                let dst = mv::resolve_conflict_with(&wanted, OnConflict::Rename, taken)?
+                    .expect("rename policy never skips");
+                reserved.insert(dst.clone());
+                Op::Mv { src: path, dst, overwrite: false }
+            }
+        };
+        ops.push(PlannedOp::checked(op)?);
+    }
+    Ok(ops)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsutil::pathinfo::PathInfo;
+    use std::time::SystemTime;
+    use tempfile::tempdir;
+
+    fn group(paths: &[PathBuf]) -> DupGroup {
+        let files = paths.iter().map(|p| PathInfo::from_fs(p, SystemTime::now()).unwrap()).collect();
+        DupGroup { size: 1, sha256: String::new(), files }
+    }
+
+    #[test]
+    fn plans_each_resolution_for_all_but_the_first() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("root");
+        fs::create_dir_all(root.join("x")).unwrap();
+        let paths = ["a.txt", "x/a.txt", "b.txt"].map(|n| root.join(n));
+        for p in &paths {
+            fs::write(p, b"x").unwrap();
+        }
+        let g = group(&paths);
+        let ops = |r: &Resolve| -> Vec<Op> {
+            plan_group(&g, r, &root, &mut HashSet::new()).unwrap().into_iter().map(|p| p.op).collect()
+        };
+
+        assert_eq!(
+            ops(&Resolve::Delete { trash: false }),
+            [Op::Rm { path: paths[1].clone(), recursive: false }, Op::Rm { path: paths[2].clone(), recursive: false }]
+        );
+        assert_eq!(ops(&Resolve::Link)[1], Op::Link { path: paths[2].clone(), target: paths[0].clone() });
+
+        // Paths stay relative to the root; names already in quarantine are not reused.
+        let q = dir.path().join("q");
+        fs::create_dir_all(q.join("x")).unwrap();
+        fs::write(q.join("x/a.txt"), b"old").unwrap();
+        assert_eq!(
+            ops(&Resolve::Quarantine(q.clone())),
+            [
+                Op::Mv { src: paths[1].clone(), dst: q.join("x/a (1).txt"), overwrite: false },
+                Op::Mv { src: paths[2].clone(), dst: q.join("b.txt"), overwrite: false },
+            ]
+        );
+    }
+
+    #[test]
+    fn prints_groups_as_text_or_json() {
+        let dir = tempdir().unwrap();
+        let paths = ["a", "b", "c", "d"].map(|n| dir.path().join(n));
+        for p in &paths {
+            fs::write(p, b"x").unwrap();
+        }
+        let groups = [group(&paths[..2]), group(&paths[2..])];
+
+        let mut buf = Vec::new();
+        print_groups(&mut buf, &groups, false).unwrap();
+        let text = String::from_utf8(buf).unwrap();
+        let blocks: Vec<&str> = text.split("\n\n").collect();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[1].lines().collect::<Vec<_>>(), [paths[2].to_str().unwrap(), paths[3].to_str().unwrap()]);
+
+        let mut buf = Vec::new();
+        print_groups(&mut buf, &groups, true).unwrap();
+        let v: serde_json::Value = serde_json::from_slice(&buf).unwrap();
+        assert_eq!(v.as_array().unwrap().len(), 2);
+        assert_eq!(v[0]["files"][1], paths[1].to_str().unwrap());
+        assert_eq!(v[0]["size"], 1);
+    }
+}
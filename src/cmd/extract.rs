@@ -0,0 +1,328 @@
+use clap::Args;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tracing::{error, info, warn};
+
+use super::find::select_or_exit;
+use super::fsutil::archive::{
+    for_each_entry, link_stays_inside, sanitize_entry_name, EntryKind, EntryMeta,
+};
+use super::fsutil::filter::{Filter, Matcher};
+use super::fsutil::pathinfo::PathInfo;
+
+#[derive(Args, Debug)]
+pub struct ExtractArgs {
+    /// Archive to extract (.tar, .tar.gz, .tgz, .tar.zst, .zip)
+    pub archive: String,
+
+    /// Target directory (created if missing)
+    pub dir: String,
+
+    /// Drop this many leading path components from member names
+    #[arg(long, default_value_t = 0)]
+    pub strip_components: usize,
+
+    /// Only extract members matching this DSL (same syntax as `find --select`)
+    #[arg(long)]
+    pub select: Option<String>,
+
+    /// Replace files that already exist in the target directory
+    #[arg(long)]
+    pub overwrite: bool,
+}
+
+/// Options for a single extraction run.
+#[derive(Default)]
+pub struct ExtractOptions {
+    pub strip_components: usize,
+    pub filter: Option<Filter>,
+    pub overwrite: bool,
+}
+
+/// Counters reported at the end of an extraction.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ExtractSummary {
+    pub extracted: u64,
+    pub skipped: u64,
+    pub failures: u64,
+}
+
+pub fn run(args: ExtractArgs) {
+    let opts = ExtractOptions {
+        strip_components: args.strip_components,
+        filter: args.select.as_deref().map(select_or_exit),
+        overwrite: args.overwrite,
+    };
+    let archive = PathBuf::from(&args.archive);
+    let dir = PathBuf::from(&args.dir);
+
+    match extract_archive(&archive, &dir, &opts) {
+        Ok(sum) => {
+            info!(target: "file-rs", action="extract", archive=%archive.display(), dir=%dir.display(),
+                extracted=sum.extracted, skipped=sum.skipped, failures=sum.failures, "Extract done");
+            if sum.failures > 0 {
+                std::process::exit(1);
+            }
+        }
+        Err(e) => {
+            error!(target: "file-rs", action="extract", archive=%archive.display(), error=%e, "Failed to read archive");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Extract `archive` into `dir`. Unsafe members are reported and skipped;
+/// the error path is only taken when the archive itself cannot be read.
+pub fn extract_archive(archive: &Path, dir: &Path, opts: &ExtractOptions) -> io::Result<ExtractSummary> {
+    fs::create_dir_all(dir)?;
+    let now = SystemTime::now();
+    let mut sum = ExtractSummary::default();
+
+    for_each_entry(archive, |meta, r| {
+        let rel = match sanitize_entry_name(&meta.name, opts.strip_components) {
+            Ok(Some(rel)) => rel,
+            Ok(None) => return Ok(()),
+            Err(e) => {
+                sum.failures += 1;
+                error!(target: "file-rs", action="extract", member=%meta.name.display(), error=%e, "Rejected member");
+                return Ok(());
+            }
+        };
+
+        if let Some(f) = &opts.filter {
+            if !f.matches(&member_info(&rel, meta, now)) {
+                sum.skipped += 1;
+                return Ok(());
+            }
+        }
+
+        match extract_one(dir, &rel, meta, r, opts.overwrite) {
+            Ok(true) => sum.extracted += 1,
+            Ok(false) => sum.skipped += 1,
+            Err(e) => {
+                sum.failures += 1;
+                error!(target: "file-rs", action="extract", member=%meta.name.display(), error=%e, "Failed to extract");
+            }
+        }
+        Ok(())
+    })?;
+
+    Ok(sum)
+}
+
+/// PathInfo for an archive member, so the regular `Filter` can match it.
+pub fn member_info(rel: &Path, meta: &EntryMeta, now: SystemTime) -> PathInfo {
+    let is_file = meta.kind == EntryKind::File;
+    let size = if is_file { Some(meta.size) } else { None };
+    PathInfo::from_parts(rel, is_file, meta.kind == EntryKind::Dir, size, meta.mtime, now)
+}
+
+/// Write one member below `dir`. Returns `Ok(false)` for members that are skipped.
+fn extract_one(dir: &Path, rel: &Path, meta: &EntryMeta, r: &mut dyn Read, overwrite: bool) -> io::Result<bool> {
+    ensure_no_symlink_ancestors(dir, rel)?;
+    let dst = dir.join(rel);
+
+    match meta.kind {
+        EntryKind::Dir => {
+            fs::create_dir_all(&dst)?;
+            Ok(true)
+        }
+        EntryKind::File => {
+            if let Some(parent) = dst.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            clear_existing(&dst, overwrite)?;
+            // create_new never follows a symlink planted at `dst`.
+            let mut out = OpenOptions::new().write(true).create_new(true).open(&dst)?;
+            io::copy(r, &mut out)?;
+            drop(out);
+            #[cfg(unix)]
+            if let Some(mode) = meta.mode {
+                use std::os::unix::fs::PermissionsExt;
+                fs::set_permissions(&dst, fs::Permissions::from_mode(mode & 0o777))?;
+            }
+            if let Some(mtime) = meta.mtime {
+                filetime::set_file_mtime(&dst, filetime::FileTime::from_system_time(mtime))?;
+            }
+            Ok(true)
+        }
+        EntryKind::Symlink => {
+            let target = meta
+                .link_target
+                .as_deref()
+                .ok_or_else(|| io::Error::other("symlink without target"))?;
+            if !link_stays_inside(dir, rel, target) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("symlink escapes target directory: -> {}", target.display()),
+                ));
+            }
+            if let Some(parent) = dst.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            clear_existing(&dst, overwrite)?;
+            symlink(target, &dst)?;
+            Ok(true)
+        }
+        EntryKind::Other => {
+            warn!(target: "file-rs", action="extract", member=%meta.name.display(), "Skipping unsupported member type");
+            Ok(false)
+        }
+    }
+}
+
+/// Refuse to write through a symlink created by an earlier member.
+fn ensure_no_symlink_ancestors(dir: &Path, rel: &Path) -> io::Result<()> {
+    let mut cur = dir.to_path_buf();
+    let parents = rel.parent().map(|p| p.components().count()).unwrap_or(0);
+    for c in rel.components().take(parents) {
+        cur.push(c);
+        if fs::symlink_metadata(&cur).is_ok_and(|m| m.file_type().is_symlink()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("member path goes through a symlink: {}", cur.display()),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn clear_existing(dst: &Path, overwrite: bool) -> io::Result<()> {
+    match fs::symlink_metadata(dst) {
+        Ok(m) if overwrite && !m.is_dir() => fs::remove_file(dst),
+        Ok(_) => Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("destination exists: {}", dst.display()),
+        )),
+        Err(_) => Ok(()),
+    }
+}
+
+#[cfg(unix)]
+fn symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(not(unix))]
+fn symlink(_target: &Path, _link: &Path) -> io::Result<()> {
+    Err(io::Error::other("symlinks are not supported on this platform"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::find::parse_select_dsl;
+    use crate::fsutil::archive::ArchiveWriter;
+    use tempfile::tempdir;
+
+    fn make_tar(dir: &Path, entries: &[(&str, tar::EntryType, &str)]) -> PathBuf {
+        let out = dir.join("evil.tar");
+        let mut b = tar::Builder::new(fs::File::create(&out).unwrap());
+        for (name, ty, body) in entries {
+            let mut h = tar::Header::new_gnu();
+            h.set_entry_type(*ty);
+            h.set_mode(0o644);
+            if *ty == tar::EntryType::Symlink {
+                h.set_size(0);
+                b.append_link(&mut h, name, body).unwrap();
+            } else {
+                h.set_size(body.len() as u64);
+                // Raw name bytes so `..` and absolute paths survive.
+                let bytes = name.as_bytes();
+                h.as_old_mut().name[..bytes.len()].copy_from_slice(bytes);
+                h.set_cksum();
+                b.append(&h, body.as_bytes()).unwrap();
+            }
+        }
+        b.finish().unwrap();
+        out
+    }
+
+    #[test]
+    fn roundtrip_with_strip_and_select() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(src.join("sub")).unwrap();
+        fs::write(src.join("sub/a.txt"), b"a").unwrap();
+        fs::write(src.join("b.jpg"), b"b").unwrap();
+        let ar = dir.path().join("out.tar.zst");
+        let mut w = ArchiveWriter::create(&ar, None).unwrap();
+        w.append(&src, Path::new("src")).unwrap();
+        w.finish().unwrap();
+
+        let to = dir.path().join("to");
+        let opts = ExtractOptions {
+            strip_components: 1,
+            filter: Some(parse_select_dsl("ext:txt").unwrap()),
+            overwrite: false,
+        };
+        let sum = extract_archive(&ar, &to, &opts).unwrap();
+        assert_eq!(sum.failures, 0);
+        assert_eq!(fs::read(to.join("sub/a.txt")).unwrap(), b"a");
+        assert!(!to.join("b.jpg").exists());
+    }
+
+    #[test]
+    fn rejects_traversal_and_escaping_symlinks() {
+        let dir = tempdir().unwrap();
+        let ar = make_tar(
+            dir.path(),
+            &[
+                ("../evil.txt", tar::EntryType::Regular, "x"),
+                ("/abs.txt", tar::EntryType::Regular, "x"),
+                ("link", tar::EntryType::Symlink, "../outside"),
+                ("inner", tar::EntryType::Symlink, "."),
+                // Chained through the link above: `to/./..`.
+                ("s", tar::EntryType::Symlink, "inner/.."),
+                // The same, with the redirecting link extracted afterwards.
+                ("t", tar::EntryType::Symlink, "later/.."),
+                ("later", tar::EntryType::Symlink, "."),
+                ("ok.txt", tar::EntryType::Regular, "ok"),
+            ],
+        );
+        let to = dir.path().join("to");
+
+        let sum = extract_archive(&ar, &to, &ExtractOptions::default()).unwrap();
+        assert_eq!(sum.failures, 5);
+        assert_eq!(sum.extracted, 3);
+        assert!(!dir.path().join("evil.txt").exists());
+        for name in ["link", "s", "t"] {
+            assert!(fs::symlink_metadata(to.join(name)).is_err(), "{name}");
+        }
+        assert_eq!(fs::read(to.join("ok.txt")).unwrap(), b"ok");
+    }
+
+    #[test]
+    fn refuses_to_write_through_symlinks() {
+        let dir = tempdir().unwrap();
+        let outside = dir.path().join("outside");
+        fs::create_dir(&outside).unwrap();
+        let ar = make_tar(dir.path(), &[("d/x.txt", tar::EntryType::Regular, "x")]);
+        let to = dir.path().join("to");
+        fs::create_dir(&to).unwrap();
+        symlink(&outside, &to.join("d")).unwrap();
+
+        let sum = extract_archive(&ar, &to, &ExtractOptions::default()).unwrap();
+        assert_eq!(sum.failures, 1);
+        assert!(!outside.join("x.txt").exists());
+    }
+
+    #[test]
+    fn existing_files_need_overwrite() {
+        let dir = tempdir().unwrap();
+        let ar = make_tar(dir.path(), &[("a.txt", tar::EntryType::Regular, "new")]);
+        let to = dir.path().join("to");
+        fs::create_dir(&to).unwrap();
+        fs::write(to.join("a.txt"), b"old").unwrap();
+
+        let sum = extract_archive(&ar, &to, &ExtractOptions::default()).unwrap();
+        assert_eq!(sum.failures, 1);
+        assert_eq!(fs::read(to.join("a.txt")).unwrap(), b"old");
+
+        let opts = ExtractOptions { overwrite: true, ..ExtractOptions::default() };
+        extract_archive(&ar, &to, &opts).unwrap();
+        assert_eq!(fs::read(to.join("a.txt")).unwrap(), b"new");
+    }
+}
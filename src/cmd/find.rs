@@ -1,140 +1,494 @@
-use clap::Args;
-use std::path::PathBuf;
-
-use super::fsutil::filter::{Filter, Matcher, merge};
-use super::fsutil::iter::FindIter;
-use super::fsutil::util::{parse_human_age, parse_human_size};
-
-#[derive(Args, Debug, Clone)]
-pub struct FindArgs {
-    #[arg(short, long, default_value = ".")]
-    pub root: String,
-
-    /// pattern, glob, suffix
-    pub pattern: Option<String>,
-
-    /// DSL (e.g. `contains:report ext:txt age>2d size<10MB hidden:true type:file`)
-    #[arg(long)]
-    pub select: Option<String>,
-
-    // preset / shortcut
-    #[arg(long)] pub images: bool,
-    #[arg(long)] pub videos: bool,
-    #[arg(long)] pub docs: bool,
-
-    #[arg(long)] pub older: Option<String>,   // "30d"  -> min_age_secs
-    #[arg(long)] pub newer: Option<String>,   // "2h"   -> max_age_secs
-    #[arg(long)] pub larger: Option<String>,  // "100MB"-> min_size
-    #[arg(long)] pub smaller: Option<String>, // "64KB" -> max_size
-}
-
-/// Return a typed iterator over matches for the given `Filter`.
-pub fn find_with_filter(root: &str, filter: Filter) -> FindIter<Filter> {
-    FindIter::new(root, filter)
-}
-
-/// Collect all matching paths into a Vec for convenience.
-pub fn find_collect(root: &str, filter: Filter) -> Vec<PathBuf> {
-    find_with_filter(root, filter).collect()
-}
-
-/// A generic helper that accepts any Matcher (not just Filter).
-pub fn find_with_matcher<M: Matcher>(root: &str, matcher: M) -> FindIter<M> {
-    FindIter::new(root, matcher)
-}
-
-/// Execute `find` with CLI arguments.
-pub fn run(args: FindArgs) {
-    // Build base filter from presets and pattern.
-    let mut f = Filter::default();
-    apply_presets(&mut f, &args);
-
-    // Merge DSL if provided.
-    if let Some(dsl) = &args.select {
-        let dsl_filter = parse_select_dsl(dsl);
-        f = merge(f, dsl_filter);
-    }
-
-    // Stream results (print one per line).
-    for p in find_with_filter(&args.root, f) {
-        println!("{}", p.display());
-    }
-}
-
-/// Apply preset flags, human sizes/ages and simple pattern heuristic.
-fn apply_presets(f: &mut Filter, args: &FindArgs) {
-    // Extensions by preset flags
-    if args.images { f.exts.extend(["jpg","jpeg","png","gif","webp"].map(String::from)); }
-    if args.videos { f.exts.extend(["mp4","mov","mkv","avi"].map(String::from)); }
-    if args.docs   { f.exts.extend(["pdf","docx","txt","md"].map(String::from)); }
-
-    // Age presets
-    if let Some(s) = &args.older   { if let Some(v) = parse_human_age(s)  { f.min_age_secs = Some(v); } }
-    if let Some(s) = &args.newer   { if let Some(v) = parse_human_age(s)  { f.max_age_secs = Some(v); } }
-
-    // Size presets
-    if let Some(s) = &args.larger  { if let Some(v) = parse_human_size(s) { f.min_size = Some(v); } }
-    if let Some(s) = &args.smaller { if let Some(v) = parse_human_size(s) { f.max_size = Some(v); } }
-
-    // Simple pattern heuristic:
-    // - If it looks like a glob, try to derive suffix by final extension.
-    // - If it starts with '.', treat as suffix (e.g., ".log")
-    // - Otherwise, treat as substring `contains`.
-    if let Some(p) = &args.pattern {
-        if p.contains('*') || p.contains('?') || p.contains('[') {
-            if let Some((_, suf)) = p.rsplit_once('.') {
-                f.suffix.push(format!(".{}", suf));
-            }
-        } else if p.starts_with('.') {
-            f.suffix.push(p.clone());
-        } else {
-            f.contains.push(p.clone());
-        }
-    }
-
-    // Normalize extensions to lowercase without leading dot.
-    if !f.exts.is_empty() {
-        for e in &mut f.exts {
-            *e = e.trim_start_matches('.').to_ascii_lowercase();
-        }
-    }
-}
-
-/// Parse a tiny DSL into a Filter.
-/// Supported tokens:
-/// - `contains:<s>` / `name:<s>`
-/// - `prefix:<s>` / `suffix:<s>` / `ext:<e>`
-/// - `type:file|dir`
-/// - `hidden:true|false|1|0`
-/// - `age>1d` / `age<2h`
-/// - `size>10MB` / `size<64KB`
-fn parse_select_dsl(dsl: &str) -> Filter {
-    let mut f = Filter::default();
-    for tok in dsl.split_whitespace() {
-        if let Some((k, v)) = tok.split_once(':').or(tok.split_once('=')) {
-            match k {
-                "contains" | "name" => f.contains.push(v.to_string()),
-                "prefix" => f.prefix.push(v.to_string()),
-                "suffix" => f.suffix.push(v.to_string()),
-                "ext" => f.exts.push(v.trim_start_matches('.').to_ascii_lowercase()),
-                "type" => {
-                    f.ty_file = v == "file";
-                    f.ty_dir  = v == "dir";
-                }
-                "hidden" => {
-                    f.include_hidden = matches!(v, "1" | "true" | "yes" | "on");
-                }
-                _ => {}
-            }
-        } else if tok.starts_with("age>") {
-            f.min_age_secs = parse_human_age(&tok[4..]);
-        } else if tok.starts_with("age<") {
-            f.max_age_secs = parse_human_age(&tok[4..]);
-        } else if tok.starts_with("size>") {
-            f.min_size = parse_human_size(&tok[5..]);
-        } else if tok.starts_with("size<") {
-            f.max_size = parse_human_size(&tok[5..]);
-        }
-    }
-    f
-}
+use clap::{Args, ValueEnum};
+use regex::Regex;
+use std::io;
+use std::path::PathBuf;
+use tracing::error;
+
+use super::output::{OutputArgs, Printer};
+
+use super::fsutil::content::ContentSearch;
+use super::fsutil::filter::{build_regex, is_glob, merge, Filter, GlobPattern, Matcher};
+use super::fsutil::select::{self, ParseError};
+use super::fsutil::iter::{FindIter, WalkError, WalkOptions};
+use super::fsutil::order::{Order, Ranking, SortKey};
+use super::fsutil::pathinfo::PathInfo;
+use super::fsutil::retention::{GroupBy, Policy, Retention};
+use super::fsutil::util::{parse_human_age, parse_human_size};
+
+#[derive(Args, Debug, Clone)]
+pub struct FindArgs {
+    #[arg(short, long, default_value = ".")]
+    pub root: String,
+
+    /// Glob (`*.csv`, `src/**/*.rs`), `.suffix`, or name substring
+    pub pattern: Option<String>,
+
+    /// DSL (e.g. `(ext:jpg or ext:png) and not contains:"thumb" age>2d size<10MB name~/^v\d+/i hidden:true`)
+    #[arg(long)]
+    pub select: Option<String>,
+
+    /// Regex matched against the file name
+    #[arg(long, value_name = "RE")]
+    pub regex: Vec<String>,
+
+    /// Regex matched against the path relative to the root
+    #[arg(long, value_name = "RE")]
+    pub path_regex: Vec<String>,
+
+    /// Make `--regex`/`--path-regex`/`--content` case-insensitive
+    #[arg(short = 'i', long)]
+    pub ignore_case: bool,
+
+    /// Only files containing this text on some line (checked after all other tests)
+    #[arg(long, value_name = "TEXT")]
+    pub content: Option<String>,
+
+    /// Treat `--content` as a regex
+    #[arg(long, requires = "content")]
+    pub content_regex: bool,
+
+    /// Read at most this much of each file when searching contents
+    #[arg(long, value_name = "SIZE", default_value = "16MB")]
+    pub max_scan: String,
+
+    /// Also search contents of binary files (a NUL byte in the first 8KB)
+    #[arg(long)]
+    pub binary: bool,
+
+    /// Skip entries matching this glob; matching directories are not descended into
+    #[arg(long, value_name = "GLOB")]
+    pub exclude: Vec<String>,
+
+    /// Never descend into directories with this name (e.g. `node_modules`)
+    #[arg(long, value_name = "DIRNAME")]
+    pub prune: Vec<String>,
+
+    /// Skip files ignored by `.gitignore`, `.ignore`, `.git/info/exclude` and git's global excludes
+    #[arg(long, overrides_with = "no_ignore")]
+    pub respect_ignore: bool,
+
+    /// Do not apply ignore files (overrides `--respect-ignore`, e.g. for rules targeting build artifacts)
+    #[arg(long, overrides_with = "respect_ignore")]
+    pub no_ignore: bool,
+
+    /// Only report entries at least N levels below the root (the root is 0)
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub min_depth: usize,
+
+    /// Do not descend more than N levels below the root
+    #[arg(long, value_name = "N")]
+    pub max_depth: Option<usize>,
+
+    /// Follow symlinks to directories; loops are reported and not descended
+    #[arg(long)]
+    pub follow_symlinks: bool,
+
+    /// Stay on the filesystem of the root (do not cross mount points)
+    #[arg(long)]
+    pub one_file_system: bool,
+
+    /// Walk with N threads (0 = one per CPU); output order is then arbitrary
+    #[arg(long, value_name = "N")]
+    pub threads: Option<usize>,
+
+    /// Print results in path order (buffers them when walking in parallel)
+    #[arg(long)]
+    pub sorted: bool,
+
+    /// Exit with status 1 if any path could not be examined (unreadable, vanished, symlink loop)
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Sort results by name, size, mtime, path or ext (buffers all results unless limited)
+    #[arg(long, value_name = "KEY")]
+    pub sort: Option<SortKey>,
+
+    /// Reverse the sort order (`--sort mtime --reverse` is newest first)
+    #[arg(long, requires = "sort")]
+    pub reverse: bool,
+
+    /// Stop after N results
+    #[arg(long, value_name = "N")]
+    pub limit: Option<usize>,
+
+    /// Drop the first N results (`--sort mtime --reverse --skip 5`: all but the 5 newest)
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub skip: usize,
+
+    /// Only the N largest files, largest first
+    #[arg(long, value_name = "N", group = "top", conflicts_with_all = ["sort", "limit"])]
+    pub largest: Option<usize>,
+
+    /// Only the N smallest files, smallest first
+    #[arg(long, value_name = "N", group = "top", conflicts_with_all = ["sort", "limit"])]
+    pub smallest: Option<usize>,
+
+    /// Only the N most recently modified entries, newest first
+    #[arg(long, value_name = "N", group = "top", conflicts_with_all = ["sort", "limit"])]
+    pub newest: Option<usize>,
+
+    /// Only the N least recently modified entries, oldest first
+    #[arg(long, value_name = "N", group = "top", conflicts_with_all = ["sort", "limit"])]
+    pub oldest: Option<usize>,
+
+    /// Retention: keep the N newest matches of each group and select the rest
+    #[arg(long, value_name = "N")]
+    pub keep_last: Option<usize>,
+
+    /// Retention: keep the newest match of each of the N most recent local calendar days that have one
+    #[arg(long, value_name = "N")]
+    pub keep_daily: Option<usize>,
+
+    /// Retention: likewise per ISO week
+    #[arg(long, value_name = "N")]
+    pub keep_weekly: Option<usize>,
+
+    /// Retention: likewise per calendar month
+    #[arg(long, value_name = "N")]
+    pub keep_monthly: Option<usize>,
+
+    /// Retention: likewise per calendar year
+    #[arg(long, value_name = "N")]
+    pub keep_yearly: Option<usize>,
+
+    /// Apply `--keep-*` per parent directory, or per directory and name prefix (up to the first digit)
+    #[arg(long, value_enum, default_value_t = GroupArg::Parent)]
+    pub group_by: GroupArg,
+
+    /// Apply `--keep-*` per value of this regex's first capture group on the file name
+    /// (names that do not match are kept)
+    #[arg(long, value_name = "RE")]
+    pub group_regex: Option<String>,
+
+    // preset / shortcut
+    #[arg(long)] pub images: bool,
+    #[arg(long)] pub videos: bool,
+    #[arg(long)] pub docs: bool,
+
+    #[arg(long)] pub older: Option<String>,   // "30d"  -> min_age_secs
+    #[arg(long)] pub newer: Option<String>,   // "2h"   -> max_age_secs
+    #[arg(long)] pub larger: Option<String>,  // "100MB"-> min_size
+    #[arg(long)] pub smaller: Option<String>, // "64KB" -> max_size
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupArg {
+    Parent,
+    Prefix,
+}
+
+/// `find` itself: what to look for plus how to print it.
+#[derive(Args, Debug, Clone)]
+pub struct FindCommand {
+    #[command(flatten)]
+    pub find: FindArgs,
+
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+/// Return a typed iterator over matches for the given `Filter`.
+pub fn find_with_filter(root: &str, filter: Filter) -> FindIter<Filter> {
+    FindIter::new(root, filter)
+}
+
+/// Like `find_with_filter`, with traversal settings; walks in parallel when
+/// `opts.threads` is set.
+pub fn find_with_options(root: &str, filter: Filter, opts: WalkOptions) -> FindIter<Filter> {
+    if opts.threads.is_some() {
+        FindIter::parallel(root, filter, opts)
+    } else {
+        FindIter::with_options(root, filter, opts)
+    }
+}
+
+/// Traversal settings from CLI arguments.
+pub fn walk_options(args: &FindArgs) -> WalkOptions {
+    WalkOptions {
+        respect_ignore: args.respect_ignore && !args.no_ignore,
+        min_depth: args.min_depth,
+        max_depth: args.max_depth,
+        follow_symlinks: args.follow_symlinks,
+        one_file_system: args.one_file_system,
+        threads: args.threads,
+        sorted: args.sorted,
+    }
+}
+
+/// Ordering and top-N selection from CLI arguments.
+pub fn ranking(args: &FindArgs) -> Ranking {
+    let top = [
+        (args.largest, SortKey::Size, true),
+        (args.smallest, SortKey::Size, false),
+        (args.newest, SortKey::Mtime, true),
+        (args.oldest, SortKey::Mtime, false),
+    ];
+    if let Some((n, key, reverse)) = top.into_iter().find_map(|(n, key, reverse)| Some((n?, key, reverse))) {
+        return Ranking { order: Some(Order { key, reverse }), skip: args.skip, limit: Some(n) };
+    }
+    Ranking { order: args.sort.map(|key| Order { key, reverse: args.reverse }), skip: args.skip, limit: args.limit }
+}
+
+/// The `--keep-*` retention policy, if any keep count was given.
+pub fn retention(args: &FindArgs) -> Option<Retention> {
+    let keeps = [args.keep_last, args.keep_daily, args.keep_weekly, args.keep_monthly, args.keep_yearly];
+    if keeps.iter().all(Option::is_none) {
+        return None;
+    }
+    let [last, daily, weekly, monthly, yearly] = keeps.map(|k| k.unwrap_or(0));
+    let group_by = match (&args.group_regex, args.group_by) {
+        (Some(re), _) => GroupBy::Capture(regex_or_exit(re, args.ignore_case)),
+        (None, GroupArg::Parent) => GroupBy::Parent,
+        (None, GroupArg::Prefix) => GroupBy::Prefix,
+    };
+    Some(Retention { policy: Policy { last, daily, weekly, monthly, yearly }, group_by })
+}
+
+/// Walk results after retention and ranking: what `find` prints and `flow` acts on.
+pub fn selected<'a, M: Matcher + 'a>(
+    it: &'a mut FindIter<M>,
+    args: &FindArgs,
+) -> impl Iterator<Item = Result<PathInfo, WalkError>> + 'a {
+    let found: Box<dyn Iterator<Item = Result<PathInfo, WalkError>> + 'a> = match retention(args) {
+        Some(r) => Box::new(r.apply(it.results())),
+        None => Box::new(it.results()),
+    };
+    ranking(args).apply(found)
+}
+
+/// Log an entry the walk could not examine.
+pub fn log_skipped(e: &WalkError, action: &str) {
+    error!(target: "file-rs", action, path=%e.path.display(), error=%e.kind, "Skipped path");
+}
+
+/// Log how many entries a finished walk skipped and return the count.
+pub fn summarize_skipped<M: Matcher>(it: &FindIter<M>, action: &str) -> usize {
+    let skipped = it.skipped().len();
+    if skipped > 0 {
+        error!(target: "file-rs", action, skipped, "Some paths could not be examined");
+    }
+    skipped
+}
+
+/// Collect all matching paths into a Vec for convenience.
+pub fn find_collect(root: &str, filter: Filter) -> Vec<PathBuf> {
+    find_with_filter(root, filter).collect()
+}
+
+/// A generic helper that accepts any Matcher (not just Filter).
+///
+/// ```no_run
+/// use file_rs::cmd::find::find_with_matcher;
+/// use file_rs::fsutil::filter::{Filter, Matcher};
+/// use file_rs::fsutil::pathinfo::PathInfo;
+///
+/// let logs = Filter { exts: vec!["log".into()], ..Filter::default() };
+/// let big = |i: &PathInfo| i.size.is_some_and(|s| s > 1 << 20);
+/// for p in find_with_matcher(".", logs.and(big).or(|i: &PathInfo| i.file_name == "core")) {
+///     println!("{}", p.display());
+/// }
+/// ```
+pub fn find_with_matcher<M: Matcher>(root: &str, matcher: M) -> FindIter<M> {
+    FindIter::new(root, matcher)
+}
+
+/// Build the `Filter` described by presets, pattern and `--select` DSL.
+pub fn build_filter(args: &FindArgs) -> Filter {
+    // Build base filter from presets and pattern.
+    let mut f = Filter { root: Some(PathBuf::from(&args.root)), ..Filter::default() };
+    apply_presets(&mut f, args);
+
+    // Merge DSL if provided.
+    if let Some(dsl) = &args.select {
+        let dsl_filter = select_or_exit(dsl);
+        f = merge(f, dsl_filter);
+    }
+    let (max_bytes, binary) = scan_limits(args);
+    f.set_scan_limits(max_bytes, binary);
+    f
+}
+
+/// `--max-scan` and `--binary`.
+fn scan_limits(args: &FindArgs) -> (u64, bool) {
+    let max_bytes = parse_human_size(&args.max_scan).unwrap_or_else(|| {
+        error!(target: "file-rs", action="find", max_scan=%args.max_scan, "Invalid size");
+        std::process::exit(2);
+    });
+    (max_bytes, args.binary)
+}
+
+/// The `--content` search with its scan limits.
+fn content_search(args: &FindArgs) -> Option<ContentSearch> {
+    let text = args.content.as_ref()?;
+    let mut c = ContentSearch::new(text, !args.content_regex, args.ignore_case).unwrap_or_else(|e| {
+        error!(target: "file-rs", action="find", regex=%text, error=%e, "Invalid regex");
+        std::process::exit(2);
+    });
+    (c.max_bytes, c.binary) = scan_limits(args);
+    Some(c)
+}
+
+/// Execute `find` with CLI arguments.
+pub fn run(cmd: FindCommand) {
+    let args = &cmd.find;
+    let mut printer = Printer::new(io::stdout().lock(), &cmd.output).unwrap_or_else(|e| {
+        error!(target: "file-rs", action="find", error=%e, "Invalid --format template");
+        std::process::exit(2);
+    });
+    let f = build_filter(args);
+    let lines_of = cmd.output.line_numbers.then(|| content_search(args)).flatten();
+
+    let mut it = find_with_options(&args.root, f, walk_options(args));
+    let mut out = Ok(());
+    for res in selected(&mut it, args) {
+        match res {
+            Ok(info) => {
+                let lines = lines_of.as_ref().map(|c| c.matching_lines(&info.path).unwrap_or_default());
+                out = printer.write(&info, lines.as_deref());
+            }
+            Err(e) => log_skipped(&e, "find"),
+        }
+        if out.is_err() {
+            break;
+        }
+    }
+    let out = out.and_then(|()| printer.finish());
+    match out {
+        // The reader went away (e.g. `| head`); nothing left to report to.
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return,
+        Err(e) => {
+            error!(target: "file-rs", action="find", error=%e, "Failed to write results");
+            std::process::exit(1);
+        }
+        Ok(()) => {}
+    }
+    if summarize_skipped(&it, "find") > 0 && args.strict {
+        std::process::exit(1);
+    }
+}
+
+/// Apply preset flags, human sizes/ages and the positional pattern.
+fn apply_presets(f: &mut Filter, args: &FindArgs) {
+    // Extensions by preset flags
+    if args.images { f.exts.extend(["jpg","jpeg","png","gif","webp"].map(String::from)); }
+    if args.videos { f.exts.extend(["mp4","mov","mkv","avi"].map(String::from)); }
+    if args.docs   { f.exts.extend(["pdf","docx","txt","md"].map(String::from)); }
+
+    // Age presets
+    if let Some(s) = &args.older   { if let Some(v) = parse_human_age(s)  { f.min_age_secs = Some(v); } }
+    if let Some(s) = &args.newer   { if let Some(v) = parse_human_age(s)  { f.max_age_secs = Some(v); } }
+
+    // Size presets
+    if let Some(s) = &args.larger  { if let Some(v) = parse_human_size(s) { f.min_size = Some(v); } }
+    if let Some(s) = &args.smaller { if let Some(v) = parse_human_size(s) { f.max_size = Some(v); } }
+
+    // Pattern:
+    // - If it looks like a glob, compile it (`/` makes it match the relative path).
+    // - If it starts with '.', treat as suffix (e.g., ".log")
+    // - Otherwise, treat as substring `contains`.
+    if let Some(p) = &args.pattern {
+        if is_glob(p) {
+            match GlobPattern::new(p) {
+                Ok(g) => f.globs.push(g),
+                Err(e) => {
+                    error!(target: "file-rs", action="find", pattern=%p, error=%e, "Invalid glob");
+                    std::process::exit(2);
+                }
+            }
+        } else if p.starts_with('.') {
+            f.suffix.push(p.clone());
+        } else {
+            f.contains.push(p.clone());
+        }
+    }
+
+    for g in &args.exclude {
+        match GlobPattern::new(g) {
+            Ok(g) => f.exclude.push(g),
+            Err(e) => {
+                error!(target: "file-rs", action="find", exclude=%g, error=%e, "Invalid glob");
+                std::process::exit(2);
+            }
+        }
+    }
+    f.prune.extend(args.prune.iter().cloned());
+
+    for re in &args.regex {
+        f.name_regex.push(regex_or_exit(re, args.ignore_case));
+    }
+    for re in &args.path_regex {
+        f.path_regex.push(regex_or_exit(re, args.ignore_case));
+    }
+    f.content.extend(content_search(args));
+
+    // Normalize extensions to lowercase without leading dot.
+    if !f.exts.is_empty() {
+        for e in &mut f.exts {
+            *e = e.trim_start_matches('.').to_ascii_lowercase();
+        }
+    }
+}
+
+fn regex_or_exit(pattern: &str, ignore_case: bool) -> Regex {
+    build_regex(pattern, ignore_case).unwrap_or_else(|e| {
+        error!(target: "file-rs", action="find", regex=%pattern, error=%e, "Invalid regex");
+        std::process::exit(2);
+    })
+}
+
+/// Parse a `--select` expression (see `fsutil::select::parse`) into a Filter.
+pub fn parse_select_dsl(dsl: &str) -> Result<Filter, ParseError> {
+    let sel = select::parse(dsl)?;
+    Ok(Filter {
+        expr: sel.expr,
+        include_hidden: sel.include_hidden,
+        exclude: sel.exclude,
+        prune: sel.prune,
+        ..Filter::default()
+    })
+}
+
+/// `parse_select_dsl`, reporting errors with a caret under the column and exiting.
+pub fn select_or_exit(dsl: &str) -> Filter {
+    parse_select_dsl(dsl).unwrap_or_else(|e| {
+        error!(target: "file-rs", action="select", column=e.col, "Invalid --select expression: {}\n{}", e.msg, caret(dsl, e.col));
+        std::process::exit(2);
+    })
+}
+
+/// `dsl` and a caret under its 1-based column `col`, on two indented lines.
+fn caret(dsl: &str, col: usize) -> String {
+    format!("  {}\n  {}^", dsl, " ".repeat(col.saturating_sub(1)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsutil::pathinfo::PathInfo;
+    use std::path::Path;
+
+    #[test]
+    fn select_merges_with_presets() {
+        use clap::Parser;
+
+        #[derive(Parser)]
+        struct Cli {
+            #[command(flatten)]
+            find: FindArgs,
+        }
+
+        let select = r"(name~/^db-\d+:full/i or ext:sql) hidden:true";
+        let args = Cli::try_parse_from(["x", "-r", "/r", "--select", select, "--larger", "1KB"]).unwrap().find;
+        let f = build_filter(&args);
+        assert!(f.include_hidden);
+        let now = std::time::SystemTime::now();
+        let info = |p: &str, size| PathInfo::from_parts(Path::new(p), true, false, Some(size), Some(now), now);
+        assert!(f.matches(&info("/r/DB-42:full.tar", 4096)));
+        assert!(f.matches(&info("/r/.x.sql", 4096)));
+        assert!(!f.matches(&info("/r/db-42:full.tar", 10)));
+        assert!(!f.matches(&info("/r/db-x:full.tar", 4096)));
+
+        assert_eq!(parse_select_dsl("ext:txt nope").err().map(|e| e.col), Some(9));
+        assert_eq!(caret("ext:txt nope", 9), "  ext:txt nope\n          ^");
+    }
+}
@@ -0,0 +1,260 @@
+use clap::{Args, ValueEnum};
+use std::collections::{BTreeSet, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use tracing::{error, info};
+
+use super::find::{find_with_filter, select_or_exit};
+use super::fsutil::file::move_one;
+use super::fsutil::filter::Filter;
+use super::fsutil::journal::{Fingerprint, Journal, JournalOp};
+use super::mv::{resolve_conflict_with, OnConflict};
+use super::plan::RunMode;
+use super::undo;
+
+#[derive(Args, Debug)]
+pub struct FlattenArgs {
+    /// Directory to flatten
+    pub dir: String,
+
+    /// Move files here instead of the top level of `dir`
+    #[arg(long)]
+    pub to: Option<String>,
+
+    /// Only flatten files matching this DSL (same syntax as `find --select`)
+    #[arg(long)]
+    pub select: Option<String>,
+
+    /// How to name files whose name is already taken
+    #[arg(long, value_enum, default_value_t = Naming::Suffix)]
+    pub naming: Naming,
+
+    /// Separator used by `--naming path`
+    #[arg(long, default_value = "__")]
+    pub separator: String,
+
+    /// Remove directories left empty afterwards
+    #[arg(long)]
+    pub remove_empty: bool,
+
+    #[command(flatten)]
+    pub mode: RunMode,
+}
+
+/// Naming scheme for flattened files.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Naming {
+    /// Keep the name, add ` (1)`, ` (2)`... on collision
+    Suffix,
+    /// Encode the relative path into the name: `a/b/c.jpg` -> `a__b__c.jpg`
+    Path,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct FlattenSummary {
+    pub moved: u64,
+    pub failures: u64,
+    pub removed_dirs: u64,
+    /// Directories files were moved out of; candidates for `--remove-empty`.
+    pub vacated: BTreeSet<PathBuf>,
+}
+
+pub fn run(args: FlattenArgs) {
+    let mut filter = args.select.as_deref().map(select_or_exit).unwrap_or_default();
+    filter.ty_file = true;
+    filter.ty_dir = false;
+
+    let dir = PathBuf::from(&args.dir);
+    filter.root = Some(dir.clone());
+    let to = args.to.as_ref().map(PathBuf::from).unwrap_or_else(|| dir.clone());
+
+    let dry_run = args.mode.dry_run();
+    let journal = undo::open_journal(dry_run);
+    let sum = flatten_dir(&dir, &to, filter, args.naming, &args.separator, dry_run, journal.as_ref());
+    let sum = if args.remove_empty && !dry_run {
+        FlattenSummary { removed_dirs: remove_empty_dirs(&dir, &sum.vacated), ..sum }
+    } else {
+        sum
+    };
+
+    info!(target: "file-rs", action="flatten", dir=%dir.display(), dry_run,
+        moved=sum.moved, removed_dirs=sum.removed_dirs, failures=sum.failures, "Flatten done");
+    if sum.failures > 0 {
+        std::process::exit(1);
+    }
+}
+
+/// Move every nested file under `dir` matching `filter` into `to`, journaling
+/// each move when `journal` is given.
+pub fn flatten_dir(
+    dir: &Path,
+    to: &Path,
+    filter: Filter,
+    naming: Naming,
+    sep: &str,
+    dry_run: bool,
+    journal: Option<&Journal>,
+) -> FlattenSummary {
+    let mut sum = FlattenSummary::default();
+
+    // Collect first: moving into the tree being walked would revisit files.
+    let candidates: Vec<PathBuf> = find_with_filter(&dir.to_string_lossy(), filter)
+        .filter(|p| p.parent() != Some(to))
+        .collect();
+
+    // Names handed out so far; in a dry run nothing is moved to claim them.
+    let mut reserved = HashSet::new();
+    for src in candidates {
+        match flatten_one(dir, to, &src, naming, sep, dry_run, &mut reserved) {
+            Ok(dst) => {
+                sum.moved += 1;
+                if let Some(parent) = src.parent() {
+                    sum.vacated.insert(parent.to_path_buf());
+                }
+                info!(target: "file-rs", action="flatten", dry_run, src=%src.display(), dst=%dst.display(),
+                    "{}", if dry_run { "Would move" } else { "Moved" });
+                if !dry_run && !undo::record(journal, JournalOp::Mv, &src, Some(&dst), Fingerprint::stat(&dst)) {
+                    sum.failures += 1;
+                }
+            }
+            Err(e) => {
+                sum.failures += 1;
+                error!(target: "file-rs", action="flatten", src=%src.display(), error=%e, "Failed to move");
+            }
+        }
+    }
+    sum
+}
+
+fn flatten_one(
+    dir: &Path,
+    to: &Path,
+    src: &Path,
+    naming: Naming,
+    sep: &str,
+    dry_run: bool,
+    reserved: &mut HashSet<PathBuf>,
+) -> io::Result<PathBuf> {
+    let name = match naming {
+        Naming::Suffix => src
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .ok_or_else(|| io::Error::other("source has no file name"))?,
+        Naming::Path => encoded_name(dir, src, sep),
+    };
+    let taken = |p: &Path| reserved.contains(p) || fs::symlink_metadata(p).is_ok();
+    let dst = resolve_conflict_with(&to.join(name), OnConflict::Rename, taken)?
+        .expect("rename policy always yields a destination");
+    reserved.insert(dst.clone());
+    if !dry_run {
+        fs::create_dir_all(to)?;
+        move_one(src, &dst)?;
+    }
+    Ok(dst)
+}
+
+/// `dir/a/b/c.jpg` -> `a<sep>b<sep>c.jpg`.
+fn encoded_name(dir: &Path, src: &Path, sep: &str) -> String {
+    let rel = src.strip_prefix(dir).unwrap_or(src);
+    rel.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(sep)
+}
+
+/// Remove the `vacated` directories and their ancestors below `dir` (never
+/// `dir` itself) once they are empty, deepest first. Nothing else is
+/// touched: directories that were empty before, or that the walk skipped
+/// (hidden, pruned), stay.
+pub fn remove_empty_dirs(dir: &Path, vacated: &BTreeSet<PathBuf>) -> u64 {
+    let mut candidates = BTreeSet::new();
+    for d in vacated {
+        candidates.extend(d.ancestors().take_while(|a| *a != dir && a.starts_with(dir)).map(Path::to_path_buf));
+    }
+    let mut candidates: Vec<PathBuf> = candidates.into_iter().collect();
+    candidates.sort_by_key(|d| std::cmp::Reverse(d.components().count()));
+    candidates.iter().filter(|d| fs::remove_dir(d).is_ok()).count() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::find::parse_select_dsl;
+    use tempfile::tempdir;
+
+    fn tree() -> tempfile::TempDir {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a/b")).unwrap();
+        fs::create_dir_all(dir.path().join("c")).unwrap();
+        fs::write(dir.path().join("top.jpg"), b"t").unwrap();
+        fs::write(dir.path().join("a/x.jpg"), b"1").unwrap();
+        fs::write(dir.path().join("a/b/x.jpg"), b"2").unwrap();
+        fs::write(dir.path().join("c/note.txt"), b"n").unwrap();
+        dir
+    }
+
+    fn file_only(select: &str) -> Filter {
+        Filter { ty_file: true, ..parse_select_dsl(select).unwrap() }
+    }
+
+    #[test]
+    fn suffix_naming_and_cleanup() {
+        let dir = tree();
+        let root = dir.path();
+        fs::create_dir_all(root.join("a/was_empty")).unwrap();
+        fs::create_dir_all(root.join("empty")).unwrap();
+        fs::create_dir_all(root.join(".git/refs/tags")).unwrap();
+
+        let sum = flatten_dir(root, root, file_only("ext:jpg"), Naming::Suffix, "__", false, None);
+        assert_eq!(sum.moved, 2);
+        assert_eq!(sum.failures, 0);
+        assert!(root.join("x.jpg").exists());
+        assert!(root.join("x (1).jpg").exists());
+        assert!(root.join("c/note.txt").exists());
+
+        assert_eq!(remove_empty_dirs(root, &sum.vacated), 1);
+        assert!(!root.join("a/b").exists());
+        // `a` still holds a directory that was empty before.
+        assert!(root.join("a/was_empty").exists());
+        assert!(root.join("empty").exists());
+        assert!(root.join(".git/refs/tags").exists());
+        assert!(root.join("c").exists());
+
+        fs::remove_dir(root.join("a/was_empty")).unwrap();
+        assert_eq!(remove_empty_dirs(root, &sum.vacated), 1);
+        assert!(!root.join("a").exists());
+    }
+
+    #[test]
+    fn path_naming_into_target_dir() {
+        let dir = tree();
+        let root = dir.path();
+        let out = root.join("out");
+
+        let sum = flatten_dir(root, &out, file_only(""), Naming::Path, "__", false, None);
+        assert_eq!(sum.moved, 4);
+        assert_eq!(fs::read(out.join("a__x.jpg")).unwrap(), b"1");
+        assert_eq!(fs::read(out.join("a__b__x.jpg")).unwrap(), b"2");
+        assert!(out.join("c__note.txt").exists());
+        assert!(out.join("top.jpg").exists());
+    }
+
+    #[test]
+    fn dry_run_moves_nothing() {
+        let dir = tree();
+        let root = dir.path();
+
+        let sum = flatten_dir(root, root, file_only(""), Naming::Suffix, "__", true, None);
+        assert_eq!(sum.moved, 3);
+        assert_eq!(sum.vacated.len(), 3);
+        assert!(root.join("a/b/x.jpg").exists());
+        assert!(!root.join("x.jpg").exists());
+
+        // Reported targets do not collide although nothing was moved.
+        let mut reserved = HashSet::new();
+        let first = flatten_one(root, root, &root.join("a/x.jpg"), Naming::Suffix, "__", true, &mut reserved).unwrap();
+        let second = flatten_one(root, root, &root.join("a/b/x.jpg"), Naming::Suffix, "__", true, &mut reserved).unwrap();
+        assert_eq!((first, second), (root.join("x.jpg"), root.join("x (1).jpg")));
+    }
+}
@@ -1,125 +1,388 @@
-use clap::Args;
-use std::str::FromStr;
-
-use super::{find, mv, compress, rm};
-
-
-#[derive(Args, Debug)]
-pub struct FlowArgs {
-    /// Find phase options
-    #[command(flatten)]
-    pub find: find::FindArgs,
-
-    /// Actions to apply in order, e.g.:
-    ///   mv:to=dst
-    ///   compress:dst=archive.tar.zst
-    ///   copy:to=backup/
-    ///   rm:trash=true
-    ///
-    /// --action 'mv:to=dst' --action 'rm:trash=true'
-    #[arg(long = "action")]
-    pub actions: Vec<ActionSpec>,
-
-    /// Dry-run
-    #[arg(long, default_value_t = true)]
-    pub dry_run: bool,
-}
-#[derive(Debug, Clone)]
-pub enum ActionSpec {
-    Mv { to: String },
-    Copy { to: String },
-    Compress { dst: String },
-    Rm { trash: bool },
-}
-
-impl FromStr for ActionSpec {
-    type Err = String;
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        // kind:key=value[,key=value...]
-        // "mv:to=dst", "rm:trash=true", "compress:dst=out.tar.zst"
-        let (kind, rest) = s.split_once(':').ok_or("expected kind:kv syntax")?;
-        let mut to: Option<String> = None;
-        let mut dst: Option<String> = None;
-        let mut trash: Option<bool> = None;
-
-        for kv in rest.split(',') {
-            let (k, v) = kv.split_once('=').ok_or("expected key=value")?;
-            match (kind, k) {
-                ("mv", "to") => to = Some(v.to_string()),
-                ("copy", "to") => to = Some(v.to_string()),
-                ("compress", "dst") => dst = Some(v.to_string()),
-                ("rm", "trash") => {
-                    trash = Some(match v {
-                        "1" | "true" | "yes" => true,
-                        "0" | "false" | "no" => false,
-                        _ => return Err("trash expects true/false".into()),
-                    })
-                }
-                _ => return Err(format!("unsupported key '{}' for kind '{}'", k, kind)),
-            }
-        }
-
-        match kind {
-            "mv" => Ok(ActionSpec::Mv { to: to.ok_or("mv requires to=<path>")? }),
-            "copy" => Ok(ActionSpec::Copy { to: to.ok_or("copy requires to=<path>")? }),
-            "compress" => Ok(ActionSpec::Compress { dst: dst.ok_or("compress requires dst=<file>")? }),
-            "rm" => Ok(ActionSpec::Rm { trash: trash.unwrap_or(true) }),
-            _ => Err(format!("unknown action kind: {}", kind)),
-        }
-    }
-}
-
-pub fn run(args: FlowArgs) {
-    println!("[flow] find root={} (dry-run={})", args.find.root, args.dry_run);
-
-    let demo_files = vec![
-        "a.jpg".to_string(),
-        "b.jpg".to_string(),
-        "nested/c.jpg".to_string(),
-    ];
-
-    for f in demo_files {
-        let mut current = f.clone();
-        for act in &args.actions {
-            match act {
-                ActionSpec::Mv { to } => {
-                    let dst = format!("{}/{}", to, file_name(&current));
-                    if args.dry_run {
-                        println!("[flow] [dry-run] mv {} -> {}", current, dst);
-                    } else {
-                        println!("[flow] mv {} -> {}", current, dst);
-                    }
-                    current = dst;
-                }
-                ActionSpec::Copy { to } => {
-                    let dst = format!("{}/{}", to, file_name(&current));
-                    if args.dry_run {
-                        println!("[flow] [dry-run] cp {} -> {}", current, dst);
-                    } else {
-                        println!("[flow] cp {} -> {}", current, dst);
-                    }
-                }
-                ActionSpec::Compress { dst } => {
-                    if args.dry_run {
-                        println!("[flow] [dry-run] compress add {} -> {}", current, dst);
-                    } else {
-                        println!("[flow] compress add {} -> {}", current, dst);
-                    }
-                }
-                ActionSpec::Rm { trash } => {
-                    if args.dry_run {
-                        println!("[flow] [dry-run] rm {} (trash={})", current, trash);
-                    } else {
-                        println!("[flow] rm {} (trash={})", current, trash);
-                    }
-                }
-            }
-        }
-    }
-
-    println!("[flow] done.");
-}
-
-fn file_name(p: &str) -> &str {
-    p.rsplit_once('/').map(|(_, base)| base).unwrap_or(p)
-}
\ No newline at end of file
+use clap::{Args, ValueEnum};
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tracing::{error, info};
+
+use super::fsutil::archive::{entry_name, ArchiveFormat};
+use super::fsutil::file::CopyOptions;
+use super::mv::{self, OnConflict};
+use super::plan::{self, Executor, Op, PlannedOp, RunMode};
+use super::undo;
+use super::find;
+
+#[derive(Args, Debug)]
+pub struct FlowArgs {
+    /// Find phase options
+    #[command(flatten)]
+    pub find: find::FindArgs,
+
+    /// Actions to apply in order, e.g.:
+    ///   mv:to=dst
+    ///   mv:to=dst,on_conflict=rename
+    ///   compress:dst=archive.tar.zst
+    ///   compress:dst=archive.zip,level=9
+    ///   copy:to=backup/
+    ///   rm:trash=true
+    ///
+    /// --action 'mv:to=dst' --action 'rm:trash=true'
+    #[arg(long = "action")]
+    pub actions: Vec<ActionSpec>,
+
+    #[command(flatten)]
+    pub mode: RunMode,
+
+    /// Write the planned operations to this JSON file instead of running them
+    #[arg(long, value_name = "FILE")]
+    pub plan: Option<String>,
+}
+#[derive(Debug, Clone)]
+pub enum ActionSpec {
+    Mv { to: String, on_conflict: OnConflict },
+    Copy { to: String },
+    Compress { dst: String, level: Option<i64> },
+    Rm { trash: bool },
+}
+
+impl FromStr for ActionSpec {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // kind:key=value[,key=value...]
+        // "mv:to=dst", "rm:trash=true", "compress:dst=out.tar.zst"
+        let (kind, rest) = s.split_once(':').ok_or("expected kind:kv syntax")?;
+        let mut to: Option<String> = None;
+        let mut dst: Option<String> = None;
+        let mut trash: Option<bool> = None;
+        let mut on_conflict = OnConflict::default();
+        let mut level: Option<i64> = None;
+
+        for kv in rest.split(',') {
+            let (k, v) = kv.split_once('=').ok_or("expected key=value")?;
+            match (kind, k) {
+                ("mv", "to") => to = Some(v.to_string()),
+                ("mv", "on_conflict") => on_conflict = OnConflict::from_str(v, true)?,
+                ("copy", "to") => to = Some(v.to_string()),
+                ("compress", "dst") => dst = Some(v.to_string()),
+                ("compress", "level") => {
+                    level = Some(v.parse().map_err(|_| "level expects an integer")?)
+                }
+                ("rm", "trash") => {
+                    trash = Some(match v {
+                        "1" | "true" | "yes" => true,
+                        "0" | "false" | "no" => false,
+                        _ => return Err("trash expects true/false".into()),
+                    })
+                }
+                _ => return Err(format!("unsupported key '{}' for kind '{}'", k, kind)),
+            }
+        }
+
+        match kind {
+            "mv" => Ok(ActionSpec::Mv { to: to.ok_or("mv requires to=<path>")?, on_conflict }),
+            "copy" => Ok(ActionSpec::Copy { to: to.ok_or("copy requires to=<path>")? }),
+            "compress" => {
+                let dst = dst.ok_or("compress requires dst=<file>")?;
+                if ArchiveFormat::from_path(Path::new(&dst)).is_none() {
+                    return Err(format!("unknown archive extension: {}", dst));
+                }
+                Ok(ActionSpec::Compress { dst, level })
+            }
+            "rm" => Ok(ActionSpec::Rm { trash: trash.unwrap_or(true) }),
+            _ => Err(format!("unknown action kind: {}", kind)),
+        }
+    }
+}
+
+pub fn run(args: FlowArgs) {
+    let dry_run = args.mode.dry_run();
+    info!(target: "file-rs", action="flow", root=%args.find.root, dry_run, "Starting flow");
+
+    let mut state = FlowState::new(&args.find.root);
+    if args.plan.is_none() {
+        if let Some(journal) = undo::open_journal(dry_run) {
+            state.exec = Executor::journaled(journal);
+        }
+    }
+    let mut planned = Vec::new();
+    let mut failures = 0usize;
+
+    // Collect first: actions must not change the tree while it is walked.
+    let (subjects, skipped) = subjects(&args.find);
+    if args.find.strict {
+        failures += skipped;
+    }
+
+    for p in subjects {
+        let res = if args.plan.is_some() {
+            plan_actions(&p, &args.actions, &mut state).map(|ops| planned.extend(ops))
+        } else {
+            apply_actions(&p, &args.actions, dry_run, &mut state)
+        };
+        if let Err(e) = res {
+            failures += 1;
+            error!(target: "file-rs", action="flow", path=%p.display(), error=%e, "Action failed");
+        }
+    }
+
+    if let Some(plan) = &args.plan {
+        plan::write_or_exit(plan, planned, failures);
+        return;
+    }
+
+    for (dst, entries, res) in state.exec.finish() {
+        match res {
+            Ok(()) => info!(target: "file-rs", action="compress", dst=%dst.display(), entries, "Archive written"),
+            Err(e) => {
+                failures += 1;
+                error!(target: "file-rs", action="compress", dst=%dst.display(), error=%e, "Failed to finish archive");
+            }
+        }
+    }
+
+    info!(target: "file-rs", action="flow", failures, "Flow done");
+    if failures > 0 {
+        std::process::exit(1);
+    }
+}
+
+/// Files selected by the find phase, and how many entries the walk skipped.
+/// Only files are acted on: never the root, and never a whole directory.
+fn subjects(args: &find::FindArgs) -> (Vec<PathBuf>, usize) {
+    let mut filter = find::build_filter(args);
+    filter.ty_file = true;
+    filter.ty_dir = false;
+    let mut opts = find::walk_options(args);
+    opts.min_depth = opts.min_depth.max(1);
+
+    let mut found = find::find_with_options(&args.root, filter, opts);
+    let mut paths = Vec::new();
+    for res in find::selected(&mut found, args) {
+        match res {
+            Ok(info) => paths.push(info.path),
+            Err(e) => find::log_skipped(&e, "flow"),
+        }
+    }
+    (paths, find::summarize_skipped(&found, "flow"))
+}
+
+/// State shared across all matched paths of one run.
+struct FlowState {
+    /// Archive entries are stored relative to the find root.
+    base: PathBuf,
+    /// Destinations handed out so far, so planned names never collide.
+    reserved: HashSet<PathBuf>,
+    /// Keeps one open archive per `compress:dst=...` until the end of the run.
+    exec: Executor,
+}
+
+impl FlowState {
+    fn new(root: &str) -> Self {
+        Self { base: PathBuf::from(root), reserved: HashSet::new(), exec: Executor::default() }
+    }
+
+    fn taken(&self, p: &Path) -> bool {
+        self.reserved.contains(p) || fs::symlink_metadata(p).is_ok()
+    }
+}
+
+/// Plan and then run (or, in dry-run, report) every action for one matched path.
+fn apply_actions(src: &Path, actions: &[ActionSpec], dry_run: bool, state: &mut FlowState) -> io::Result<()> {
+    for planned in plan_actions(src, actions, state)? {
+        let op = &planned.op;
+        if dry_run {
+            info!(target: "file-rs", action=op.kind(), dry_run=true, op=%op, "Would run");
+        } else {
+            state.exec.execute(op)?;
+            info!(target: "file-rs", action=op.kind(), dry_run=false, op=%op, "Done");
+        }
+    }
+    Ok(())
+}
+
+/// Resolve the actions for one matched path into concrete operations.
+/// `Mv` changes the path seen by later actions; the others leave it as is.
+fn plan_actions(src: &Path, actions: &[ActionSpec], state: &mut FlowState) -> io::Result<Vec<PlannedOp>> {
+    let mut current = src.to_path_buf();
+    let mut ops = Vec::new();
+    for act in actions {
+        let op = match act {
+            ActionSpec::Mv { to, on_conflict } => {
+                let wanted = Path::new(to).join(file_name(&current)?);
+                let Some(dst) = mv::resolve_conflict_with(&wanted, *on_conflict, |p| state.taken(p))? else {
+                    info!(target: "file-rs", action="mv", src=%current.display(), dst=%wanted.display(), "Skipped (destination exists)");
+                    continue;
+                };
+                let overwrite = *on_conflict == OnConflict::Overwrite;
+                Op::Mv { src: current.clone(), dst, overwrite }
+            }
+            ActionSpec::Copy { to } => {
+                let wanted = Path::new(to).join(file_name(&current)?);
+                let dst = mv::resolve_conflict_with(&wanted, OnConflict::Fail, |p| state.taken(p))?
+                    .expect("fail policy never skips");
+                Op::Copy { src: current.clone(), dst, overwrite: false, preserve: CopyOptions::default() }
+            }
+            ActionSpec::Compress { dst, level } => Op::Compress {
+                src: current.clone(),
+                dst: PathBuf::from(dst),
+                name: entry_name(&current, &state.base)?,
+                level: *level,
+            },
+            ActionSpec::Rm { trash: true } => Op::Trash { path: current.clone() },
+            ActionSpec::Rm { trash: false } => Op::Rm { path: current.clone(), recursive: false },
+        };
+
+        let planned = if current.as_path() == src {
+            PlannedOp::checked(op)?
+        } else {
+            PlannedOp::unchecked(op)
+        };
+        if let Op::Mv { dst, .. } | Op::Copy { dst, .. } = &planned.op {
+            state.reserved.insert(dst.clone());
+        }
+        if let Op::Mv { dst, .. } = &planned.op {
+            current = dst.clone();
+        }
+        ops.push(planned);
+    }
+    Ok(ops)
+}
+
+fn file_name(p: &Path) -> io::Result<&std::ffi::OsStr> {
+    p.file_name().ok_or_else(|| io::Error::other("path has no file name"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn dry_run_leaves_files_in_place() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("a.jpg");
+        fs::write(&src, b"x").unwrap();
+        let to = dir.path().join("out");
+
+        let actions = vec![ActionSpec::Mv { to: to.to_string_lossy().into_owned(), on_conflict: OnConflict::Fail }];
+        apply_actions(&src, &actions, true, &mut FlowState::new(".")).unwrap();
+
+        assert!(src.exists());
+        assert!(!to.exists());
+    }
+
+    #[test]
+    fn copy_then_move_then_rm() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("a.jpg");
+        fs::write(&src, b"x").unwrap();
+        let backup = dir.path().join("backup");
+        let moved = dir.path().join("moved");
+
+        let actions = vec![
+            ActionSpec::Copy { to: backup.to_string_lossy().into_owned() },
+            ActionSpec::Mv { to: moved.to_string_lossy().into_owned(), on_conflict: OnConflict::Fail },
+            ActionSpec::Rm { trash: false },
+        ];
+        apply_actions(&src, &actions, false, &mut FlowState::new(".")).unwrap();
+
+        assert!(!src.exists());
+        assert!(backup.join("a.jpg").exists());
+        assert!(moved.is_dir());
+        assert!(!moved.join("a.jpg").exists());
+    }
+
+    #[test]
+    fn compress_collects_all_matches_into_one_archive() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("root");
+        fs::create_dir_all(root.join("nested")).unwrap();
+        fs::write(root.join("a.jpg"), b"a").unwrap();
+        fs::write(root.join("nested/c.jpg"), b"c").unwrap();
+        let dst = dir.path().join("out.tar");
+
+        let actions = vec![ActionSpec::Compress { dst: dst.to_string_lossy().into_owned(), level: None }];
+        let mut state = FlowState::new(&root.to_string_lossy());
+        apply_actions(&root.join("a.jpg"), &actions, false, &mut state).unwrap();
+        apply_actions(&root.join("nested/c.jpg"), &actions, false, &mut state).unwrap();
+        for (_, _, res) in state.exec.finish() {
+            res.unwrap();
+        }
+
+        let mut ar = tar::Archive::new(fs::File::open(&dst).unwrap());
+        let names: Vec<String> = ar
+            .entries()
+            .unwrap()
+            .map(|e| e.unwrap().path().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, vec!["a.jpg", "nested/c.jpg"]);
+    }
+
+    #[test]
+    fn plan_resolves_chains_without_touching_files() {
+        let dir = tempdir().unwrap();
+        let a = dir.path().join("x/a.jpg");
+        let b = dir.path().join("y/a.jpg");
+        fs::create_dir_all(a.parent().unwrap()).unwrap();
+        fs::create_dir_all(b.parent().unwrap()).unwrap();
+        fs::write(&a, b"a").unwrap();
+        fs::write(&b, b"b").unwrap();
+        let out = dir.path().join("out");
+
+        let actions = vec![
+            ActionSpec::Mv { to: out.to_string_lossy().into_owned(), on_conflict: OnConflict::Rename },
+            ActionSpec::Rm { trash: false },
+        ];
+        let mut state = FlowState::new(".");
+        let first = plan_actions(&a, &actions, &mut state).unwrap();
+        let second = plan_actions(&b, &actions, &mut state).unwrap();
+
+        assert_eq!(first[0].op, Op::Mv { src: a.clone(), dst: out.join("a.jpg"), overwrite: false });
+        assert!(first[0].expect.is_some());
+        assert_eq!(first[1].op, Op::Rm { path: out.join("a.jpg"), recursive: false });
+        assert!(first[1].expect.is_none());
+        // The second file sees the first one's reserved name.
+        assert_eq!(second[0].op, Op::Mv { src: b.clone(), dst: out.join("a (1).jpg"), overwrite: false });
+        assert!(a.exists() && b.exists() && !out.exists());
+    }
+
+    #[test]
+    fn subjects_are_files_below_the_root() {
+        use clap::Parser;
+
+        #[derive(Parser)]
+        struct Cli {
+            #[command(flatten)]
+            flow: FlowArgs,
+        }
+
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src/nested")).unwrap();
+        fs::write(dir.path().join("src/a.rs"), b"a").unwrap();
+        fs::write(dir.path().join("src/nested/b.rs"), b"b").unwrap();
+
+        let root = dir.path().to_string_lossy().into_owned();
+        let cli = Cli::try_parse_from(["x", "-r", &root, "--sort", "path", "--action", "rm:trash=false"]).unwrap();
+        let (paths, skipped) = subjects(&cli.flow.find);
+        assert_eq!(paths, [dir.path().join("src/a.rs"), dir.path().join("src/nested/b.rs")]);
+        assert_eq!(skipped, 0);
+    }
+
+    #[test]
+    fn parse_action_specs() {
+        assert!(matches!("mv:to=dst".parse::<ActionSpec>(), Ok(ActionSpec::Mv { to, .. }) if to == "dst"));
+        assert!(matches!(
+            "mv:to=dst,on_conflict=rename".parse::<ActionSpec>(),
+            Ok(ActionSpec::Mv { on_conflict: OnConflict::Rename, .. })
+        ));
+        assert!(matches!("rm:trash=false".parse::<ActionSpec>(), Ok(ActionSpec::Rm { trash: false })));
+        assert!("rm:trash=maybe".parse::<ActionSpec>().is_err());
+        assert!("zip:dst=x".parse::<ActionSpec>().is_err());
+        assert!(matches!(
+            "compress:dst=out.tar.zst,level=19".parse::<ActionSpec>(),
+            Ok(ActionSpec::Compress { level: Some(19), .. })
+        ));
+        assert!("compress:dst=out.rar".parse::<ActionSpec>().is_err());
+    }
+}
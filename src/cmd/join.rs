@@ -0,0 +1,199 @@
+use clap::Args;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use tracing::{error, info};
+
+use super::fsutil::hash::hex;
+use super::split::{Manifest, MANIFEST_VERSION};
+
+#[derive(Args, Debug)]
+pub struct JoinArgs {
+    /// Manifest written by `split`
+    pub manifest: String,
+
+    /// Output path (default: original file name next to the manifest)
+    #[arg(short, long)]
+    pub out: Option<String>,
+}
+
+pub fn run(args: JoinArgs) {
+    let manifest = PathBuf::from(&args.manifest);
+    match join_file(&manifest, args.out.as_deref().map(Path::new)) {
+        Ok(out) => {
+            info!(target: "file-rs", action="join", manifest=%manifest.display(), out=%out.display(), "Joined and verified");
+        }
+        Err(e) => {
+            error!(target: "file-rs", action="join", manifest=%manifest.display(), error=%e, "Failed to join");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Reassemble the file described by `manifest_path`, verifying every chunk and
+/// the whole-file checksum. Data goes to `<out>.partial` first and is only
+/// renamed into place once everything matches.
+pub fn join_file(manifest_path: &Path, out: Option<&Path>) -> io::Result<PathBuf> {
+    let manifest: Manifest = serde_json::from_slice(&fs::read(manifest_path)?)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    if manifest.version != MANIFEST_VERSION {
+        return Err(invalid(format!("unsupported manifest version {}", manifest.version)));
+    }
+
+    check_plain_name(&manifest.file)?;
+    for chunk in &manifest.chunks {
+        check_plain_name(&chunk.name)?;
+    }
+
+    let dir = manifest_path.parent().unwrap_or(Path::new(""));
+    let out = match out {
+        Some(o) => o.to_path_buf(),
+        None => dir.join(&manifest.file),
+    };
+    if out.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("output exists: {}", out.display()),
+        ));
+    }
+
+    let mut partial_name = out.file_name().unwrap_or_default().to_os_string();
+    partial_name.push(".partial");
+    let partial = out.with_file_name(partial_name);
+
+    write_chunks(&manifest, dir, &partial)?;
+    fs::rename(&partial, &out)?;
+    Ok(out)
+}
+
+/// Write the verified chunks to `partial`, which must not exist yet. The
+/// partial file is removed again on failure, but only once we created it.
+fn write_chunks(manifest: &Manifest, dir: &Path, partial: &Path) -> io::Result<()> {
+    let mut dst = OpenOptions::new().write(true).create_new(true).open(partial)?;
+    let res = copy_chunks(manifest, dir, &mut dst);
+    if res.is_err() {
+        let _ = fs::remove_file(partial);
+    }
+    res
+}
+
+fn copy_chunks(manifest: &Manifest, dir: &Path, dst: &mut File) -> io::Result<()> {
+    let mut whole = Sha256::new();
+    let mut total = 0u64;
+    let mut buf = vec![0u8; 64 * 1024];
+
+    for chunk in &manifest.chunks {
+        let mut src = BufReader::new(File::open(dir.join(&chunk.name))?);
+        let mut hasher = Sha256::new();
+        let mut size = 0u64;
+        loop {
+            let n = src.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            dst.write_all(&buf[..n])?;
+            hasher.update(&buf[..n]);
+            whole.update(&buf[..n]);
+            size += n as u64;
+        }
+        if size != chunk.size || hex(&hasher.finalize()) != chunk.sha256 {
+            return Err(invalid(format!("chunk {} is corrupt or truncated", chunk.name)));
+        }
+        total += size;
+    }
+
+    if total != manifest.size || hex(&whole.finalize()) != manifest.sha256 {
+        return Err(invalid("reassembled file does not match manifest checksum".to_string()));
+    }
+    dst.sync_all()
+}
+
+/// Manifest names must be a single normal component, so nothing is read or
+/// written outside the manifest's directory.
+fn check_plain_name(name: &str) -> io::Result<()> {
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) => Ok(()),
+        _ => Err(invalid(format!("not a plain file name in manifest: {name}"))),
+    }
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::split::{split_file, Layout};
+    use tempfile::tempdir;
+
+    #[test]
+    fn roundtrip_split_join() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("data.bin");
+        let data: Vec<u8> = (0..5000u32).map(|i| (i % 251) as u8).collect();
+        fs::write(&file, &data).unwrap();
+        let chunks = dir.path().join("chunks");
+
+        let (mpath, _) = split_file(&file, &chunks, Layout::Size(1024)).unwrap();
+        let out = join_file(&mpath, None).unwrap();
+        assert_eq!(out, chunks.join("data.bin"));
+        assert_eq!(fs::read(out).unwrap(), data);
+    }
+
+    #[test]
+    fn detects_corrupt_chunk() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("data.bin");
+        fs::write(&file, vec![1u8; 3000]).unwrap();
+        let chunks = dir.path().join("chunks");
+        let (mpath, _) = split_file(&file, &chunks, Layout::Size(1000)).unwrap();
+        fs::write(chunks.join("data.bin.002"), vec![2u8; 1000]).unwrap();
+
+        let err = join_file(&mpath, None).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(!chunks.join("data.bin").exists());
+        assert!(!chunks.join("data.bin.partial").exists());
+    }
+
+    #[test]
+    fn rejects_names_outside_the_manifest_dir() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("data.bin");
+        fs::write(&file, vec![1u8; 3000]).unwrap();
+        let chunks = dir.path().join("chunks");
+        let (mpath, _) = split_file(&file, &chunks, Layout::Size(1000)).unwrap();
+        let original: Manifest = serde_json::from_slice(&fs::read(&mpath).unwrap()).unwrap();
+
+        for bad in ["../escaped.bin", "..", "/tmp/escaped.bin", "sub/data.bin", ""] {
+            let mut m = original.clone();
+            m.file = bad.to_string();
+            fs::write(&mpath, serde_json::to_vec(&m).unwrap()).unwrap();
+            let err = join_file(&mpath, None).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{bad}");
+
+            let mut m = original.clone();
+            m.chunks[1].name = bad.to_string();
+            fs::write(&mpath, serde_json::to_vec(&m).unwrap()).unwrap();
+            let err = join_file(&mpath, None).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{bad}");
+        }
+        assert!(!dir.path().join("escaped.bin").exists());
+    }
+
+    #[test]
+    fn keeps_a_foreign_partial_file() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("data.bin");
+        fs::write(&file, vec![1u8; 3000]).unwrap();
+        let chunks = dir.path().join("chunks");
+        let (mpath, _) = split_file(&file, &chunks, Layout::Size(1000)).unwrap();
+        fs::write(chunks.join("data.bin.partial"), b"another join").unwrap();
+
+        let err = join_file(&mpath, None).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read(chunks.join("data.bin.partial")).unwrap(), b"another join");
+    }
+}
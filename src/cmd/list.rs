@@ -0,0 +1,59 @@
+use clap::Args;
+use std::path::PathBuf;
+use std::time::SystemTime;
+use tracing::error;
+
+use super::extract::member_info;
+use super::find::select_or_exit;
+use super::fsutil::archive::{for_each_entry, EntryKind};
+use super::fsutil::filter::Matcher;
+
+#[derive(Args, Debug)]
+pub struct ListArgs {
+    /// Archive to list (.tar, .tar.gz, .tgz, .tar.zst, .zip)
+    pub archive: String,
+
+    /// Only list members matching this DSL (same syntax as `find --select`)
+    #[arg(long)]
+    pub select: Option<String>,
+
+    /// Show type and size next to each member
+    #[arg(short, long)]
+    pub long: bool,
+}
+
+pub fn run(args: ListArgs) {
+    let archive = PathBuf::from(&args.archive);
+    let filter = args.select.as_deref().map(select_or_exit);
+    let now = SystemTime::now();
+
+    let res = for_each_entry(&archive, |meta, _| {
+        if let Some(f) = &filter {
+            if !f.matches(&member_info(&meta.name, meta, now)) {
+                return Ok(());
+            }
+        }
+        if args.long {
+            let ty = match meta.kind {
+                EntryKind::File => 'f',
+                EntryKind::Dir => 'd',
+                EntryKind::Symlink => 'l',
+                EntryKind::Other => '?',
+            };
+            match &meta.link_target {
+                Some(t) if meta.kind == EntryKind::Symlink => {
+                    println!("{} {:>12} {} -> {}", ty, meta.size, meta.name.display(), t.display())
+                }
+                _ => println!("{} {:>12} {}", ty, meta.size, meta.name.display()),
+            }
+        } else {
+            println!("{}", meta.name.display());
+        }
+        Ok(())
+    });
+
+    if let Err(e) = res {
+        error!(target: "file-rs", action="list", archive=%archive.display(), error=%e, "Failed to read archive");
+        std::process::exit(1);
+    }
+}
@@ -1,36 +1,69 @@
-use clap::{Parser, Subcommand};
-use super::fsutil;
-
-pub mod find;
-pub mod rm;
-pub mod compress;
-pub mod mv;
-pub mod flow;
-
-#[derive(Parser, Debug)]
-#[command(name = "file-rs")]
-#[command(version, about = "A fast and safe file organizer and cleaner built with Rust.")]
-pub struct Cli {
-    #[command(subcommand)]
-    pub command: Commands,
-}
-
-#[derive(Subcommand, Debug)]
-pub enum Commands {
-    Compress(compress::CompressArgs),
-    Find(find::FindArgs),
-    Rm(rm::RmArgs),
-    #[command(alias = "move")]
-    Mv(mv::MoveArgs),
-    Flow(flow::FlowArgs)
-}
-
-pub fn run(cli: Cli) {
-    match cli.command {
-        Commands::Find(args) => find::run(args),
-        Commands::Rm(args) => rm::run(args),
-        Commands::Compress(args) => compress::run(args),
-        Commands::Mv(args) => mv::run(args),
-        Commands::Flow(args) => flow::run(args),
-    }
-}
+use clap::{Parser, Subcommand};
+use super::fsutil;
+
+pub mod find;
+pub mod rm;
+pub mod compress;
+pub mod mv;
+pub mod flow;
+pub mod copy;
+pub mod extract;
+pub mod list;
+pub mod split;
+pub mod join;
+pub mod flatten;
+pub mod trash;
+pub mod plan;
+pub mod apply;
+pub mod undo;
+pub mod output;
+pub mod dupes;
+
+#[derive(Parser, Debug)]
+#[command(name = "file-rs")]
+#[command(version, about = "A fast and safe file organizer and cleaner built with Rust.")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Commands,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Commands {
+    Compress(compress::CompressArgs),
+    Extract(extract::ExtractArgs),
+    List(list::ListArgs),
+    Find(find::FindCommand),
+    Rm(rm::RmArgs),
+    Trash(trash::TrashArgs),
+    #[command(alias = "move")]
+    Mv(mv::MoveArgs),
+    #[command(alias = "cp")]
+    Copy(copy::CopyArgs),
+    Split(split::SplitArgs),
+    Join(join::JoinArgs),
+    Flatten(flatten::FlattenArgs),
+    Flow(flow::FlowArgs),
+    Apply(apply::ApplyArgs),
+    Undo(undo::UndoArgs),
+    Dupes(dupes::DupesArgs),
+}
+
+pub fn run(cli: Cli) {
+    match cli.command {
+        Commands::Find(args) => find::run(args),
+        Commands::Rm(args) => rm::run(args),
+        Commands::Trash(args) => trash::run(args),
+        Commands::Compress(args) => compress::run(args),
+        Commands::Extract(args) => extract::run(args),
+        Commands::List(args) => list::run(args),
+        Commands::Mv(args) => mv::run(args),
+        Commands::Copy(args) => copy::run(args),
+        Commands::Split(args) => split::run(args),
+        Commands::Join(args) => join::run(args),
+        Commands::Flatten(args) => flatten::run(args),
+        Commands::Flow(args) => flow::run(args),
+        Commands::Apply(args) => apply::run(args),
+        Commands::Undo(args) => undo::run(args),
+        Commands::Dupes(args) => dupes::run(args),
+    }
+}
@@ -1,44 +1,300 @@
-use clap::Args;
-use std::io::{self, BufRead, Read};
-
-#[derive(Args, Debug)]
-pub struct MoveArgs {
-    /// Source path (if omitted, will read from stdin)
-    #[arg(short, long)]
-    pub src: Option<String>,
-
-    /// Destination path
-    pub dst: String,
-
-    /// Read NUL-delimited input from stdin (for find --print0 compatibility)
-    #[arg(long)]
-    pub stdin0: bool,
-}
-
-pub fn run(args: MoveArgs) {
-    let sources: Vec<String> = if let Some(src) = args.src {
-        vec![src]
-    } else {
-        if args.stdin0 {
-            let mut buf = Vec::new();
-            io::stdin().read_to_end(&mut buf).expect("stdin read failed");
-            buf.split(|b| *b == 0)
-                .filter(|s| !s.is_empty())
-                .map(|s| String::from_utf8_lossy(s).into_owned())
-                .collect()
-        } else {
-            let stdin = io::stdin();
-            stdin.lock().lines()
-                .filter_map(Result::ok)
-                .collect()
-        }
-    };
-
-    for s in sources {
-        println!("[mv] {} -> {}/{}", s, args.dst, file_name(&s));
-    }
-}
-
-fn file_name(path: &str) -> &str {
-    path.rsplit_once('/').map(|(_, base)| base).unwrap_or(path)
-}
\ No newline at end of file
+use clap::{Args, ValueEnum};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use tracing::{error, info};
+
+use super::fsutil::file::move_one;
+use super::fsutil::journal::{Fingerprint, JournalOp};
+use super::fsutil::input::{iter_stdin_lines, iter_stdin_nul};
+use super::plan::{self, Op, PlannedOp, RunMode};
+use super::undo;
+
+#[derive(Args, Debug)]
+pub struct MoveArgs {
+    /// Source path (if omitted, will read from stdin)
+    #[arg(short, long)]
+    pub src: Option<String>,
+
+    /// Destination directory (created if missing)
+    pub dst: String,
+
+    /// Read NUL-delimited input from stdin (for find --print0 compatibility)
+    #[arg(long)]
+    pub stdin0: bool,
+
+    /// What to do when the destination already exists
+    #[arg(long, value_enum, default_value_t = OnConflict::Fail)]
+    pub on_conflict: OnConflict,
+
+    #[command(flatten)]
+    pub mode: RunMode,
+
+    /// Write the planned moves to this JSON file instead of running them
+    #[arg(long, value_name = "FILE")]
+    pub plan: Option<String>,
+}
+
+/// Policy applied when the destination name is already taken.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OnConflict {
+    /// Leave the source where it is
+    Skip,
+    /// Replace the existing destination
+    Overwrite,
+    /// Pick a free name like `name (1).ext`
+    Rename,
+    /// Report an error
+    #[default]
+    Fail,
+}
+
+/// Result of a single move request.
+#[derive(Debug, PartialEq, Eq)]
+pub enum MoveOutcome {
+    Moved(PathBuf),
+    WouldMove(PathBuf),
+    Skipped(PathBuf),
+}
+
+pub fn run(args: MoveArgs) {
+    let sources: Vec<String> = if let Some(src) = args.src {
+        vec![src]
+    } else if args.stdin0 {
+        iter_stdin_nul().collect()
+    } else {
+        match iter_stdin_lines().collect() {
+            Ok(lines) => lines,
+            Err(e) => {
+                error!(target: "file-rs", action="mv", error=%e, "Failed to read paths from stdin");
+                std::process::exit(1);
+            }
+        }
+    };
+
+    let dst_dir = PathBuf::from(&args.dst);
+    let mut failures = 0usize;
+
+    if let Some(plan) = &args.plan {
+        let mut ops = Vec::new();
+        for s in sources {
+            let p = PathBuf::from(&s);
+            match plan_mv(&p, &dst_dir, args.on_conflict) {
+                Ok(Some(op)) => ops.push(op),
+                Ok(None) => {
+                    info!(target: "file-rs", action="mv", src=%p.display(), "Skipped (destination exists)");
+                }
+                Err(e) => {
+                    failures += 1;
+                    error!(target: "file-rs", action="mv", src=%p.display(), error=%e, "Failed to plan move");
+                }
+            }
+        }
+        plan::write_or_exit(plan, ops, failures);
+        return;
+    }
+
+    let dry_run = args.mode.dry_run();
+    let journal = undo::open_journal(dry_run);
+    for s in sources {
+        let p = PathBuf::from(&s);
+        match mv_path(&p, &dst_dir, args.on_conflict, dry_run) {
+            Ok(MoveOutcome::WouldMove(dst)) => {
+                info!(target: "file-rs", action="mv", dry_run=true, src=%p.display(), dst=%dst.display(), "Would move");
+            }
+            Ok(MoveOutcome::Moved(dst)) => {
+                info!(target: "file-rs", action="mv", dry_run=false, src=%p.display(), dst=%dst.display(), "Moved");
+                if !undo::record(journal.as_ref(), JournalOp::Mv, &p, Some(&dst), Fingerprint::stat(&dst)) {
+                    failures += 1;
+                }
+            }
+            Ok(MoveOutcome::Skipped(dst)) => {
+                info!(target: "file-rs", action="mv", src=%p.display(), dst=%dst.display(), "Skipped (destination exists)");
+            }
+            Err(e) => {
+                failures += 1;
+                error!(target: "file-rs", action="mv", src=%p.display(), error=%e, "Failed to move");
+            }
+        }
+    }
+
+    if failures > 0 {
+        std::process::exit(1);
+    }
+}
+
+/// Move `src` into directory `dst_dir`, keeping its file name and applying
+/// `on_conflict` when that name is already taken.
+pub fn mv_path(src: &Path, dst_dir: &Path, on_conflict: OnConflict, dry_run: bool) -> io::Result<MoveOutcome> {
+    let name = src
+        .file_name()
+        .ok_or_else(|| io::Error::other("source has no file name"))?;
+    let dst = match resolve_conflict(&dst_dir.join(name), on_conflict)? {
+        Some(dst) => dst,
+        None => return Ok(MoveOutcome::Skipped(dst_dir.join(name))),
+    };
+
+    if dry_run {
+        fs::symlink_metadata(src)?;
+        return Ok(MoveOutcome::WouldMove(dst));
+    }
+
+    fs::create_dir_all(dst_dir)?;
+    if on_conflict == OnConflict::Overwrite {
+        if let Ok(meta) = fs::symlink_metadata(&dst) {
+            if meta.is_dir() && !fs::symlink_metadata(src)?.is_dir() {
+                return Err(io::Error::other("refusing to overwrite a directory with a file"));
+            }
+        }
+    }
+    move_one(src, &dst)?;
+    Ok(MoveOutcome::Moved(dst))
+}
+
+/// Resolve the move of `src` into `dst_dir` into a plan entry. `None` means skip.
+pub fn plan_mv(src: &Path, dst_dir: &Path, on_conflict: OnConflict) -> io::Result<Option<PlannedOp>> {
+    let name = src
+        .file_name()
+        .ok_or_else(|| io::Error::other("source has no file name"))?;
+    let Some(dst) = resolve_conflict(&dst_dir.join(name), on_conflict)? else {
+        return Ok(None);
+    };
+    let overwrite = on_conflict == OnConflict::Overwrite;
+    PlannedOp::checked(Op::Mv { src: src.to_path_buf(), dst, overwrite }).map(Some)
+}
+
+/// Apply `policy` to a wanted destination. `None` means skip.
+pub fn resolve_conflict(dst: &Path, policy: OnConflict) -> io::Result<Option<PathBuf>> {
+    resolve_conflict_with(dst, policy, |p| fs::symlink_metadata(p).is_ok())
+}
+
+/// Like `resolve_conflict`, with a custom notion of which names are taken.
+pub fn resolve_conflict_with<F>(dst: &Path, policy: OnConflict, taken: F) -> io::Result<Option<PathBuf>>
+where
+    F: Fn(&Path) -> bool,
+{
+    if !taken(dst) {
+        return Ok(Some(dst.to_path_buf()));
+    }
+    match policy {
+        OnConflict::Skip => Ok(None),
+        OnConflict::Overwrite => Ok(Some(dst.to_path_buf())),
+        OnConflict::Rename => Ok(Some(next_free_name(dst, taken))),
+        OnConflict::Fail => Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("destination exists: {}", dst.display()),
+        )),
+    }
+}
+
+/// First `name (N).ext` next to `path` that is not taken yet. A compressed
+/// extension keeps the one before it, so `a.tar.gz` becomes `a (1).tar.gz`.
+fn next_free_name<F: Fn(&Path) -> bool>(path: &Path, taken: F) -> PathBuf {
+    let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    let (stem, ext) = split_extension(&name);
+    (1..)
+        .map(|n| {
+            let name = match ext {
+                Some(ext) => format!("{} ({}).{}", stem, n, ext),
+                None => format!("{} ({})", stem, n),
+            };
+            path.with_file_name(name)
+        })
+        .find(|p| !taken(p))
+        .expect("unbounded counter")
+}
+
+/// Extensions that wrap another one, like the `gz` in `tar.gz`.
+const COMPRESSED_EXTS: &[&str] = &["gz", "bz2", "xz", "zst", "lz", "lz4", "lzma", "br", "z"];
+
+/// Split a file name into stem and extension, treating `x.tar.gz` style
+/// suffixes as one extension. A leading dot does not start an extension.
+fn split_extension(name: &str) -> (&str, Option<&str>) {
+    let last = match name.rfind('.') {
+        Some(i) if i > 0 => i,
+        _ => return (name, None),
+    };
+    let mut start = last;
+    if COMPRESSED_EXTS.contains(&name[last + 1..].to_ascii_lowercase().as_str()) {
+        if let Some(i) = name[..last].rfind('.').filter(|&i| i > 0) {
+            start = i;
+        }
+    }
+    (&name[..start], Some(&name[start + 1..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn moves_into_new_directory() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("a.txt");
+        fs::write(&src, b"a").unwrap();
+        let dst_dir = dir.path().join("nested/out");
+
+        let res = mv_path(&src, &dst_dir, OnConflict::Fail, false).unwrap();
+        assert_eq!(res, MoveOutcome::Moved(dst_dir.join("a.txt")));
+        assert!(!src.exists());
+        assert_eq!(fs::read(dst_dir.join("a.txt")).unwrap(), b"a");
+    }
+
+    #[test]
+    fn dry_run_does_not_move() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("a.txt");
+        fs::write(&src, b"a").unwrap();
+        let dst_dir = dir.path().join("out");
+
+        let res = mv_path(&src, &dst_dir, OnConflict::Fail, true).unwrap();
+        assert_eq!(res, MoveOutcome::WouldMove(dst_dir.join("a.txt")));
+        assert!(src.exists());
+        assert!(!dst_dir.exists());
+    }
+
+    #[test]
+    fn conflict_policies() {
+        let dir = tempdir().unwrap();
+        let dst_dir = dir.path().join("out");
+        fs::create_dir(&dst_dir).unwrap();
+        fs::write(dst_dir.join("a.txt"), b"old").unwrap();
+
+        let src = dir.path().join("a.txt");
+        fs::write(&src, b"new").unwrap();
+
+        let err = mv_path(&src, &dst_dir, OnConflict::Fail, false).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+
+        let res = mv_path(&src, &dst_dir, OnConflict::Skip, false).unwrap();
+        assert_eq!(res, MoveOutcome::Skipped(dst_dir.join("a.txt")));
+        assert!(src.exists());
+
+        let res = mv_path(&src, &dst_dir, OnConflict::Rename, false).unwrap();
+        assert_eq!(res, MoveOutcome::Moved(dst_dir.join("a (1).txt")));
+        assert_eq!(fs::read(dst_dir.join("a.txt")).unwrap(), b"old");
+
+        fs::write(&src, b"newer").unwrap();
+        let res = mv_path(&src, &dst_dir, OnConflict::Overwrite, false).unwrap();
+        assert_eq!(res, MoveOutcome::Moved(dst_dir.join("a.txt")));
+        assert_eq!(fs::read(dst_dir.join("a.txt")).unwrap(), b"newer");
+    }
+
+    #[test]
+    fn rename_skips_taken_suffixes() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("a.txt"), b"").unwrap();
+        fs::write(dir.path().join("a (1).txt"), b"").unwrap();
+        fs::write(dir.path().join("LICENSE"), b"").unwrap();
+
+        let exists = |p: &Path| p.exists();
+        assert_eq!(next_free_name(&dir.path().join("a.txt"), exists), dir.path().join("a (2).txt"));
+        assert_eq!(next_free_name(&dir.path().join("LICENSE"), exists), dir.path().join("LICENSE (1)"));
+        assert_eq!(next_free_name(&dir.path().join("a.tar.gz"), exists), dir.path().join("a (1).tar.gz"));
+        assert_eq!(next_free_name(&dir.path().join("app.log.ZST"), exists), dir.path().join("app (1).log.ZST"));
+        assert_eq!(next_free_name(&dir.path().join("x.y.txt"), exists), dir.path().join("x.y (1).txt"));
+        assert_eq!(next_free_name(&dir.path().join(".bashrc"), exists), dir.path().join(".bashrc (1)"));
+        assert_eq!(next_free_name(&dir.path().join(".cache.gz"), exists), dir.path().join(".cache (1).gz"));
+    }
+}
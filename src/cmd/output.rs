@@ -0,0 +1,413 @@
+use chrono::{DateTime, Local, SecondsFormat};
+use clap::Args;
+use serde::Serialize;
+use std::io::{self, Write};
+
+use super::fsutil::pathinfo::PathInfo;
+
+/// How `find` prints its results; one line per path by default.
+#[derive(Args, Debug, Clone, Default)]
+#[group(skip)]
+pub struct OutputArgs {
+    /// Terminate each path with NUL instead of a newline (for `mv --stdin0` and friends)
+    #[arg(long, group = "output")]
+    pub print0: bool,
+
+    /// Print one JSON array of {path, size, mtime, ext, type, hidden} objects
+    #[arg(long, group = "output")]
+    pub json: bool,
+
+    /// Print one JSON object per line
+    #[arg(long, group = "output")]
+    pub ndjson: bool,
+
+    /// Print CSV with a header row
+    #[arg(long, group = "output")]
+    pub csv: bool,
+
+    /// Print a line per result from a template, e.g. '{path}\t{size}\t{mtime}'
+    /// (fields: path, name, size, mtime, ext, type, hidden, lines; `{{`/`}}` for braces)
+    #[arg(long, value_name = "TEMPLATE", group = "output")]
+    pub format: Option<String>,
+
+    /// Add the numbers of the lines matching `--content` (`path:3,17`, a `lines` field or `{lines}`)
+    #[arg(long, requires = "content")]
+    pub line_numbers: bool,
+}
+
+const CSV_HEADER: &str = "path,size,mtime,ext,type,hidden";
+const CSV_FIELDS: [Field; 6] = [Field::Path, Field::Size, Field::Mtime, Field::Ext, Field::Type, Field::Hidden];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Path,
+    Name,
+    Size,
+    Mtime,
+    Ext,
+    Type,
+    Hidden,
+    Lines,
+}
+
+impl Field {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "path" => Self::Path,
+            "name" => Self::Name,
+            "size" => Self::Size,
+            "mtime" => Self::Mtime,
+            "ext" => Self::Ext,
+            "type" => Self::Type,
+            "hidden" => Self::Hidden,
+            "lines" => Self::Lines,
+            _ => return None,
+        })
+    }
+
+    /// Text form used by CSV and templates; missing values are empty.
+    fn text(self, info: &PathInfo, lines: Option<&[u64]>) -> String {
+        match self {
+            Self::Path => info.path.to_string_lossy().into_owned(),
+            Self::Name => info.path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default(),
+            Self::Size => info.size.map(|s| s.to_string()).unwrap_or_default(),
+            Self::Mtime => mtime(info).unwrap_or_default(),
+            Self::Ext => info.ext.clone().unwrap_or_default(),
+            Self::Type => kind(info).to_string(),
+            Self::Hidden => info.hidden.to_string(),
+            Self::Lines => join_lines(lines.unwrap_or_default(), ","),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+    Lit(String),
+    Field(Field),
+}
+
+/// Parse a `--format` template; `\t`, `\n`, `\0` and `\\` are unescaped.
+fn parse_template(src: &str) -> Result<Vec<Piece>, String> {
+    let mut out = Vec::new();
+    let mut lit = String::new();
+    let mut chars = src.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('t') => lit.push('\t'),
+                Some('n') => lit.push('\n'),
+                Some('0') => lit.push('\0'),
+                Some('\\') => lit.push('\\'),
+                Some(other) => {
+                    lit.push('\\');
+                    lit.push(other);
+                }
+                None => lit.push('\\'),
+            },
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                lit.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                lit.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => return Err(format!("unclosed `{{{name}`")),
+                    }
+                }
+                let field = Field::parse(&name).ok_or_else(|| format!("unknown field {{{name}}}"))?;
+                if !lit.is_empty() {
+                    out.push(Piece::Lit(std::mem::take(&mut lit)));
+                }
+                out.push(Piece::Field(field));
+            }
+            '}' => return Err("unmatched `}` (write `}}` for a literal brace)".into()),
+            c => lit.push(c),
+        }
+    }
+    if !lit.is_empty() {
+        out.push(Piece::Lit(lit));
+    }
+    Ok(out)
+}
+
+#[derive(Serialize)]
+struct Record<'a> {
+    path: String,
+    size: Option<u64>,
+    mtime: Option<String>,
+    ext: Option<&'a str>,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    hidden: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lines: Option<&'a [u64]>,
+}
+
+impl<'a> Record<'a> {
+    fn new(info: &'a PathInfo, lines: Option<&'a [u64]>) -> Self {
+        Self {
+            path: info.path.to_string_lossy().into_owned(),
+            size: info.size,
+            mtime: mtime(info),
+            ext: info.ext.as_deref(),
+            kind: kind(info),
+            hidden: info.hidden,
+            lines,
+        }
+    }
+}
+
+fn join_lines(lines: &[u64], sep: &str) -> String {
+    lines.iter().map(u64::to_string).collect::<Vec<_>>().join(sep)
+}
+
+fn kind(info: &PathInfo) -> &'static str {
+    if info.is_file {
+        "file"
+    } else if info.is_dir {
+        "dir"
+    } else {
+        "other"
+    }
+}
+
+fn mtime(info: &PathInfo) -> Option<String> {
+    info.mtime.map(|t| DateTime::<Local>::from(t).to_rfc3339_opts(SecondsFormat::Secs, false))
+}
+
+enum Format {
+    Lines,
+    Print0,
+    Json,
+    Ndjson,
+    Csv,
+    Template(Vec<Piece>),
+}
+
+/// Writes results to `out` in the format chosen by `OutputArgs`.
+pub struct Printer<W: Write> {
+    out: W,
+    format: Format,
+    count: usize,
+    /// CSV gets a `lines` column.
+    line_numbers: bool,
+}
+
+impl<W: Write> Printer<W> {
+    /// Fails only on an invalid `--format` template.
+    pub fn new(out: W, args: &OutputArgs) -> Result<Self, String> {
+        let format = if args.print0 {
+            Format::Print0
+        } else if args.json {
+            Format::Json
+        } else if args.ndjson {
+            Format::Ndjson
+        } else if args.csv {
+            Format::Csv
+        } else if let Some(t) = &args.format {
+            Format::Template(parse_template(t)?)
+        } else {
+            Format::Lines
+        };
+        Ok(Self { out, format, count: 0, line_numbers: args.line_numbers })
+    }
+
+    /// Write one result; `lines` are the matching line numbers, if wanted.
+    pub fn write(&mut self, info: &PathInfo, lines: Option<&[u64]>) -> io::Result<()> {
+        let first = self.count == 0;
+        self.count += 1;
+        match &self.format {
+            Format::Lines => {
+                self.out.write_all(info.path.as_os_str().as_encoded_bytes())?;
+                if let Some(lines) = lines {
+                    write!(self.out, ":{}", join_lines(lines, ","))?;
+                }
+                self.out.write_all(b"\n")
+            }
+            Format::Print0 => {
+                self.out.write_all(info.path.as_os_str().as_encoded_bytes())?;
+                self.out.write_all(b"\0")
+            }
+            Format::Json => {
+                self.out.write_all(if first { b"[\n  " } else { b",\n  " })?;
+                serde_json::to_writer(&mut self.out, &Record::new(info, lines)).map_err(io::Error::other)
+            }
+            Format::Ndjson => {
+                serde_json::to_writer(&mut self.out, &Record::new(info, lines)).map_err(io::Error::other)?;
+                self.out.write_all(b"\n")
+            }
+            Format::Csv => {
+                if first {
+                    self.csv_header()?;
+                }
+                let mut row: Vec<String> = CSV_FIELDS.iter().map(|f| csv_field(&f.text(info, lines))).collect();
+                if self.line_numbers {
+                    row.push(join_lines(lines.unwrap_or_default(), " "));
+                }
+                writeln!(self.out, "{}", row.join(","))
+            }
+            Format::Template(pieces) => {
+                for p in pieces {
+                    match p {
+                        Piece::Lit(s) => self.out.write_all(s.as_bytes())?,
+                        Piece::Field(f) => self.out.write_all(f.text(info, lines).as_bytes())?,
+                    }
+                }
+                self.out.write_all(b"\n")
+            }
+        }
+    }
+
+    /// Close the JSON array and flush; empty results still get `[]` or
+    /// the CSV header.
+    pub fn finish(mut self) -> io::Result<()> {
+        match self.format {
+            Format::Json => self.out.write_all(if self.count == 0 { b"[]\n" } else { b"\n]\n" })?,
+            Format::Csv if self.count == 0 => self.csv_header()?,
+            _ => {}
+        }
+        self.out.flush()
+    }
+
+    fn csv_header(&mut self) -> io::Result<()> {
+        let lines = if self.line_numbers { ",lines" } else { "" };
+        writeln!(self.out, "{CSV_HEADER}{lines}")
+    }
+}
+
+/// Quote a CSV field if it contains a separator, quote or line break.
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use std::time::{Duration, SystemTime};
+
+    fn render(args: OutputArgs, infos: &[PathInfo]) -> String {
+        let mut buf = Vec::new();
+        let mut p = Printer::new(&mut buf, &args).unwrap();
+        for i in infos {
+            p.write(i, None).unwrap();
+        }
+        p.finish().unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    fn infos() -> Vec<PathInfo> {
+        let now = SystemTime::now();
+        let then = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        vec![
+            PathInfo::from_parts(Path::new("/r/a,b.TXT"), true, false, Some(3), Some(then), now),
+            PathInfo::from_parts(Path::new("/r/.d\nir"), false, true, None, None, now),
+        ]
+    }
+
+    #[test]
+    fn print0_and_lines() {
+        let out = render(OutputArgs { print0: true, ..OutputArgs::default() }, &infos());
+        assert_eq!(out, "/r/a,b.TXT\0/r/.d\nir\0");
+        let out = render(OutputArgs::default(), &infos()[..1]);
+        assert_eq!(out, "/r/a,b.TXT\n");
+    }
+
+    #[test]
+    fn json_and_ndjson() {
+        let out = render(OutputArgs { json: true, ..OutputArgs::default() }, &infos());
+        let v: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(v[0]["path"], "/r/a,b.TXT");
+        assert_eq!(v[0]["size"], 3);
+        assert_eq!(v[0]["ext"], "txt");
+        assert_eq!(v[0]["type"], "file");
+        assert!(v[0]["mtime"].as_str().unwrap().starts_with("2023-11-1"));
+        assert_eq!(v[1]["type"], "dir");
+        assert_eq!(v[1]["hidden"], true);
+        assert!(v[1]["size"].is_null());
+        assert_eq!(render(OutputArgs { json: true, ..OutputArgs::default() }, &[]), "[]\n");
+
+        let out = render(OutputArgs { ndjson: true, ..OutputArgs::default() }, &infos());
+        let lines: Vec<serde_json::Value> = out.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1]["path"], "/r/.d\nir");
+    }
+
+    #[test]
+    fn csv_quotes_fields() {
+        let out = render(OutputArgs { csv: true, ..OutputArgs::default() }, &infos());
+        let mut lines = out.splitn(2, '\n');
+        assert_eq!(lines.next(), Some(CSV_HEADER));
+        let rest = lines.next().unwrap();
+        assert!(rest.starts_with("\"/r/a,b.TXT\",3,2023-11-1"));
+        assert!(rest.contains("\n\"/r/.d\nir\",,,,dir,true\n"));
+        assert_eq!(render(OutputArgs { csv: true, ..OutputArgs::default() }, &[]), format!("{CSV_HEADER}\n"));
+    }
+
+    #[test]
+    fn templates() {
+        let fmt = |t: &str| OutputArgs { format: Some(t.into()), ..OutputArgs::default() };
+        let out = render(fmt(r"{name}\t{size}\t{type} {{x}}"), &infos()[..1]);
+        assert_eq!(out, "a,b.TXT\t3\tfile {x}\n");
+        assert_eq!(parse_template("{nope}").unwrap_err(), "unknown field {nope}");
+        assert!(parse_template("a}b").is_err());
+        assert!(parse_template("{path").is_err());
+    }
+
+    #[test]
+    fn one_format_and_line_numbers_need_content() {
+        use clap::Parser;
+
+        #[derive(Parser)]
+        struct Cli {
+            #[command(flatten)]
+            cmd: crate::cmd::find::FindCommand,
+        }
+
+        let parse = |args: &[&str]| Cli::try_parse_from([&["x"], args].concat());
+        assert!(parse(&["--csv", "--json"]).is_err());
+        assert!(parse(&["--line-numbers"]).is_err());
+        let ok = parse(&["--csv", "--line-numbers", "--content", "todo"]).unwrap();
+        assert!(ok.cmd.output.csv && ok.cmd.output.line_numbers);
+    }
+
+    #[test]
+    fn line_numbers() {
+        let info = &infos()[0];
+        let with_lines = OutputArgs { line_numbers: true, ..OutputArgs::default() };
+        let mut buf = Vec::new();
+        let mut p = Printer::new(&mut buf, &with_lines).unwrap();
+        p.write(info, Some(&[3, 17])).unwrap();
+        p.finish().unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), "/r/a,b.TXT:3,17\n");
+
+        let mut buf = Vec::new();
+        let mut p = Printer::new(&mut buf, &OutputArgs { csv: true, ..with_lines }).unwrap();
+        p.write(info, Some(&[3, 17])).unwrap();
+        p.finish().unwrap();
+        let out = String::from_utf8(buf).unwrap();
+        assert!(out.starts_with(&format!("{CSV_HEADER},lines\n")));
+        assert!(out.ends_with(",file,false,3 17\n"));
+
+        let mut buf = Vec::new();
+        let mut p = Printer::new(&mut buf, &OutputArgs { ndjson: true, ..OutputArgs::default() }).unwrap();
+        p.write(info, Some(&[2])).unwrap();
+        p.write(info, None).unwrap();
+        p.finish().unwrap();
+        let out = String::from_utf8(buf).unwrap();
+        let v: Vec<serde_json::Value> = out.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(v[0]["lines"], serde_json::json!([2]));
+        assert!(v[1].get("lines").is_none());
+    }
+}
@@ -0,0 +1,431 @@
+use clap::{ArgAction, Args};
+use serde::{Deserialize, Serialize};
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tracing::{error, info};
+
+use super::fsutil::archive::ArchiveWriter;
+use super::fsutil::file::{copy_tree, move_one, replace_with_hard_link, CopyOptions};
+use super::fsutil::journal::{Fingerprint, Journal, JournalOp};
+use super::fsutil::trash::Trash;
+use super::copy::check_not_into_itself;
+use super::rm::{check_guards, rm_path, rm_tree};
+
+/// Shared `--dry-run` / `--apply` switches for commands that modify files.
+#[derive(Args, Debug, Clone)]
+pub struct RunMode {
+    /// Only report what would happen (default true); `--dry-run=false` executes
+    #[arg(
+        long,
+        default_value_t = true,
+        action = ArgAction::Set,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+    pub dry_run: bool,
+
+    /// Execute the operations (same as `--dry-run=false`)
+    #[arg(long, conflicts_with = "dry_run")]
+    pub apply: bool,
+}
+
+impl RunMode {
+    pub fn dry_run(&self) -> bool {
+        self.dry_run && !self.apply
+    }
+}
+
+impl Default for RunMode {
+    fn default() -> Self {
+        Self { dry_run: true, apply: false }
+    }
+}
+
+pub const PLAN_VERSION: u32 = 1;
+
+/// A reviewed list of operations, written by `--plan` and run by `apply`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Plan {
+    pub version: u32,
+    pub created: String,
+    pub ops: Vec<PlannedOp>,
+}
+
+impl Plan {
+    pub fn new(ops: Vec<PlannedOp>) -> Self {
+        Self { version: PLAN_VERSION, created: chrono::Local::now().to_rfc3339(), ops }
+    }
+
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_vec_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, json)
+    }
+
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let plan: Plan = serde_json::from_slice(&fs::read(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if plan.version != PLAN_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported plan version {}", plan.version),
+            ));
+        }
+        Ok(plan)
+    }
+}
+
+/// One concrete filesystem operation with all names already resolved.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum Op {
+    Rm { path: PathBuf, recursive: bool },
+    Trash { path: PathBuf },
+    Mv { src: PathBuf, dst: PathBuf, overwrite: bool },
+    Copy { src: PathBuf, dst: PathBuf, overwrite: bool, preserve: CopyOptions },
+    Compress { src: PathBuf, dst: PathBuf, name: PathBuf, level: Option<i64> },
+    /// Replace `path` with a hard link to `target`, which has the same contents.
+    Link { path: PathBuf, target: PathBuf },
+}
+
+impl Op {
+    /// Short name used as the `action` field in logs.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Op::Rm { .. } => "rm",
+            Op::Trash { .. } => "trash",
+            Op::Mv { .. } => "mv",
+            Op::Copy { .. } => "copy",
+            Op::Compress { .. } => "compress",
+            Op::Link { .. } => "link",
+        }
+    }
+
+    pub fn journal_op(&self) -> JournalOp {
+        match self {
+            Op::Rm { .. } => JournalOp::Rm,
+            Op::Trash { .. } => JournalOp::Trash,
+            Op::Mv { .. } => JournalOp::Mv,
+            Op::Copy { .. } => JournalOp::Copy,
+            Op::Compress { .. } => JournalOp::Compress,
+            Op::Link { .. } => JournalOp::Link,
+        }
+    }
+
+    /// The existing path this operation reads or removes.
+    pub fn subject(&self) -> &Path {
+        match self {
+            Op::Rm { path, .. } | Op::Trash { path } | Op::Link { path, .. } => path,
+            Op::Mv { src, .. } | Op::Copy { src, .. } | Op::Compress { src, .. } => src,
+        }
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Op::Rm { path, recursive: true } => write!(f, "rm -R {}", path.display()),
+            Op::Rm { path, .. } => write!(f, "rm {}", path.display()),
+            Op::Trash { path } => write!(f, "trash {}", path.display()),
+            Op::Mv { src, dst, .. } => write!(f, "mv {} -> {}", src.display(), dst.display()),
+            Op::Copy { src, dst, .. } => write!(f, "copy {} -> {}", src.display(), dst.display()),
+            Op::Compress { src, dst, name, .. } => {
+                write!(f, "compress {} -> {}:{}", src.display(), dst.display(), name.display())
+            }
+            Op::Link { path, target } => write!(f, "link {} -> {}", path.display(), target.display()),
+        }
+    }
+}
+
+/// An operation plus the state its subject had when the plan was made.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PlannedOp {
+    #[serde(flatten)]
+    pub op: Op,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expect: Option<Snapshot>,
+}
+
+impl PlannedOp {
+    /// Plan `op`, recording the current size/mtime of its subject.
+    pub fn checked(op: Op) -> io::Result<Self> {
+        let expect = Some(Snapshot::take(op.subject())?);
+        Ok(Self { op, expect })
+    }
+
+    /// Plan `op` on a path that only exists once earlier ops ran.
+    pub fn unchecked(op: Op) -> Self {
+        Self { op, expect: None }
+    }
+}
+
+/// Size and mtime used to detect changes between plan and apply.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Snapshot {
+    /// File size; `None` for directories.
+    pub size: Option<u64>,
+    pub mtime_secs: i64,
+    pub mtime_nanos: u32,
+}
+
+impl Snapshot {
+    pub fn take(path: &Path) -> io::Result<Self> {
+        let md = fs::symlink_metadata(path)?;
+        let size = if md.is_dir() { None } else { Some(md.len()) };
+        let (mtime_secs, mtime_nanos) = match md.modified()?.duration_since(UNIX_EPOCH) {
+            Ok(d) => (d.as_secs() as i64, d.subsec_nanos()),
+            Err(e) => (-(e.duration().as_secs() as i64), e.duration().subsec_nanos()),
+        };
+        Ok(Self { size, mtime_secs, mtime_nanos })
+    }
+}
+
+/// Check every recorded snapshot; returns the paths that changed or vanished.
+pub fn changed_paths(plan: &Plan) -> Vec<(PathBuf, String)> {
+    plan.ops
+        .iter()
+        .filter_map(|p| {
+            let expect = p.expect?;
+            let path = p.op.subject();
+            match Snapshot::take(path) {
+                Ok(now) if now == expect => None,
+                Ok(_) => Some((path.to_path_buf(), "size or mtime changed".to_string())),
+                Err(e) => Some((path.to_path_buf(), e.to_string())),
+            }
+        })
+        .collect()
+}
+
+/// Runs operations; keeps archives open so many `Compress` ops share one file.
+/// With a journal, every applied operation is recorded for `undo`.
+#[derive(Default)]
+pub struct Executor {
+    archives: BTreeMap<PathBuf, ArchiveWriter>,
+    journal: Option<Journal>,
+}
+
+impl Executor {
+    pub fn journaled(journal: Journal) -> Self {
+        Self { archives: BTreeMap::new(), journal: Some(journal) }
+    }
+
+    pub fn execute(&mut self, op: &Op) -> io::Result<()> {
+        let dst = self.run_op(op)?;
+        if let Some(journal) = &self.journal {
+            // Only what undo can reverse is fingerprinted; removals are final.
+            let fp = match (op, &dst) {
+                (Op::Mv { .. }, Some(d)) => Fingerprint::stat(d),
+                (Op::Copy { .. } | Op::Trash { .. }, Some(d)) => Fingerprint::of(d),
+                _ => Fingerprint::default(),
+            };
+            journal.record(op.journal_op(), op.subject(), dst.as_deref(), fp)?;
+        }
+        Ok(())
+    }
+
+    /// Perform `op`, returning where its subject ended up (if anywhere).
+    fn run_op(&mut self, op: &Op) -> io::Result<Option<PathBuf>> {
+        match op {
+            Op::Rm { path, recursive } => {
+                let home = std::env::var_os("HOME").map(PathBuf::from);
+                check_guards(path, None, home.as_deref())?;
+                if *recursive {
+                    let report = rm_tree(path, false);
+                    if let Some((p, e)) = report.failures.into_iter().next() {
+                        return Err(io::Error::new(e.kind(), format!("{}: {}", p.display(), e)));
+                    }
+                } else {
+                    rm_path(path, false)?;
+                }
+                Ok(None)
+            }
+            Op::Trash { path } => Ok(Some(Trash::from_env()?.put(path)?.file_path())),
+            Op::Mv { src, dst, overwrite } => {
+                ensure_free(dst, *overwrite)?;
+                create_parent(dst)?;
+                move_one(src, dst)?;
+                Ok(Some(dst.clone()))
+            }
+            Op::Copy { src, dst, overwrite, preserve } => {
+                check_not_into_itself(src, dst.parent().unwrap_or(Path::new("")))?;
+                ensure_free(dst, *overwrite)?;
+                create_parent(dst)?;
+                copy_tree(src, dst, preserve)?;
+                Ok(Some(dst.clone()))
+            }
+            Op::Compress { src, dst, name, level } => {
+                let writer = match self.archives.entry(dst.clone()) {
+                    Entry::Occupied(e) => e.into_mut(),
+                    Entry::Vacant(e) => e.insert(ArchiveWriter::create(dst, *level)?),
+                };
+                writer.append(src, name)?;
+                Ok(Some(dst.clone()))
+            }
+            Op::Link { path, target } => {
+                let (now, want) = (Fingerprint::of(path), Fingerprint::of(target));
+                if now.sha256.is_none() || now != want {
+                    return Err(io::Error::other(format!("contents differ from {}", target.display())));
+                }
+                replace_with_hard_link(path, target)?;
+                Ok(Some(target.clone()))
+            }
+        }
+    }
+
+    /// Finish all open archives, returning `(archive, entries, result)` for each.
+    pub fn finish(self) -> Vec<(PathBuf, u64, io::Result<()>)> {
+        self.archives
+            .into_iter()
+            .map(|(dst, w)| {
+                let entries = w.entries();
+                (dst, entries, w.finish())
+            })
+            .collect()
+    }
+}
+
+/// Write `ops` as a plan file for `--plan`, or exit non-zero if planning failed.
+pub fn write_or_exit(path: &str, ops: Vec<PlannedOp>, failures: usize) {
+    if failures > 0 {
+        error!(target: "file-rs", action="plan", plan=%path, failures, "Planning failed; no plan written");
+        std::process::exit(1);
+    }
+    let n = ops.len();
+    if let Err(e) = Plan::new(ops).write(Path::new(path)) {
+        error!(target: "file-rs", action="plan", plan=%path, error=%e, "Failed to write plan");
+        std::process::exit(1);
+    }
+    info!(target: "file-rs", action="plan", plan=%path, ops=n, "Plan written");
+}
+
+fn ensure_free(dst: &Path, overwrite: bool) -> io::Result<()> {
+    if !overwrite && fs::symlink_metadata(dst).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("destination exists: {}", dst.display()),
+        ));
+    }
+    Ok(())
+}
+
+fn create_parent(dst: &Path) -> io::Result<()> {
+    match dst.parent() {
+        Some(p) if !p.as_os_str().is_empty() => fs::create_dir_all(p),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use tempfile::tempdir;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        mode: RunMode,
+    }
+
+    #[test]
+    fn dry_run_switches() {
+        assert!(Cli::parse_from(["x"]).mode.dry_run());
+        assert!(Cli::parse_from(["x", "--dry-run"]).mode.dry_run());
+        assert!(!Cli::parse_from(["x", "--dry-run=false"]).mode.dry_run());
+        assert!(!Cli::parse_from(["x", "--apply"]).mode.dry_run());
+        assert!(Cli::try_parse_from(["x", "--apply", "--dry-run=true"]).is_err());
+    }
+
+    #[test]
+    fn plan_roundtrip_and_change_detection() {
+        let dir = tempdir().unwrap();
+        let a = dir.path().join("a.txt");
+        fs::write(&a, b"one").unwrap();
+        let dst = dir.path().join("out/a.txt");
+
+        let plan = Plan::new(vec![
+            PlannedOp::checked(Op::Mv { src: a.clone(), dst: dst.clone(), overwrite: false }).unwrap(),
+            PlannedOp::unchecked(Op::Rm { path: dst.clone(), recursive: false }),
+        ]);
+        let file = dir.path().join("plan.json");
+        plan.write(&file).unwrap();
+        let read = Plan::read(&file).unwrap();
+        assert_eq!(read, plan);
+        assert!(changed_paths(&read).is_empty());
+
+        fs::write(&a, b"changed!").unwrap();
+        let changed = changed_paths(&read);
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed[0].0, a);
+    }
+
+    #[test]
+    fn executor_runs_ops() {
+        let dir = tempdir().unwrap();
+        let a = dir.path().join("a.txt");
+        fs::write(&a, b"one").unwrap();
+        let copy = dir.path().join("bk/a.txt");
+        let moved = dir.path().join("mv/a.txt");
+
+        let mut ex = Executor::default();
+        ex.execute(&Op::Copy { src: a.clone(), dst: copy.clone(), overwrite: false, preserve: CopyOptions::default() })
+            .unwrap();
+        ex.execute(&Op::Mv { src: a.clone(), dst: moved.clone(), overwrite: false }).unwrap();
+        let err = ex.execute(&Op::Mv { src: copy.clone(), dst: moved.clone(), overwrite: false }).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        ex.execute(&Op::Rm { path: moved.clone(), recursive: false }).unwrap();
+        assert!(ex.finish().is_empty());
+
+        assert!(!a.exists());
+        assert!(!moved.exists());
+        assert_eq!(fs::read(&copy).unwrap(), b"one");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn link_replaces_identical_files_only() {
+        use std::os::unix::fs::MetadataExt;
+
+        let dir = tempdir().unwrap();
+        let (a, b, c) = (dir.path().join("a"), dir.path().join("b"), dir.path().join("c"));
+        fs::write(&a, b"same").unwrap();
+        fs::write(&b, b"same").unwrap();
+        fs::write(&c, b"diff").unwrap();
+
+        let mut ex = Executor::default();
+        ex.execute(&Op::Link { path: b.clone(), target: a.clone() }).unwrap();
+        assert_eq!(fs::metadata(&a).unwrap().ino(), fs::metadata(&b).unwrap().ino());
+        assert!(ex.execute(&Op::Link { path: c.clone(), target: a.clone() }).is_err());
+        assert_eq!(fs::read(&c).unwrap(), b"diff");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 3);
+    }
+
+    #[test]
+    fn executor_journals_applied_ops() {
+        let dir = tempdir().unwrap();
+        let a = dir.path().join("a.txt");
+        fs::write(&a, b"one").unwrap();
+        let moved = dir.path().join("mv/a.txt");
+        let journal = Journal::at(dir.path().join("journal.jsonl"));
+
+        let mut ex = Executor::journaled(journal.clone());
+        ex.execute(&Op::Mv { src: a.clone(), dst: moved.clone(), overwrite: false }).unwrap();
+        ex.execute(&Op::Mv { src: a.clone(), dst: moved.clone(), overwrite: false }).unwrap_err();
+        ex.execute(&Op::Rm { path: moved.clone(), recursive: false }).unwrap();
+
+        let entries = journal.read().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!((entries[0].op, entries[0].dst.as_deref()), (JournalOp::Mv, Some(moved.as_path())));
+        // Moves are checked by size and mtime; nothing is hashed.
+        assert_eq!(entries[0].size, Some(3));
+        assert!(entries[0].mtime_ns.is_some() && entries[0].sha256.is_none());
+        // Permanent removals cannot be undone, so they carry no fingerprint.
+        assert_eq!((entries[1].op, entries[1].src.as_path()), (JournalOp::Rm, moved.as_path()));
+        assert_eq!(entries[1].fingerprint(), Fingerprint::default());
+    }
+}
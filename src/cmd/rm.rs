@@ -1,146 +1,381 @@
-use clap::Args;
-use std::fs;
-use std::io::{self, BufRead, Read};
-use std::path::{Path, PathBuf};
-use tracing::{error, info};
-use anyhow::{Result, anyhow};
-
-#[derive(Args, Debug)]
-pub struct RmArgs {
-    /// Source path (if omitted, will read from stdin)
-    #[arg(short, long)]
-    pub src: Option<String>,
-
-    #[arg(long)]
-    pub stdin0: bool,
-
-    /// Dry-run only (default true)
-    #[arg(long, default_value_t = true)]
-    pub dry_run: bool,   
-}
-
-pub fn run(args: RmArgs) {
-    let sources: Vec<String> = if let Some(src) = args.src {
-        vec![src]
-    } else if args.stdin0 {
-        let mut buf = Vec::new();
-        io::stdin().read_to_end(&mut buf).expect("stdin read failed");
-        buf.split(|b| *b == 0)
-            .filter(|s| !s.is_empty())
-            .map(|s| String::from_utf8_lossy(s).into_owned())
-            .collect()
-    } else {
-        let stdin = io::stdin();
-        stdin.lock().lines().filter_map(Result::ok).collect()
-    };
-
-    let mut failures = 0usize;
-
-    for s in sources {
-        let p = PathBuf::from(&s);
-        match rm_path(&p, args.dry_run) {
-            Ok(false) => {
-                info!(target: "file-rs", action="rm", dry_run=true, path=%p.display(), "Would remove");
-            }
-            Ok(true) => {
-                info!(target: "file-rs", action="rm", dry_run=false, path=%p.display(), "Removed");
-            }
-            Err(e) => {
-                failures += 1;
-                error!(target: "file-rs", action="rm", path=%p.display(), error=%e, "Failed to remove");
-            }
-        }
-    }
-
-    if failures > 0 {
-        std::process::exit(1);
-    }
-}
-
-pub fn rm_path(path: &Path, dry_run: bool) -> io::Result<bool> {
-    if dry_run {
-        return Ok(false);
-    }
-
-    let meta = match fs::symlink_metadata(path) {
-        Ok(m) => m,
-        Err(e) => return Err(e),
-    };
-
-    if meta.is_file() {
-        fs::remove_file(path)?;
-        Ok(true)
-    } else if meta.is_dir() {
-        match fs::remove_dir(path) {
-            Ok(()) => Ok(true),
-            Err(e) => Err(e),
-        }
-    } else {
-        Err(io::Error::new(
-            io::ErrorKind::Other,
-            "unsupported file type (symlink/special)",
-        ))
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use tempfile::tempdir;
-
-    #[test]
-    fn dry_run_does_not_delete_file() {
-        let dir = tempdir().unwrap();
-        let file = dir.path().join("a.txt");
-        fs::write(&file, b"hello").unwrap();
-
-        let res = rm_path(&file, true).unwrap();
-        assert_eq!(res, false);
-        assert!(file.exists());
-    }
-
-    #[test]
-    fn apply_deletes_file() {
-        let dir = tempdir().unwrap();
-        let file = dir.path().join("a.txt");
-        fs::write(&file, b"hello").unwrap();
-
-        let res = rm_path(&file, false).unwrap();
-        assert_eq!(res, true);
-        assert!(!file.exists());
-    }
-
-    #[test]
-    fn error_on_nonexistent_path() {
-        let dir = tempdir().unwrap();
-        let missing = dir.path().join("nope.txt");
-
-        let err = rm_path(&missing, false).unwrap_err();
-        assert_eq!(err.kind(), io::ErrorKind::NotFound);
-    }
-
-    #[test]
-    fn remove_empty_dir() {
-        let dir = tempdir().unwrap();
-        let empty = dir.path().join("empty");
-        fs::create_dir(&empty).unwrap();
-
-        let res = rm_path(&empty, false).unwrap();
-        assert_eq!(res, true);
-        assert!(!empty.exists());
-    }
-
-    #[test]
-    fn non_empty_dir_fails() {
-        let dir = tempdir().unwrap();
-        let non_empty = dir.path().join("d");
-        let inner = non_empty.join("x.txt");
-        fs::create_dir(&non_empty).unwrap();
-        fs::write(&inner, b"x").unwrap();
-
-        let err = rm_path(&non_empty, false).unwrap_err();
-        assert!(matches!(err.kind(), io::ErrorKind::Other | io::ErrorKind::PermissionDenied));
-        assert!(non_empty.exists());
-        assert!(inner.exists());
-    }
-}
\ No newline at end of file
+use clap::Args;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use tracing::{error, info};
+use walkdir::WalkDir;
+
+use super::fsutil::input::iter_stdin_lines;
+use super::fsutil::journal::{Fingerprint, JournalOp};
+use super::fsutil::trash::Trash;
+use super::plan::{self, Op, PlannedOp, RunMode};
+use super::undo;
+
+#[derive(Args, Debug)]
+pub struct RmArgs {
+    /// Source path (if omitted, will read from stdin)
+    #[arg(short, long)]
+    pub src: Option<String>,
+
+    #[arg(long)]
+    pub stdin0: bool,
+
+    /// Remove directories and their contents (bottom-up, symlinks are not followed)
+    #[arg(short = 'R', long)]
+    pub recursive: bool,
+
+    /// Move to the freedesktop trash instead of deleting
+    #[arg(long)]
+    pub trash: bool,
+
+    /// Refuse to remove anything outside this directory
+    #[arg(long)]
+    pub within: Option<String>,
+
+    #[command(flatten)]
+    pub mode: RunMode,
+
+    /// Write the planned removals to this JSON file instead of running them
+    #[arg(long, value_name = "FILE")]
+    pub plan: Option<String>,
+}
+
+pub fn run(args: RmArgs) {
+    let sources: Vec<String> = if let Some(src) = args.src {
+        vec![src]
+    } else if args.stdin0 {
+        let mut buf = Vec::new();
+        io::stdin().read_to_end(&mut buf).expect("stdin read failed");
+        buf.split(|b| *b == 0)
+            .filter(|s| !s.is_empty())
+            .map(|s| String::from_utf8_lossy(s).into_owned())
+            .collect()
+    } else {
+        match iter_stdin_lines().collect() {
+            Ok(lines) => lines,
+            Err(e) => {
+                error!(target: "file-rs", action="rm", error=%e, "Failed to read paths from stdin");
+                std::process::exit(1);
+            }
+        }
+    };
+
+    let within = args.within.as_ref().map(PathBuf::from);
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    let dry_run = args.mode.dry_run();
+    let journal = if args.plan.is_some() { None } else { undo::open_journal(dry_run) };
+    let mut failures = 0usize;
+    let mut planned = Vec::new();
+
+    for s in sources {
+        let p = PathBuf::from(&s);
+        if let Err(e) = check_guards(&p, within.as_deref(), home.as_deref()) {
+            failures += 1;
+            error!(target: "file-rs", action="rm", path=%p.display(), error=%e, "Refusing to remove");
+            continue;
+        }
+
+        if args.plan.is_some() {
+            let op = if args.trash {
+                Op::Trash { path: p.clone() }
+            } else {
+                Op::Rm { path: p.clone(), recursive: args.recursive }
+            };
+            match PlannedOp::checked(op) {
+                Ok(op) => planned.push(op),
+                Err(e) => {
+                    failures += 1;
+                    error!(target: "file-rs", action="rm", path=%p.display(), error=%e, "Failed to plan removal");
+                }
+            }
+            continue;
+        }
+
+        if args.trash {
+            if dry_run {
+                info!(target: "file-rs", action="rm", dry_run=true, trash=true, path=%p.display(), "Would trash");
+                continue;
+            }
+            match Trash::from_env().and_then(|t| t.put(&p)) {
+                Ok(entry) => {
+                    let trashed = entry.file_path();
+                    info!(target: "file-rs", action="rm", dry_run=false, trash=true, path=%p.display(), trashed=%trashed.display(), "Trashed");
+                    if !undo::record(journal.as_ref(), JournalOp::Trash, &p, Some(&trashed), Fingerprint::of(&trashed)) {
+                        failures += 1;
+                    }
+                }
+                Err(e) => {
+                    failures += 1;
+                    error!(target: "file-rs", action="rm", path=%p.display(), error=%e, "Failed to trash");
+                }
+            }
+            continue;
+        }
+
+        if args.recursive {
+            let report = rm_tree(&p, dry_run);
+            for (fp, e) in &report.failures {
+                error!(target: "file-rs", action="rm", path=%fp.display(), error=%e, "Failed to remove");
+            }
+            failures += report.failures.len();
+            if dry_run {
+                info!(target: "file-rs", action="rm", dry_run=true, path=%p.display(), entries=report.removed, "Would remove tree");
+            } else {
+                info!(target: "file-rs", action="rm", dry_run=false, path=%p.display(), entries=report.removed, "Removed tree");
+                if report.removed > 0 && !undo::record(journal.as_ref(), JournalOp::Rm, &p, None, Fingerprint::default()) {
+                    failures += 1;
+                }
+            }
+            continue;
+        }
+
+        match rm_path(&p, dry_run) {
+            Ok(false) => {
+                info!(target: "file-rs", action="rm", dry_run=true, path=%p.display(), "Would remove");
+            }
+            Ok(true) => {
+                info!(target: "file-rs", action="rm", dry_run=false, path=%p.display(), "Removed");
+                if !undo::record(journal.as_ref(), JournalOp::Rm, &p, None, Fingerprint::default()) {
+                    failures += 1;
+                }
+            }
+            Err(e) => {
+                failures += 1;
+                error!(target: "file-rs", action="rm", path=%p.display(), error=%e, "Failed to remove");
+            }
+        }
+    }
+
+    if let Some(plan) = &args.plan {
+        plan::write_or_exit(plan, planned, failures);
+        return;
+    }
+
+    if failures > 0 {
+        std::process::exit(1);
+    }
+}
+
+pub fn rm_path(path: &Path, dry_run: bool) -> io::Result<bool> {
+    if dry_run {
+        return Ok(false);
+    }
+
+    let meta = fs::symlink_metadata(path)?;
+
+    if meta.is_file() {
+        fs::remove_file(path)?;
+        Ok(true)
+    } else if meta.is_dir() {
+        fs::remove_dir(path)?;
+        Ok(true)
+    } else {
+        Err(io::Error::other("unsupported file type (symlink/special)"))
+    }
+}
+
+/// Outcome of a recursive removal.
+#[derive(Debug, Default)]
+pub struct RmReport {
+    /// Entries removed (or that would be removed in dry-run).
+    pub removed: u64,
+    pub failures: Vec<(PathBuf, io::Error)>,
+}
+
+/// Remove `path` and everything below it, deepest entries first.
+/// Symlinks are unlinked, never followed. A failing entry is recorded and
+/// the walk continues; its ancestors are then left in place silently.
+pub fn rm_tree(path: &Path, dry_run: bool) -> RmReport {
+    let mut report = RmReport::default();
+    let walk = WalkDir::new(path)
+        .follow_links(false)
+        .follow_root_links(false)
+        .contents_first(true);
+
+    for entry in walk {
+        let entry = match entry {
+            Ok(e) => e,
+            Err(e) => {
+                let p = e.path().map(Path::to_path_buf).unwrap_or_else(|| path.to_path_buf());
+                report.failures.push((p, e.into()));
+                continue;
+            }
+        };
+        let p = entry.path();
+        let is_dir = entry.file_type().is_dir();
+
+        if is_dir && report.failures.iter().any(|(f, _)| f.starts_with(p)) {
+            continue;
+        }
+        if dry_run {
+            report.removed += 1;
+            continue;
+        }
+        let res = if is_dir { fs::remove_dir(p) } else { fs::remove_file(p) };
+        match res {
+            Ok(()) => report.removed += 1,
+            Err(e) => report.failures.push((p.to_path_buf(), e)),
+        }
+    }
+    report
+}
+
+/// Refuse `/`, the home directory (or anything containing it), and any path
+/// outside `within`. The final path component is not resolved, so a symlink
+/// is judged by where it lives, not where it points.
+pub fn check_guards(path: &Path, within: Option<&Path>, home: Option<&Path>) -> io::Result<()> {
+    let abs = match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => {
+            let parent = if parent.as_os_str().is_empty() { Path::new(".") } else { parent };
+            fs::canonicalize(parent)?.join(name)
+        }
+        _ => fs::canonicalize(path)?,
+    };
+
+    if abs.parent().is_none() {
+        return Err(refuse(format!("refusing to remove filesystem root {}", abs.display())));
+    }
+    if let Some(home) = home.and_then(|h| fs::canonicalize(h).ok()) {
+        if home.starts_with(&abs) {
+            return Err(refuse(format!("refusing to remove home directory {}", abs.display())));
+        }
+    }
+    if let Some(within) = within {
+        let within = fs::canonicalize(within)?;
+        if !abs.starts_with(&within) {
+            return Err(refuse(format!("{} is outside {}", abs.display(), within.display())));
+        }
+    }
+    Ok(())
+}
+
+fn refuse(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::PermissionDenied, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn dry_run_does_not_delete_file() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("a.txt");
+        fs::write(&file, b"hello").unwrap();
+
+        let res = rm_path(&file, true).unwrap();
+        assert!(!res);
+        assert!(file.exists());
+    }
+
+    #[test]
+    fn apply_deletes_file() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("a.txt");
+        fs::write(&file, b"hello").unwrap();
+
+        let res = rm_path(&file, false).unwrap();
+        assert!(res);
+        assert!(!file.exists());
+    }
+
+    #[test]
+    fn error_on_nonexistent_path() {
+        let dir = tempdir().unwrap();
+        let missing = dir.path().join("nope.txt");
+
+        let err = rm_path(&missing, false).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn remove_empty_dir() {
+        let dir = tempdir().unwrap();
+        let empty = dir.path().join("empty");
+        fs::create_dir(&empty).unwrap();
+
+        let res = rm_path(&empty, false).unwrap();
+        assert!(res);
+        assert!(!empty.exists());
+    }
+
+    #[test]
+    fn non_empty_dir_fails() {
+        let dir = tempdir().unwrap();
+        let non_empty = dir.path().join("d");
+        let inner = non_empty.join("x.txt");
+        fs::create_dir(&non_empty).unwrap();
+        fs::write(&inner, b"x").unwrap();
+
+        let err = rm_path(&non_empty, false).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            io::ErrorKind::Other | io::ErrorKind::PermissionDenied | io::ErrorKind::DirectoryNotEmpty
+        ));
+        assert!(non_empty.exists());
+        assert!(inner.exists());
+    }
+
+    #[test]
+    fn recursive_removes_tree() {
+        let dir = tempdir().unwrap();
+        let tree = dir.path().join("t");
+        fs::create_dir_all(tree.join("a/b")).unwrap();
+        fs::write(tree.join("a/b/x.txt"), b"x").unwrap();
+        fs::write(tree.join("y.txt"), b"y").unwrap();
+
+        let report = rm_tree(&tree, true);
+        assert_eq!(report.removed, 5);
+        assert!(tree.join("a/b/x.txt").exists());
+
+        let report = rm_tree(&tree, false);
+        assert!(report.failures.is_empty());
+        assert_eq!(report.removed, 5);
+        assert!(!tree.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn recursive_does_not_follow_symlinks() {
+        let dir = tempdir().unwrap();
+        let outside = dir.path().join("outside");
+        fs::create_dir(&outside).unwrap();
+        fs::write(outside.join("keep.txt"), b"k").unwrap();
+        let tree = dir.path().join("t");
+        fs::create_dir(&tree).unwrap();
+        std::os::unix::fs::symlink(&outside, tree.join("link")).unwrap();
+
+        let report = rm_tree(&tree, false);
+        assert!(report.failures.is_empty());
+        assert!(!tree.exists());
+        assert!(outside.join("keep.txt").exists());
+
+        // A symlink given as the root is removed itself, not its target.
+        let link = dir.path().join("root-link");
+        std::os::unix::fs::symlink(&outside, &link).unwrap();
+        rm_tree(&link, false);
+        assert!(fs::symlink_metadata(&link).is_err());
+        assert!(outside.join("keep.txt").exists());
+    }
+
+    #[test]
+    fn guards_refuse_root_home_and_outside_within() {
+        let dir = tempdir().unwrap();
+        let home = dir.path().join("home");
+        let work = dir.path().join("work");
+        fs::create_dir_all(home.join("docs")).unwrap();
+        fs::create_dir_all(work.join("sub")).unwrap();
+
+        let err = check_guards(Path::new("/"), None, None).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+
+        assert!(check_guards(&home, None, Some(&home)).is_err());
+        assert!(check_guards(dir.path(), None, Some(&home)).is_err());
+        assert!(check_guards(&home.join("docs"), None, Some(&home)).is_ok());
+
+        assert!(check_guards(&work.join("sub"), Some(&work), None).is_ok());
+        assert!(check_guards(&work.join("sub/../../home"), Some(&work), None).is_err());
+        assert!(check_guards(&home, Some(&work), None).is_err());
+    }
+}
@@ -0,0 +1,283 @@
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use tracing::{error, info};
+
+use super::fsutil::hash::hex;
+use super::fsutil::util::parse_human_size;
+
+#[derive(Args, Debug)]
+pub struct SplitArgs {
+    /// File to split
+    pub file: String,
+
+    /// Maximum chunk size (e.g. `100MB`, `1.5G`)
+    #[arg(long, conflicts_with = "parts", required_unless_present = "parts")]
+    pub size: Option<String>,
+
+    /// Exact number of chunks; their sizes differ by at most one byte
+    #[arg(long)]
+    pub parts: Option<u64>,
+
+    /// Directory for chunks and manifest (default: next to the file)
+    #[arg(long)]
+    pub out: Option<String>,
+}
+
+/// Describes a split file; written as `<name>.manifest.json` next to the chunks.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    pub version: u32,
+    /// Original file name
+    pub file: String,
+    pub size: u64,
+    pub sha256: String,
+    pub chunks: Vec<Chunk>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    /// Chunk file name, relative to the manifest
+    pub name: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// How a file is cut into chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// Chunks of this many bytes; the last one may be shorter.
+    Size(u64),
+    /// Exactly this many chunks; the first `len % n` are one byte larger.
+    Parts(u64),
+}
+
+impl Layout {
+    /// Chunk sizes for a file of `len` bytes. An empty file still gets one
+    /// (empty) chunk.
+    fn sizes(self, len: u64) -> io::Result<Vec<u64>> {
+        match self {
+            Self::Size(0) | Self::Parts(0) => {
+                Err(io::Error::new(io::ErrorKind::InvalidInput, "chunk size and part count must be at least 1"))
+            }
+            Self::Size(_) if len == 0 => Ok(vec![0]),
+            Self::Size(size) => {
+                let (full, rest) = (len / size, len % size);
+                let mut sizes = vec![size; full as usize];
+                if rest > 0 {
+                    sizes.push(rest);
+                }
+                Ok(sizes)
+            }
+            Self::Parts(1) if len == 0 => Ok(vec![0]),
+            Self::Parts(n) if n > len => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("cannot split {} bytes into {} parts", len, n),
+            )),
+            Self::Parts(n) => Ok((0..n).map(|i| len / n + u64::from(i < len % n)).collect()),
+        }
+    }
+}
+
+pub fn run(args: SplitArgs) {
+    let file = PathBuf::from(&args.file);
+    let layout = match (&args.size, args.parts) {
+        (Some(s), _) => match parse_human_size(s) {
+            Some(v) if v > 0 => Layout::Size(v),
+            _ => {
+                error!(target: "file-rs", action="split", size=%s, "Invalid chunk size");
+                std::process::exit(2);
+            }
+        },
+        (None, Some(n)) if n > 0 => match fs::metadata(&file) {
+            Ok(md) if n > md.len().max(1) => {
+                error!(target: "file-rs", action="split", path=%file.display(), parts=n, size=md.len(),
+                    "--parts exceeds the file size");
+                std::process::exit(2);
+            }
+            Ok(_) => Layout::Parts(n),
+            Err(e) => {
+                error!(target: "file-rs", action="split", path=%file.display(), error=%e, "Failed to stat");
+                std::process::exit(1);
+            }
+        },
+        _ => {
+            error!(target: "file-rs", action="split", "--parts must be at least 1");
+            std::process::exit(2);
+        }
+    };
+    let out_dir = match &args.out {
+        Some(o) => PathBuf::from(o),
+        None => file.parent().map(Path::to_path_buf).unwrap_or_default(),
+    };
+
+    match split_file(&file, &out_dir, layout) {
+        Ok((manifest_path, m)) => {
+            info!(target: "file-rs", action="split", path=%file.display(), chunks=m.chunks.len(),
+                manifest=%manifest_path.display(), "Split done");
+        }
+        Err(e) => {
+            error!(target: "file-rs", action="split", path=%file.display(), error=%e, "Failed to split");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Split `file` into chunks inside `out_dir` as described by `layout` and
+/// write the manifest. Chunks are named `<name>.001`, `<name>.002`, ...
+/// On error, chunks written so far are removed again.
+pub fn split_file(file: &Path, out_dir: &Path, layout: Layout) -> io::Result<(PathBuf, Manifest)> {
+    let name = file
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| io::Error::other("file name is not valid UTF-8"))?
+        .to_string();
+    let manifest_path = out_dir.join(format!("{}.manifest.json", name));
+    if manifest_path.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("manifest exists: {}", manifest_path.display()),
+        ));
+    }
+
+    let src = File::open(file)?;
+    let sizes = layout.sizes(src.metadata()?.len())?;
+    fs::create_dir_all(out_dir)?;
+
+    let mut written = Vec::new();
+    let res = write_chunks(BufReader::new(src), out_dir, &name, &sizes, &mut written).and_then(|(sha256, chunks)| {
+        let manifest = Manifest { version: MANIFEST_VERSION, file: name.clone(), size: sizes.iter().sum(), sha256, chunks };
+        let json = serde_json::to_vec_pretty(&manifest).map_err(io::Error::other)?;
+        fs::write(&manifest_path, json)?;
+        Ok(manifest)
+    });
+    match res {
+        Ok(manifest) => Ok((manifest_path, manifest)),
+        Err(e) => {
+            for p in &written {
+                let _ = fs::remove_file(p);
+            }
+            Err(e)
+        }
+    }
+}
+
+/// Copy `sizes` bytes per chunk from `src`, recording each created chunk in
+/// `written`. Returns the SHA-256 of the whole input and the chunk list.
+fn write_chunks(
+    mut src: impl Read,
+    out_dir: &Path,
+    name: &str,
+    sizes: &[u64],
+    written: &mut Vec<PathBuf>,
+) -> io::Result<(String, Vec<Chunk>)> {
+    let mut whole = Sha256::new();
+    let mut chunks = Vec::new();
+    let mut buf = vec![0u8; 64 * 1024];
+
+    for (i, &size) in sizes.iter().enumerate() {
+        let chunk_name = format!("{}.{:03}", name, i + 1);
+        let path = out_dir.join(&chunk_name);
+        let mut out = OpenOptions::new().write(true).create_new(true).open(&path)?;
+        written.push(path);
+        let mut hasher = Sha256::new();
+        let mut left = size;
+        while left > 0 {
+            let want = buf.len().min(left as usize);
+            let n = src.read(&mut buf[..want])?;
+            if n == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "file shrank while splitting"));
+            }
+            out.write_all(&buf[..n])?;
+            hasher.update(&buf[..n]);
+            whole.update(&buf[..n]);
+            left -= n as u64;
+        }
+        out.sync_all()?;
+        chunks.push(Chunk { name: chunk_name, size, sha256: hex(&hasher.finalize()) });
+    }
+    if src.read(&mut buf[..1])? > 0 {
+        return Err(io::Error::other("file grew while splitting"));
+    }
+    Ok((hex(&whole.finalize()), chunks))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn splits_into_sized_chunks() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("data.bin");
+        fs::write(&file, vec![7u8; 2500]).unwrap();
+
+        let (mpath, m) = split_file(&file, dir.path(), Layout::Size(1000)).unwrap();
+        assert_eq!(mpath, dir.path().join("data.bin.manifest.json"));
+        assert_eq!(m.size, 2500);
+        let sizes: Vec<u64> = m.chunks.iter().map(|c| c.size).collect();
+        assert_eq!(sizes, vec![1000, 1000, 500]);
+        assert_eq!(m.chunks[0].name, "data.bin.001");
+        assert_eq!(fs::metadata(dir.path().join("data.bin.003")).unwrap().len(), 500);
+
+        let parsed: Manifest = serde_json::from_slice(&fs::read(&mpath).unwrap()).unwrap();
+        assert_eq!(parsed, m);
+    }
+
+    #[test]
+    fn exact_multiple_and_empty_file() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("even.bin");
+        fs::write(&file, vec![1u8; 2000]).unwrap();
+        let (_, m) = split_file(&file, &dir.path().join("out"), Layout::Size(1000)).unwrap();
+        assert_eq!(m.chunks.len(), 2);
+
+        let empty = dir.path().join("empty.bin");
+        fs::write(&empty, b"").unwrap();
+        let (_, m) = split_file(&empty, dir.path(), Layout::Size(1000)).unwrap();
+        assert_eq!(m.chunks.len(), 1);
+        assert_eq!(m.chunks[0].size, 0);
+    }
+
+    #[test]
+    fn parts_gives_exactly_n_chunks() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("ten.bin");
+        fs::write(&file, b"0123456789").unwrap();
+
+        let (_, m) = split_file(&file, &dir.path().join("six"), Layout::Parts(6)).unwrap();
+        let sizes: Vec<u64> = m.chunks.iter().map(|c| c.size).collect();
+        assert_eq!(sizes, vec![2, 2, 2, 2, 1, 1]);
+        assert_eq!(fs::read(dir.path().join("six/ten.bin.005")).unwrap(), b"8");
+
+        let (_, m) = split_file(&file, &dir.path().join("ten"), Layout::Parts(10)).unwrap();
+        assert_eq!(m.chunks.len(), 10);
+
+        let err = split_file(&file, &dir.path().join("eleven"), Layout::Parts(11)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(!dir.path().join("eleven/ten.bin.001").exists());
+    }
+
+    #[test]
+    fn removes_written_chunks_on_error() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("data.bin");
+        fs::write(&file, vec![3u8; 30]).unwrap();
+        let out = dir.path().join("out");
+        fs::create_dir(&out).unwrap();
+        // The third chunk name is taken, so writing fails halfway.
+        fs::write(out.join("data.bin.003"), b"other").unwrap();
+
+        assert!(split_file(&file, &out, Layout::Size(10)).is_err());
+        assert!(!out.join("data.bin.001").exists());
+        assert!(!out.join("data.bin.002").exists());
+        assert_eq!(fs::read(out.join("data.bin.003")).unwrap(), b"other");
+        assert!(!out.join("data.bin.manifest.json").exists());
+    }
+}
@@ -0,0 +1,151 @@
+use clap::{Args, Subcommand};
+use std::path::{Path, PathBuf};
+use tracing::{error, info};
+
+use super::fsutil::journal::{Fingerprint, JournalOp};
+use super::fsutil::trash::{now_local, Trash, TrashEntry};
+use super::fsutil::util::parse_human_age;
+use super::plan::RunMode;
+use super::undo;
+
+#[derive(Args, Debug)]
+pub struct TrashArgs {
+    #[command(subcommand)]
+    pub command: TrashCommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TrashCommand {
+    /// List trashed items (oldest first)
+    List {
+        /// Only items deleted longer ago than this (e.g. `30d`)
+        #[arg(long)]
+        older: Option<String>,
+    },
+    /// Restore items by original path or trash name
+    Restore {
+        /// Original path (most recent deletion wins) or name inside the trash
+        item: String,
+
+        /// Restore to this path instead of the original location
+        #[arg(long)]
+        to: Option<String>,
+    },
+    /// Permanently delete trashed items
+    Empty {
+        /// Only items deleted longer ago than this (e.g. `30d`)
+        #[arg(long)]
+        older: Option<String>,
+
+        #[command(flatten)]
+        mode: RunMode,
+    },
+}
+
+pub fn run(args: TrashArgs) {
+    let trash = match Trash::from_env() {
+        Ok(t) => t,
+        Err(e) => {
+            error!(target: "file-rs", action="trash", error=%e, "Cannot locate trash");
+            std::process::exit(1);
+        }
+    };
+    let entries = match trash.list() {
+        Ok(v) => v,
+        Err(e) => {
+            error!(target: "file-rs", action="trash", dir=%trash.home_dir().display(), error=%e, "Failed to read trash");
+            std::process::exit(1);
+        }
+    };
+
+    match args.command {
+        TrashCommand::List { older } => {
+            for e in filter_older(entries, older.as_deref()) {
+                let date = e.deleted.map(|d| d.to_string()).unwrap_or_else(|| "?".into());
+                println!("{}\t{}\t{}", date, e.name, e.original.display());
+            }
+        }
+        TrashCommand::Restore { item, to } => {
+            let Some(entry) = find_entry(&entries, &item) else {
+                error!(target: "file-rs", action="trash-restore", item=%item, "No such item in trash");
+                std::process::exit(1);
+            };
+            let journal = undo::open_journal(false);
+            let from = entry.file_path();
+            let fp = Fingerprint::stat(&from);
+            match trash.restore(entry, to.as_deref().map(Path::new)) {
+                Ok(dst) => {
+                    info!(target: "file-rs", action="trash-restore", name=%entry.name, dst=%dst.display(), "Restored");
+                    if !undo::record(journal.as_ref(), JournalOp::Restore, &from, Some(&dst), fp) {
+                        std::process::exit(1);
+                    }
+                }
+                Err(e) => {
+                    error!(target: "file-rs", action="trash-restore", name=%entry.name, error=%e, "Failed to restore");
+                    std::process::exit(1);
+                }
+            }
+        }
+        TrashCommand::Empty { older, mode } => {
+            let dry_run = mode.dry_run();
+            let journal = undo::open_journal(dry_run);
+            let mut failures = 0usize;
+            for e in filter_older(entries, older.as_deref()) {
+                if dry_run {
+                    info!(target: "file-rs", action="trash-empty", dry_run=true, name=%e.name, original=%e.original.display(), "Would purge");
+                    continue;
+                }
+                let from = e.file_path();
+                match trash.purge(&e) {
+                    Ok(()) => {
+                        info!(target: "file-rs", action="trash-empty", dry_run=false, name=%e.name, "Purged");
+                        if !undo::record(journal.as_ref(), JournalOp::Rm, &from, None, Fingerprint::default()) {
+                            failures += 1;
+                        }
+                    }
+                    Err(err) => {
+                        failures += 1;
+                        error!(target: "file-rs", action="trash-empty", name=%e.name, error=%err, "Failed to purge");
+                    }
+                }
+            }
+            if failures > 0 {
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+/// Keep entries deleted at least `older` ago; entries without a date are kept
+/// only when no age limit is given.
+fn filter_older(entries: Vec<TrashEntry>, older: Option<&str>) -> Vec<TrashEntry> {
+    let Some(min_age) = older.map(|s| parse_human_age(s).unwrap_or_else(|| {
+        error!(target: "file-rs", action="trash", older=%s, "Invalid age");
+        std::process::exit(2);
+    })) else {
+        return entries;
+    };
+    let now = now_local();
+    entries
+        .into_iter()
+        .filter(|e| e.age_secs(now).is_some_and(|a| a >= min_age))
+        .collect()
+}
+
+/// Match by trash name first, then by original path (latest deletion).
+fn find_entry<'a>(entries: &'a [TrashEntry], item: &str) -> Option<&'a TrashEntry> {
+    if let Some(e) = entries.iter().find(|e| e.name == item) {
+        return Some(e);
+    }
+    let wanted = absolute(Path::new(item));
+    entries.iter().rev().find(|e| e.original == wanted)
+}
+
+/// Best-effort absolute form of `p`; its parent may no longer exist.
+fn absolute(p: &Path) -> PathBuf {
+    let parent = p.parent().filter(|d| !d.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    match (std::fs::canonicalize(parent), p.file_name()) {
+        (Ok(dir), Some(name)) => dir.join(name),
+        _ => std::path::absolute(p).unwrap_or_else(|_| p.to_path_buf()),
+    }
+}
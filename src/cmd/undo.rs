@@ -0,0 +1,366 @@
+use clap::Args;
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use tracing::{error, info, warn};
+
+use super::fsutil::file::move_one;
+use super::fsutil::journal::{Fingerprint, Journal, JournalEntry, JournalOp};
+use super::fsutil::trash::Trash;
+use super::plan::RunMode;
+
+#[derive(Args, Debug)]
+pub struct UndoArgs {
+    /// Undo the most recent run that was not undone yet (the default)
+    #[arg(long, conflicts_with_all = ["run", "list"])]
+    pub last: bool,
+
+    /// Undo the run with this id (see `--list`)
+    #[arg(long, value_name = "ID", conflicts_with = "list")]
+    pub run: Option<String>,
+
+    /// List journaled runs instead of undoing one
+    #[arg(long)]
+    pub list: bool,
+
+    #[command(flatten)]
+    pub mode: RunMode,
+}
+
+/// One invocation as seen in the journal.
+#[derive(Debug, PartialEq, Eq)]
+pub struct RunInfo {
+    pub id: String,
+    pub started: String,
+    pub ops: usize,
+    /// An `undo` marker references this run.
+    pub undone: bool,
+    /// This run was itself an `undo`.
+    pub is_undo: bool,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct UndoSummary {
+    pub reversed: u64,
+    pub irreversible: u64,
+    pub failures: u64,
+}
+
+pub fn run(args: UndoArgs) {
+    let journal = match Journal::from_env() {
+        Ok(j) => j,
+        Err(e) => {
+            error!(target: "file-rs", action="undo", error=%e, "Cannot locate journal");
+            std::process::exit(1);
+        }
+    };
+    let entries = match journal.read() {
+        Ok(v) => v,
+        Err(e) => {
+            error!(target: "file-rs", action="undo", journal=%journal.path().display(), error=%e, "Failed to read journal");
+            std::process::exit(1);
+        }
+    };
+
+    if args.list {
+        for r in runs(&entries) {
+            let state = if r.is_undo { "undo" } else if r.undone { "undone" } else { "" };
+            println!("{}\t{}\t{}\t{}", r.id, r.started, r.ops, state);
+        }
+        return;
+    }
+
+    let target = match args.run {
+        Some(id) if entries.iter().any(|e| e.run == id) => id,
+        Some(id) => {
+            error!(target: "file-rs", action="undo", run=%id, "No such run in journal");
+            std::process::exit(1);
+        }
+        None => match last_undoable(&entries) {
+            Some(id) => id,
+            None => {
+                info!(target: "file-rs", action="undo", "Nothing to undo");
+                return;
+            }
+        },
+    };
+
+    let dry_run = args.mode.dry_run();
+    let trash = Trash::from_env().ok();
+    let sum = undo_run(&journal, &entries, &target, trash.as_ref(), dry_run);
+    info!(target: "file-rs", action="undo", run=%target, dry_run, reversed=sum.reversed,
+        irreversible=sum.irreversible, failures=sum.failures, "Undo done");
+    if sum.failures > 0 {
+        std::process::exit(1);
+    }
+}
+
+/// Journal for one command invocation; `None` in dry-run. Exits if the
+/// journal cannot be located, since nothing would be recoverable.
+pub fn open_journal(dry_run: bool) -> Option<Journal> {
+    if dry_run {
+        return None;
+    }
+    match Journal::from_env() {
+        Ok(j) => Some(j),
+        Err(e) => {
+            error!(target: "file-rs", action="journal", error=%e, "Cannot locate journal");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Journal one applied operation. Returns false (after logging) if the
+/// entry could not be written.
+pub fn record(journal: Option<&Journal>, op: JournalOp, src: &Path, dst: Option<&Path>, fp: Fingerprint) -> bool {
+    let Some(journal) = journal else {
+        return true;
+    };
+    match journal.record(op, src, dst, fp) {
+        Ok(()) => true,
+        Err(e) => {
+            error!(target: "file-rs", action="journal", journal=%journal.path().display(), src=%src.display(), error=%e, "Failed to write journal");
+            false
+        }
+    }
+}
+
+/// Runs in journal order.
+pub fn runs(entries: &[JournalEntry]) -> Vec<RunInfo> {
+    let undone: HashSet<&str> = entries
+        .iter()
+        .filter(|e| e.op == JournalOp::Undo)
+        .filter_map(|e| e.undoes.as_deref())
+        .collect();
+    let mut out: Vec<RunInfo> = Vec::new();
+    for e in entries {
+        match out.iter_mut().find(|r| r.id == e.run) {
+            Some(r) => r.ops += usize::from(e.op != JournalOp::Undo),
+            None => out.push(RunInfo {
+                id: e.run.clone(),
+                started: e.ts.clone(),
+                ops: usize::from(e.op != JournalOp::Undo),
+                undone: undone.contains(e.run.as_str()),
+                is_undo: e.undoes.is_some(),
+            }),
+        }
+    }
+    out
+}
+
+/// Most recent run that is neither an undo nor already undone.
+pub fn last_undoable(entries: &[JournalEntry]) -> Option<String> {
+    runs(entries).into_iter().rev().find(|r| !r.undone && !r.is_undo).map(|r| r.id)
+}
+
+/// How one journaled operation is reversed.
+#[derive(Debug, PartialEq, Eq)]
+enum Reversal<'a> {
+    /// Move `from` back to `to`.
+    Move { from: &'a Path, to: &'a Path },
+    /// Restore the trashed file at `from` to its original location.
+    Restore { from: &'a Path },
+    /// Delete a copy.
+    Remove(&'a Path),
+    Irreversible(&'static str),
+}
+
+fn reversal(e: &JournalEntry) -> Reversal<'_> {
+    match (e.op, e.dst.as_deref()) {
+        (JournalOp::Mv, Some(dst)) => Reversal::Move { from: dst, to: &e.src },
+        (JournalOp::Trash, Some(dst)) => Reversal::Restore { from: dst },
+        (JournalOp::Copy, Some(dst)) if e.sha256.is_some() => Reversal::Remove(dst),
+        (JournalOp::Copy, _) => Reversal::Irreversible("copied directory is left in place"),
+        (JournalOp::Rm, _) => Reversal::Irreversible("permanently deleted"),
+        (JournalOp::Compress, _) => Reversal::Irreversible("archive is left in place"),
+        (JournalOp::Link, _) => Reversal::Irreversible("hard link is left in place (contents unchanged)"),
+        (JournalOp::Restore, _) => Reversal::Irreversible("restored from trash"),
+        _ => Reversal::Irreversible("incomplete journal entry"),
+    }
+}
+
+/// Reverse the operations of `run`, newest first. Entries already reversed
+/// by an earlier, partly failed undo are skipped. The run is marked undone
+/// once everything reversible went through.
+pub fn undo_run(
+    journal: &Journal,
+    entries: &[JournalEntry],
+    run: &str,
+    trash: Option<&Trash>,
+    dry_run: bool,
+) -> UndoSummary {
+    let mut sum = UndoSummary::default();
+    let done: HashSet<&Path> = entries
+        .iter()
+        .filter(|e| e.undoes.as_deref() == Some(run))
+        .map(|e| e.src.as_path())
+        .collect();
+
+    for e in entries.iter().rev().filter(|e| e.run == run && e.op != JournalOp::Undo) {
+        if e.dst.as_deref().is_some_and(|d| done.contains(d)) {
+            info!(target: "file-rs", action="undo", op=?e.op, src=%e.src.display(), "Already undone");
+            continue;
+        }
+        let rev = reversal(e);
+        if let Reversal::Irreversible(why) = rev {
+            sum.irreversible += 1;
+            warn!(target: "file-rs", action="undo", op=?e.op, src=%e.src.display(), reason=why, "Cannot undo");
+            continue;
+        }
+        if dry_run {
+            sum.reversed += 1;
+            info!(target: "file-rs", action="undo", dry_run=true, op=?e.op, src=%e.src.display(), "Would undo");
+            continue;
+        }
+        match reverse(journal, e, rev, run, trash) {
+            Ok(()) => {
+                sum.reversed += 1;
+                info!(target: "file-rs", action="undo", dry_run=false, op=?e.op, src=%e.src.display(), "Undone");
+            }
+            Err(err) => {
+                sum.failures += 1;
+                error!(target: "file-rs", action="undo", op=?e.op, src=%e.src.display(), error=%err, "Failed to undo");
+            }
+        }
+    }
+
+    if !dry_run && sum.failures == 0 {
+        let mut marker = journal.entry(JournalOp::Undo, Path::new(""), None, Fingerprint::default());
+        marker.undoes = Some(run.to_string());
+        if let Err(err) = journal.append(&marker) {
+            sum.failures += 1;
+            error!(target: "file-rs", action="undo", run=%run, error=%err, "Failed to write journal");
+        }
+    }
+    sum
+}
+
+fn reverse(journal: &Journal, e: &JournalEntry, rev: Reversal<'_>, run: &str, trash: Option<&Trash>) -> io::Result<()> {
+    let fp = e.fingerprint();
+    let (op, src, dst) = match rev {
+        Reversal::Move { from, to } => {
+            ensure_unchanged(from, &fp)?;
+            if fs::symlink_metadata(to).is_ok() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("original location is taken: {}", to.display()),
+                ));
+            }
+            if let Some(parent) = to.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            move_one(from, to)?;
+            (JournalOp::Mv, from, Some(to.to_path_buf()))
+        }
+        Reversal::Restore { from } => {
+            let trash = trash.ok_or_else(|| io::Error::other("cannot locate trash"))?;
+            let entry = trash
+                .list()?
+                .into_iter()
+                .find(|t| t.file_path() == from)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "item is no longer in the trash"))?;
+            ensure_unchanged(from, &fp)?;
+            let restored: PathBuf = trash.restore(&entry, None)?;
+            (JournalOp::Restore, from, Some(restored))
+        }
+        Reversal::Remove(path) => {
+            ensure_unchanged(path, &fp)?;
+            fs::remove_file(path)?;
+            (JournalOp::Rm, path, None)
+        }
+        Reversal::Irreversible(why) => return Err(io::Error::other(why)),
+    };
+    let mut entry = journal.entry(op, src, dst.as_deref(), fp);
+    entry.undoes = Some(run.to_string());
+    journal.append(&entry)
+}
+
+fn ensure_unchanged(path: &Path, fp: &Fingerprint) -> io::Result<()> {
+    if fs::symlink_metadata(path).is_err() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("missing: {}", path.display())));
+    }
+    if !fp.matches(path) {
+        return Err(io::Error::other(format!("changed since the operation: {}", path.display())));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn undo_reverses_moves_copies_and_trash() {
+        let dir = tempdir().unwrap();
+        let a = dir.path().join("a.txt");
+        let b = dir.path().join("b.txt");
+        let c = dir.path().join("c.txt");
+        fs::write(&a, b"a").unwrap();
+        fs::write(&b, b"b").unwrap();
+        fs::write(&c, b"c").unwrap();
+        let trash = Trash::at(dir.path().join("Trash"));
+
+        let j = Journal::at(dir.path().join("journal.jsonl"));
+        let moved = dir.path().join("out/a.txt");
+        fs::create_dir_all(moved.parent().unwrap()).unwrap();
+        move_one(&a, &moved).unwrap();
+        j.record(JournalOp::Mv, &a, Some(&moved), Fingerprint::of(&moved)).unwrap();
+        let copy = dir.path().join("out/b.txt");
+        fs::copy(&b, &copy).unwrap();
+        j.record(JournalOp::Copy, &b, Some(&copy), Fingerprint::of(&copy)).unwrap();
+        let trashed = trash.put(&c).unwrap().file_path();
+        j.record(JournalOp::Trash, &c, Some(&trashed), Fingerprint::of(&trashed)).unwrap();
+        j.record(JournalOp::Rm, &dir.path().join("gone"), None, Fingerprint::default()).unwrap();
+
+        let entries = j.read().unwrap();
+        assert_eq!(last_undoable(&entries).as_deref(), Some(j.run_id()));
+
+        let undo = Journal::at(j.path());
+        let dry = undo_run(&undo, &entries, j.run_id(), Some(&trash), true);
+        assert_eq!(dry, UndoSummary { reversed: 3, irreversible: 1, failures: 0 });
+        assert!(moved.exists());
+
+        let sum = undo_run(&undo, &entries, j.run_id(), Some(&trash), false);
+        assert_eq!(sum, UndoSummary { reversed: 3, irreversible: 1, failures: 0 });
+        assert_eq!(fs::read(&a).unwrap(), b"a");
+        assert!(!moved.exists());
+        assert!(!copy.exists() && b.exists());
+        assert_eq!(fs::read(&c).unwrap(), b"c");
+
+        let entries = j.read().unwrap();
+        let runs = runs(&entries);
+        assert!(runs[0].undone);
+        assert_eq!(runs[0].ops, 4);
+        assert!(last_undoable(&entries).is_none());
+    }
+
+    #[test]
+    fn changed_files_are_not_touched_and_retry_skips_done_entries() {
+        let dir = tempdir().unwrap();
+        let a = dir.path().join("a.txt");
+        let b = dir.path().join("b.txt");
+        fs::write(&a, b"a").unwrap();
+        fs::write(&b, b"b").unwrap();
+
+        let j = Journal::at(dir.path().join("journal.jsonl"));
+        let (ma, mb) = (dir.path().join("ma.txt"), dir.path().join("mb.txt"));
+        for (src, dst) in [(&a, &ma), (&b, &mb)] {
+            move_one(src, dst).unwrap();
+            j.record(JournalOp::Mv, src, Some(dst), Fingerprint::of(dst)).unwrap();
+        }
+        fs::write(&ma, b"edited").unwrap();
+
+        let undo = Journal::at(j.path());
+        let sum = undo_run(&undo, &j.read().unwrap(), j.run_id(), None, false);
+        assert_eq!(sum, UndoSummary { reversed: 1, irreversible: 0, failures: 1 });
+        assert!(b.exists() && ma.exists() && !a.exists());
+        assert_eq!(last_undoable(&j.read().unwrap()).as_deref(), Some(j.run_id()));
+
+        fs::write(&ma, b"a").unwrap();
+        let sum = undo_run(&undo, &j.read().unwrap(), j.run_id(), None, false);
+        assert_eq!(sum, UndoSummary { reversed: 1, irreversible: 0, failures: 0 });
+        assert!(a.exists() && b.exists());
+    }
+}
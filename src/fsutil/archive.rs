@@ -0,0 +1,619 @@
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use walkdir::WalkDir;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// Container + codec, derived from the archive file name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Tar,
+    TarGz,
+    TarZst,
+    Zip,
+}
+
+impl ArchiveFormat {
+    /// Detect the format from `.tar`, `.tar.gz`, `.tgz`, `.tar.zst` or `.zip`.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+        if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(Self::TarGz)
+        } else if name.ends_with(".tar.zst") || name.ends_with(".tzst") {
+            Some(Self::TarZst)
+        } else if name.ends_with(".tar") {
+            Some(Self::Tar)
+        } else if name.ends_with(".zip") {
+            Some(Self::Zip)
+        } else {
+            None
+        }
+    }
+}
+
+enum Inner {
+    Tar(tar::Builder<BufWriter<File>>),
+    TarGz(tar::Builder<GzEncoder<BufWriter<File>>>),
+    TarZst(tar::Builder<zstd::Encoder<'static, BufWriter<File>>>),
+    Zip(Box<ZipWriter<File>>),
+}
+
+/// Streaming archive writer. Entries are written as they are appended;
+/// call `finish` to flush trailers.
+pub struct ArchiveWriter {
+    inner: Inner,
+    level: Option<i64>,
+    entries: u64,
+    /// Canonical path of the archive itself, never added to it.
+    own: PathBuf,
+    /// Member names written so far; a name is never stored twice.
+    names: HashSet<PathBuf>,
+}
+
+impl ArchiveWriter {
+    /// Create a new archive at `path`. Fails if the file already exists.
+    /// `level` is codec specific (gzip 0-9, zstd 1-22, zip deflate 0-9); `None` uses the default.
+    pub fn create(path: &Path, level: Option<i64>) -> io::Result<Self> {
+        let format = ArchiveFormat::from_path(path).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown archive extension: {}", path.display()),
+            )
+        })?;
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().write(true).create_new(true).open(path)?;
+        let own = fs::canonicalize(path)?;
+
+        let inner = match format {
+            ArchiveFormat::Tar => Inner::Tar(tar_builder(BufWriter::new(file))),
+            ArchiveFormat::TarGz => {
+                let lvl = level.map_or(flate2::Compression::default(), |l| {
+                    flate2::Compression::new(l.clamp(0, 9) as u32)
+                });
+                Inner::TarGz(tar_builder(GzEncoder::new(BufWriter::new(file), lvl)))
+            }
+            ArchiveFormat::TarZst => {
+                let lvl = level.map_or(0, |l| l.clamp(1, 22) as i32);
+                Inner::TarZst(tar_builder(zstd::Encoder::new(BufWriter::new(file), lvl)?))
+            }
+            ArchiveFormat::Zip => Inner::Zip(Box::new(ZipWriter::new(file))),
+        };
+        Ok(Self { inner, level, entries: 0, own, names: HashSet::new() })
+    }
+
+    /// Number of entries written so far.
+    pub fn entries(&self) -> u64 {
+        self.entries
+    }
+
+    /// Append `path` (recursively for directories) under `name` inside the archive.
+    /// An empty `name` stores a directory's contents at the archive root.
+    /// The archive being written is skipped when it lies below `path`.
+    pub fn append(&mut self, path: &Path, name: &Path) -> io::Result<()> {
+        let own = self.own.clone();
+        let is_own = |p: &Path| p.file_name() == own.file_name() && fs::canonicalize(p).is_ok_and(|c| c == own);
+        for entry in WalkDir::new(path).sort_by_file_name().into_iter().filter_entry(|e| !is_own(e.path())) {
+            let entry = entry.map_err(io::Error::from)?;
+            let rel = entry.path().strip_prefix(path).unwrap_or(Path::new(""));
+            // `join("")` would add a trailing slash.
+            let entry_path = if rel.as_os_str().is_empty() { name.to_path_buf() } else { name.join(rel) };
+            if entry_path.as_os_str().is_empty() {
+                continue;
+            }
+            self.append_one(entry.path(), &entry_path)?;
+        }
+        Ok(())
+    }
+
+    fn append_one(&mut self, path: &Path, name: &Path) -> io::Result<()> {
+        if !self.names.insert(name.to_path_buf()) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("duplicate member name: {}", name.display()),
+            ));
+        }
+        match &mut self.inner {
+            Inner::Tar(b) => b.append_path_with_name(path, name)?,
+            Inner::TarGz(b) => b.append_path_with_name(path, name)?,
+            Inner::TarZst(b) => b.append_path_with_name(path, name)?,
+            Inner::Zip(z) => zip_append(z, path, name, self.level)?,
+        }
+        self.entries += 1;
+        Ok(())
+    }
+
+    /// Write trailers and flush everything to disk. On failure the partial
+    /// archive is removed, so a rerun can create it again.
+    pub fn finish(self) -> io::Result<()> {
+        let own = self.own.clone();
+        self.finish_inner().inspect_err(|_| {
+            let _ = fs::remove_file(&own);
+        })
+    }
+
+    fn finish_inner(self) -> io::Result<()> {
+        match self.inner {
+            Inner::Tar(b) => b.into_inner()?.into_inner()?.sync_all(),
+            Inner::TarGz(b) => b.into_inner()?.finish()?.into_inner()?.sync_all(),
+            Inner::TarZst(b) => b.into_inner()?.finish()?.into_inner()?.sync_all(),
+            Inner::Zip(z) => z.finish()?.sync_all(),
+        }
+    }
+}
+
+fn tar_builder<W: Write>(w: W) -> tar::Builder<W> {
+    let mut b = tar::Builder::new(w);
+    b.follow_symlinks(false);
+    b
+}
+
+fn zip_append(z: &mut ZipWriter<File>, path: &Path, name: &Path, level: Option<i64>) -> io::Result<()> {
+    let meta = fs::symlink_metadata(path)?;
+    let zname = zip_name(name);
+    let mut opts = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .compression_level(level.map(|l| l.clamp(0, 9)))
+        .large_file(meta.len() >= u32::MAX as u64);
+    if let Some(dt) = meta.modified().ok().and_then(zip_datetime) {
+        opts = opts.last_modified_time(dt);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        opts = opts.unix_permissions(meta.permissions().mode());
+    }
+
+    let ty = meta.file_type();
+    if ty.is_symlink() {
+        let target = fs::read_link(path)?;
+        z.add_symlink(zname, target.to_string_lossy(), opts)?;
+    } else if ty.is_dir() {
+        z.add_directory(format!("{}/", zname), opts)?;
+    } else {
+        z.start_file(zname, opts)?;
+        io::copy(&mut File::open(path)?, z)?;
+    }
+    Ok(())
+}
+
+/// Zip entry names always use `/` separators.
+fn zip_name(name: &Path) -> String {
+    name.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Convert a timestamp to the (UTC, 2-second resolution) zip format.
+fn zip_datetime(t: SystemTime) -> Option<zip::DateTime> {
+    let secs = t.duration_since(UNIX_EPOCH).ok()?.as_secs();
+    let (days, rem) = (secs / 86400, secs % 86400);
+
+    // Civil-from-days (Howard Hinnant), days since 1970-01-01.
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u8;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u8;
+    let year = (yoe + era * 400 + i64::from(month <= 2)) as u16;
+
+    zip::DateTime::from_date_and_time(
+        year,
+        month,
+        day,
+        (rem / 3600) as u8,
+        (rem % 3600 / 60) as u8,
+        (rem % 60) as u8,
+    )
+    .ok()
+}
+
+/// Name to store `path` under, relative to `base`. Falls back to the bare
+/// file name when `path` is not below `base`; `ArchiveWriter` refuses a
+/// name that is already taken, so such fallbacks cannot collide silently.
+pub fn entry_name(path: &Path, base: &Path) -> io::Result<PathBuf> {
+    let clean = |p: &Path| -> PathBuf {
+        p.components().filter(|c| !matches!(c, Component::CurDir)).collect()
+    };
+    let (p, b) = (clean(path), clean(base));
+    if let Ok(rel) = p.strip_prefix(&b) {
+        if rel.components().all(|c| matches!(c, Component::Normal(_))) {
+            return Ok(rel.to_path_buf());
+        }
+    }
+    path.file_name()
+        .map(PathBuf::from)
+        .ok_or_else(|| io::Error::other(format!("cannot derive entry name for {}", path.display())))
+}
+
+/// Kind of an archive member.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    File,
+    Dir,
+    Symlink,
+    /// Hardlinks, devices, fifos: listed but never extracted.
+    Other,
+}
+
+/// Metadata of one archive member, independent of the container format.
+#[derive(Debug, Clone)]
+pub struct EntryMeta {
+    /// Raw member name as stored (not yet sanitized).
+    pub name: PathBuf,
+    pub kind: EntryKind,
+    pub size: u64,
+    pub mtime: Option<SystemTime>,
+    pub mode: Option<u32>,
+    pub link_target: Option<PathBuf>,
+}
+
+/// Visit every member of the archive at `path` in stored order.
+/// The reader yields the member contents (empty for non-files).
+pub fn for_each_entry<F>(path: &Path, mut f: F) -> io::Result<()>
+where
+    F: FnMut(&EntryMeta, &mut dyn Read) -> io::Result<()>,
+{
+    let format = ArchiveFormat::from_path(path).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("unknown archive extension: {}", path.display()),
+        )
+    })?;
+    let file = File::open(path)?;
+    let reader: Box<dyn Read> = match format {
+        ArchiveFormat::Zip => return zip_for_each(file, f),
+        ArchiveFormat::Tar => Box::new(BufReader::new(file)),
+        ArchiveFormat::TarGz => Box::new(GzDecoder::new(BufReader::new(file))),
+        ArchiveFormat::TarZst => Box::new(zstd::Decoder::new(file)?),
+    };
+
+    let mut ar = tar::Archive::new(reader);
+    for entry in ar.entries()? {
+        let mut entry = entry?;
+        let header = entry.header();
+        let kind = match header.entry_type() {
+            tar::EntryType::Regular | tar::EntryType::Continuous => EntryKind::File,
+            tar::EntryType::Directory => EntryKind::Dir,
+            tar::EntryType::Symlink => EntryKind::Symlink,
+            _ => EntryKind::Other,
+        };
+        let meta = EntryMeta {
+            name: entry.path()?.into_owned(),
+            kind,
+            size: entry.size(),
+            mtime: header.mtime().ok().map(|t| UNIX_EPOCH + Duration::from_secs(t)),
+            mode: header.mode().ok(),
+            link_target: entry.link_name()?.map(|l| l.into_owned()),
+        };
+        f(&meta, &mut entry)?;
+    }
+    Ok(())
+}
+
+fn zip_for_each<F>(file: File, mut f: F) -> io::Result<()>
+where
+    F: FnMut(&EntryMeta, &mut dyn Read) -> io::Result<()>,
+{
+    let mut z = zip::ZipArchive::new(BufReader::new(file))?;
+    for i in 0..z.len() {
+        let mut zf = z.by_index(i)?;
+        let kind = if zf.is_dir() {
+            EntryKind::Dir
+        } else if zf.is_symlink() {
+            EntryKind::Symlink
+        } else {
+            EntryKind::File
+        };
+        let link_target = if kind == EntryKind::Symlink {
+            let mut target = String::new();
+            zf.read_to_string(&mut target)?;
+            Some(PathBuf::from(target))
+        } else {
+            None
+        };
+        let meta = EntryMeta {
+            name: PathBuf::from(zf.name().trim_end_matches('/')),
+            kind,
+            size: zf.size(),
+            mtime: zf.last_modified().and_then(zip_systime),
+            mode: zf.unix_mode(),
+            link_target,
+        };
+        f(&meta, &mut zf)?;
+    }
+    Ok(())
+}
+
+/// Convert a (UTC) zip timestamp back to `SystemTime`.
+fn zip_systime(dt: zip::DateTime) -> Option<SystemTime> {
+    // Days-from-civil (Howard Hinnant).
+    let (y, m, d) = (i64::from(dt.year()), i64::from(dt.month()), i64::from(dt.day()));
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = if m > 2 { m - 3 } else { m + 9 };
+    let doy = (153 * mp + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    let secs = days * 86400
+        + i64::from(dt.hour()) * 3600
+        + i64::from(dt.minute()) * 60
+        + i64::from(dt.second());
+    Some(UNIX_EPOCH + Duration::from_secs(u64::try_from(secs).ok()?))
+}
+
+/// Turn a stored member name into a safe relative path, dropping the first
+/// `strip` components. Rejects absolute names and `..`; `Ok(None)` means
+/// nothing is left after stripping.
+pub fn sanitize_entry_name(name: &Path, strip: usize) -> io::Result<Option<PathBuf>> {
+    let mut out = PathBuf::new();
+    let mut skipped = 0usize;
+    for c in name.components() {
+        match c {
+            Component::Normal(part) => {
+                if skipped < strip {
+                    skipped += 1;
+                } else {
+                    out.push(part);
+                }
+            }
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("unsafe member path: {}", name.display()),
+                ));
+            }
+        }
+    }
+    Ok(if out.as_os_str().is_empty() { None } else { Some(out) })
+}
+
+/// True when a symlink at `link` (relative to the extraction root `root`)
+/// pointing at `target` stays inside the root. A `..` is only accepted
+/// right after a component that already exists as a real directory below
+/// `root`, so no symlink, present or extracted later, can redirect it.
+pub fn link_stays_inside(root: &Path, link: &Path, target: &Path) -> bool {
+    if target.has_root() {
+        return false;
+    }
+    // Position relative to the root, starting at the directory holding the link.
+    let mut cur = link.parent().map(Path::to_path_buf).unwrap_or_default();
+    for c in target.components() {
+        match c {
+            Component::Normal(part) => cur.push(part),
+            Component::ParentDir => {
+                let real_dir = fs::symlink_metadata(root.join(&cur)).is_ok_and(|m| m.is_dir());
+                if cur.as_os_str().is_empty() || !real_dir {
+                    return false;
+                }
+                cur.pop();
+            }
+            Component::CurDir => {}
+            Component::RootDir | Component::Prefix(_) => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn detects_format_from_extension() {
+        assert_eq!(ArchiveFormat::from_path(Path::new("a.tar")), Some(ArchiveFormat::Tar));
+        assert_eq!(ArchiveFormat::from_path(Path::new("a.TAR.GZ")), Some(ArchiveFormat::TarGz));
+        assert_eq!(ArchiveFormat::from_path(Path::new("a.tgz")), Some(ArchiveFormat::TarGz));
+        assert_eq!(ArchiveFormat::from_path(Path::new("x/a.tar.zst")), Some(ArchiveFormat::TarZst));
+        assert_eq!(ArchiveFormat::from_path(Path::new("a.zip")), Some(ArchiveFormat::Zip));
+        assert_eq!(ArchiveFormat::from_path(Path::new("a.rar")), None);
+    }
+
+    #[test]
+    fn entry_names_relative_to_base() {
+        assert_eq!(entry_name(Path::new("root/a/b.txt"), Path::new("root")).unwrap(), Path::new("a/b.txt"));
+        assert_eq!(entry_name(Path::new("./a/b.txt"), Path::new(".")).unwrap(), Path::new("a/b.txt"));
+        assert_eq!(entry_name(Path::new("a/b.txt"), Path::new(".")).unwrap(), Path::new("a/b.txt"));
+        assert_eq!(entry_name(Path::new("/elsewhere/b.txt"), Path::new("root")).unwrap(), Path::new("b.txt"));
+        assert_eq!(entry_name(Path::new("."), Path::new(".")).unwrap(), Path::new(""));
+    }
+
+    #[test]
+    fn zip_datetime_conversion() {
+        // 2024-02-29T12:34:56Z
+        let t = UNIX_EPOCH + std::time::Duration::from_secs(1_709_210_096);
+        let dt = zip_datetime(t).unwrap();
+        assert_eq!((dt.year(), dt.month(), dt.day()), (2024, 2, 29));
+        assert_eq!((dt.hour(), dt.minute(), dt.second()), (12, 34, 56));
+        assert_eq!(zip_systime(dt), Some(t));
+    }
+
+    #[test]
+    fn writes_tar_gz_with_relative_names() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(src.join("sub")).unwrap();
+        fs::write(src.join("sub/a.txt"), b"hello").unwrap();
+        let out = dir.path().join("out.tar.gz");
+
+        let mut w = ArchiveWriter::create(&out, Some(9)).unwrap();
+        w.append(&src, Path::new("src")).unwrap();
+        assert_eq!(w.entries(), 3);
+        w.finish().unwrap();
+
+        let gz = flate2::read::GzDecoder::new(File::open(&out).unwrap());
+        let mut ar = tar::Archive::new(gz);
+        let mut names = Vec::new();
+        for e in ar.entries().unwrap() {
+            let mut e = e.unwrap();
+            let name = e.path().unwrap().to_string_lossy().trim_end_matches('/').to_string();
+            if name == "src/sub/a.txt" {
+                let mut s = String::new();
+                e.read_to_string(&mut s).unwrap();
+                assert_eq!(s, "hello");
+            }
+            names.push(name);
+        }
+        assert_eq!(names, vec!["src", "src/sub", "src/sub/a.txt"]);
+    }
+
+    #[test]
+    fn never_adds_the_archive_to_itself() {
+        let dir = tempdir().unwrap();
+        let d = dir.path().join("d");
+        fs::create_dir(&d).unwrap();
+        fs::write(d.join("a.txt"), b"a").unwrap();
+
+        // The second round sees the first archive as an ordinary file.
+        for (name, expected) in [("out.tar", &["d", "d/a.txt"][..]), ("out.zip", &["d", "d/a.txt", "d/out.tar"][..])] {
+            let out = d.join(name);
+            let mut w = ArchiveWriter::create(&out, None).unwrap();
+            w.append(&d, Path::new("d")).unwrap();
+            // Named through a different spelling of the same directory.
+            w.append(&d.join("../d/").join(name), Path::new("again")).unwrap();
+            w.finish().unwrap();
+
+            let mut names = Vec::new();
+            for_each_entry(&out, |m, _| {
+                names.push(m.name.to_string_lossy().into_owned());
+                Ok(())
+            })
+            .unwrap();
+            assert_eq!(names, expected, "{name}");
+        }
+    }
+
+    #[test]
+    fn rejects_duplicate_member_names() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("x")).unwrap();
+        fs::create_dir_all(dir.path().join("y")).unwrap();
+        fs::write(dir.path().join("x/a.txt"), b"x").unwrap();
+        fs::write(dir.path().join("y/a.txt"), b"y").unwrap();
+        let base = dir.path().join("base");
+
+        let mut w = ArchiveWriter::create(&dir.path().join("out.tar"), None).unwrap();
+        for p in ["x/a.txt", "y/a.txt"].map(|n| dir.path().join(n)) {
+            let name = entry_name(&p, &base).unwrap();
+            let res = w.append(&p, &name);
+            assert_eq!(res.is_err(), p.ends_with("y/a.txt"));
+        }
+        assert_eq!(w.entries(), 1);
+    }
+
+    #[test]
+    fn writes_zip_and_tar_zst() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("a.txt");
+        fs::write(&file, b"zipped").unwrap();
+
+        let zip_path = dir.path().join("out.zip");
+        let mut w = ArchiveWriter::create(&zip_path, None).unwrap();
+        w.append(&file, Path::new("docs/a.txt")).unwrap();
+        w.finish().unwrap();
+
+        let mut z = zip::ZipArchive::new(File::open(&zip_path).unwrap()).unwrap();
+        let mut s = String::new();
+        z.by_name("docs/a.txt").unwrap().read_to_string(&mut s).unwrap();
+        assert_eq!(s, "zipped");
+
+        let zst_path = dir.path().join("out.tar.zst");
+        let mut w = ArchiveWriter::create(&zst_path, Some(3)).unwrap();
+        w.append(&file, Path::new("a.txt")).unwrap();
+        w.finish().unwrap();
+
+        let dec = zstd::Decoder::new(File::open(&zst_path).unwrap()).unwrap();
+        let mut ar = tar::Archive::new(dec);
+        assert_eq!(ar.entries().unwrap().count(), 1);
+    }
+
+    #[test]
+    fn refuses_to_overwrite_existing_archive() {
+        let dir = tempdir().unwrap();
+        let out = dir.path().join("out.tar");
+        fs::write(&out, b"").unwrap();
+        let err = ArchiveWriter::create(&out, None).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+    }
+
+    #[test]
+    fn sanitizes_member_names() {
+        assert_eq!(sanitize_entry_name(Path::new("./a/b.txt"), 0).unwrap(), Some(PathBuf::from("a/b.txt")));
+        assert_eq!(sanitize_entry_name(Path::new("top/a/b.txt"), 1).unwrap(), Some(PathBuf::from("a/b.txt")));
+        assert_eq!(sanitize_entry_name(Path::new("top"), 1).unwrap(), None);
+        assert!(sanitize_entry_name(Path::new("../evil"), 0).is_err());
+        assert!(sanitize_entry_name(Path::new("a/../../evil"), 0).is_err());
+        assert!(sanitize_entry_name(Path::new("/etc/passwd"), 0).is_err());
+    }
+
+    #[test]
+    fn symlink_escape_detection() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("a/b")).unwrap();
+        let inside = |link: &str, target: &str| link_stays_inside(root, Path::new(link), Path::new(target));
+        assert!(inside("a/link", "../b.txt"));
+        assert!(inside("link", "sub/./x"));
+        assert!(inside("link", "a/b/../../x"));
+        assert!(!inside("link", "../x"));
+        assert!(!inside("a/link", "../../x"));
+        assert!(!inside("link", "/etc/passwd"));
+        // `..` after a missing directory or a symlink is refused.
+        assert!(!inside("link", "missing/../x"));
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(".", root.join("dot")).unwrap();
+            assert!(inside("link", "dot/x"));
+            assert!(!inside("link", "dot/../x"));
+        }
+    }
+
+    #[test]
+    fn reads_back_every_format() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(src.join("sub")).unwrap();
+        fs::write(src.join("sub/a.txt"), b"hello").unwrap();
+
+        for name in ["out.tar", "out.tgz", "out.tar.zst", "out.zip"] {
+            let out = dir.path().join(name);
+            let mut w = ArchiveWriter::create(&out, None).unwrap();
+            w.append(&src, Path::new("src")).unwrap();
+            w.finish().unwrap();
+
+            let mut seen = Vec::new();
+            for_each_entry(&out, |meta, r| {
+                let mut body = String::new();
+                r.read_to_string(&mut body)?;
+                let name = meta.name.to_string_lossy().trim_end_matches('/').to_string();
+                seen.push((name, meta.kind, body));
+                Ok(())
+            })
+            .unwrap();
+            assert_eq!(
+                seen,
+                vec![
+                    ("src".to_string(), EntryKind::Dir, String::new()),
+                    ("src/sub".to_string(), EntryKind::Dir, String::new()),
+                    ("src/sub/a.txt".to_string(), EntryKind::File, "hello".to_string()),
+                ],
+                "{}",
+                name
+            );
+        }
+    }
+}
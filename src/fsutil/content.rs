@@ -0,0 +1,109 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+
+use regex::bytes::{Regex, RegexBuilder};
+
+/// Files with a NUL byte in their first this many bytes count as binary.
+const BINARY_PROBE: usize = 8 * 1024;
+
+/// Default for `ContentSearch::max_bytes`.
+pub const DEFAULT_MAX_SCAN: u64 = 16 * 1024 * 1024;
+
+/// A text or regex searched for line by line in file contents.
+#[derive(Clone, Debug)]
+pub struct ContentSearch {
+    re: Regex,
+    /// Stop reading a file after this many bytes.
+    pub max_bytes: u64,
+    /// Also search files that look binary.
+    pub binary: bool,
+}
+
+impl ContentSearch {
+    /// Search for `pattern` as a regex, or as plain text when `literal`.
+    pub fn new(pattern: &str, literal: bool, ignore_case: bool) -> Result<Self, regex::Error> {
+        let src = if literal { regex::escape(pattern) } else { pattern.to_string() };
+        let re = RegexBuilder::new(&src).case_insensitive(ignore_case).build()?;
+        Ok(Self { re, max_bytes: DEFAULT_MAX_SCAN, binary: false })
+    }
+
+    /// Whether some line of the file matches; unreadable files do not.
+    pub fn is_match(&self, path: &Path) -> bool {
+        self.scan(path, false).is_ok_and(|lines| !lines.is_empty())
+    }
+
+    /// 1-based numbers of the matching lines (none for skipped binary files).
+    pub fn matching_lines(&self, path: &Path) -> io::Result<Vec<u64>> {
+        self.scan(path, true)
+    }
+
+    fn scan(&self, path: &Path, all: bool) -> io::Result<Vec<u64>> {
+        let mut r = BufReader::new(File::open(path)?.take(self.max_bytes));
+        if !self.binary {
+            // Fill the buffer without consuming it to peek at the start.
+            let head = r.fill_buf()?;
+            if head[..head.len().min(BINARY_PROBE)].contains(&0) {
+                return Ok(Vec::new());
+            }
+        }
+        let mut found = Vec::new();
+        let mut line = Vec::new();
+        let mut n = 0;
+        loop {
+            line.clear();
+            if r.read_until(b'\n', &mut line)? == 0 {
+                return Ok(found);
+            }
+            n += 1;
+            let text = line.strip_suffix(b"\n").unwrap_or(&line);
+            if self.re.is_match(text.strip_suffix(b"\r").unwrap_or(text)) {
+                found.push(n);
+                if !all {
+                    return Ok(found);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn literal_regex_and_line_numbers() {
+        let dir = tempdir().unwrap();
+        let f = dir.path().join("a.txt");
+        fs::write(&f, "alpha\r\nTODO: beta\nfoo.bar\nTODO gamma\n").unwrap();
+
+        let todo = ContentSearch::new("todo", true, true).unwrap();
+        assert_eq!(todo.matching_lines(&f).unwrap(), [2, 4]);
+        assert!(!ContentSearch::new("todo", true, false).unwrap().is_match(&f));
+
+        // Literal text is not a regex; line anchors see lines without `\r\n`.
+        assert_eq!(ContentSearch::new("o.b", true, false).unwrap().matching_lines(&f).unwrap(), [3]);
+        assert_eq!(ContentSearch::new("^alpha$", false, false).unwrap().matching_lines(&f).unwrap(), [1]);
+        assert!(!ContentSearch::new("x", true, false).unwrap().is_match(&dir.path().join("missing")));
+    }
+
+    #[test]
+    fn binary_files_and_scan_limit() {
+        let dir = tempdir().unwrap();
+        let bin = dir.path().join("a.bin");
+        fs::write(&bin, b"\x00\x01needle").unwrap();
+        let mut s = ContentSearch::new("needle", true, false).unwrap();
+        assert!(!s.is_match(&bin));
+        s.binary = true;
+        assert!(s.is_match(&bin));
+
+        let big = dir.path().join("big.txt");
+        fs::write(&big, format!("{}\nneedle\n", "x".repeat(100))).unwrap();
+        s.max_bytes = 50;
+        assert!(!s.is_match(&big));
+        s.max_bytes = 200;
+        assert!(s.is_match(&big));
+    }
+}
@@ -0,0 +1,263 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use sha2::{Digest, Sha256};
+
+use super::hash::{hex, sha256_file};
+use super::pathinfo::PathInfo;
+
+/// Bytes hashed from each end of a file in the partial-hash pass.
+const PARTIAL_BLOCK: u64 = 4096;
+
+/// Files with identical contents. `files` has at least two entries, none
+/// of them hard links to each other.
+pub struct DupGroup {
+    pub size: u64,
+    /// SHA-256 of the contents, as hex.
+    pub sha256: String,
+    pub files: Vec<PathInfo>,
+}
+
+impl DupGroup {
+    /// Bytes freed by keeping a single copy.
+    pub fn wasted(&self) -> u64 {
+        self.size * (self.files.len() as u64 - 1)
+    }
+
+    /// Order `files` so the one to keep comes first.
+    pub fn sort_by(&mut self, keep: Keep) {
+        self.files.sort_by(|a, b| keep.cmp(a, b));
+    }
+}
+
+/// Which copy of a duplicate group survives.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Keep {
+    /// Earliest mtime.
+    #[default]
+    Oldest,
+    /// Latest mtime.
+    Newest,
+    /// Fewest characters in the path.
+    Shortest,
+}
+
+impl Keep {
+    /// Ties (and missing mtimes, which rank last) fall back to the path.
+    fn cmp(self, a: &PathInfo, b: &PathInfo) -> std::cmp::Ordering {
+        let by = match self {
+            Self::Oldest => a.mtime.is_none().cmp(&b.mtime.is_none()).then(a.mtime.cmp(&b.mtime)),
+            Self::Newest => a.mtime.is_none().cmp(&b.mtime.is_none()).then(b.mtime.cmp(&a.mtime)),
+            Self::Shortest => a.path.as_os_str().len().cmp(&b.path.as_os_str().len()),
+        };
+        by.then_with(|| a.path.cmp(&b.path))
+    }
+}
+
+impl FromStr for Keep {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "oldest" => Ok(Self::Oldest),
+            "newest" => Ok(Self::Newest),
+            "shortest" => Ok(Self::Shortest),
+            _ => Err(format!("unknown keep rule: {s} (expected oldest, newest or shortest)")),
+        }
+    }
+}
+
+/// Result of `find_duplicates`.
+#[derive(Default)]
+pub struct Dupes {
+    /// Largest waste first, then by first path.
+    pub groups: Vec<DupGroup>,
+    /// Files that could not be read while hashing.
+    pub errors: Vec<(PathBuf, io::Error)>,
+}
+
+/// Group regular files with identical contents. Candidates are narrowed
+/// by size, then by a hash of the first and last `PARTIAL_BLOCK` bytes,
+/// and only the remaining ones are hashed in full. Directories, symlinks
+/// and empty files are ignored; hard links to the same file count once.
+pub fn find_duplicates(infos: impl IntoIterator<Item = PathInfo>) -> Dupes {
+    let mut out = Dupes::default();
+    let mut by_size: HashMap<u64, Vec<PathInfo>> = HashMap::new();
+    let mut seen = HashSet::new();
+    for info in infos {
+        let Some(size) = info.size.filter(|&s| s > 0 && info.is_file) else { continue };
+        let Ok(md) = fs::symlink_metadata(&info.path) else { continue };
+        if md.is_file() && file_id(&md).is_none_or(|id| seen.insert(id)) {
+            by_size.entry(size).or_default().push(info);
+        }
+    }
+
+    for (size, same_size) in by_size {
+        if same_size.len() < 2 {
+            continue;
+        }
+        // Small files are read completely by the partial hash already.
+        let whole = size <= 2 * PARTIAL_BLOCK;
+        for (partial, candidates) in split_by(same_size, |p| partial_hash(p, size), &mut out.errors) {
+            let groups = if whole {
+                vec![(partial, candidates)]
+            } else {
+                let mut full: HashMap<String, Vec<PathInfo>> = HashMap::new();
+                for info in candidates {
+                    match sha256_file(&info.path) {
+                        Ok(h) => full.entry(h).or_default().push(info),
+                        Err(e) => out.errors.push((info.path, e)),
+                    }
+                }
+                full.into_iter().filter(|(_, v)| v.len() > 1).collect()
+            };
+            for (sha256, mut files) in groups {
+                files.sort_by(|a, b| a.path.cmp(&b.path));
+                out.groups.push(DupGroup { size, sha256, files });
+            }
+        }
+    }
+    out.groups.sort_by(|a, b| b.wasted().cmp(&a.wasted()).then_with(|| a.files[0].path.cmp(&b.files[0].path)));
+    out
+}
+
+/// Split `infos` by `key`, keeping only buckets with more than one entry.
+fn split_by<K, F>(infos: Vec<PathInfo>, key: F, errors: &mut Vec<(PathBuf, io::Error)>) -> Vec<(K, Vec<PathInfo>)>
+where
+    K: std::hash::Hash + Eq,
+    F: Fn(&Path) -> io::Result<K>,
+{
+    let mut buckets: HashMap<K, Vec<PathInfo>> = HashMap::new();
+    for info in infos {
+        match key(&info.path) {
+            Ok(k) => buckets.entry(k).or_default().push(info),
+            Err(e) => errors.push((info.path, e)),
+        }
+    }
+    buckets.into_iter().filter(|(_, v)| v.len() > 1).collect()
+}
+
+/// SHA-256 of the first and last `PARTIAL_BLOCK` bytes (the whole file
+/// when it is no longer than two blocks), as hex.
+fn partial_hash(path: &Path, size: u64) -> io::Result<String> {
+    let mut f = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = Vec::new();
+    if size <= 2 * PARTIAL_BLOCK {
+        f.read_to_end(&mut buf)?;
+        hasher.update(&buf);
+    } else {
+        (&mut f).take(PARTIAL_BLOCK).read_to_end(&mut buf)?;
+        f.seek(SeekFrom::End(-(PARTIAL_BLOCK as i64)))?;
+        f.take(PARTIAL_BLOCK).read_to_end(&mut buf)?;
+        hasher.update(&buf);
+    }
+    Ok(hex(&hasher.finalize()))
+}
+
+/// Identity of the underlying file, so hard links are not reported as
+/// duplicates of each other.
+#[cfg(unix)]
+fn file_id(md: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((md.dev(), md.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_md: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+    use tempfile::tempdir;
+
+    fn infos(paths: &[PathBuf]) -> Vec<PathInfo> {
+        paths.iter().map(|p| PathInfo::from_fs(p, SystemTime::now()).unwrap()).collect()
+    }
+
+    fn names(g: &DupGroup) -> Vec<String> {
+        g.files.iter().map(|i| i.file_name.clone()).collect()
+    }
+
+    #[test]
+    fn groups_by_size_then_partial_then_full_hash() {
+        let dir = tempdir().unwrap();
+        let big = |head: u8, mid: u8| {
+            let mut v = vec![head; 10_000];
+            v[5_000] = mid;
+            v
+        };
+        let files = [
+            ("a.bin", big(1, 0)),
+            ("b.bin", big(1, 0)),
+            // Same ends, different middle: only the full hash tells them apart.
+            ("c.bin", big(1, 9)),
+            ("d.txt", b"hello".to_vec()),
+            ("e.txt", b"hello".to_vec()),
+            ("f.txt", b"world".to_vec()),
+            ("g.txt", Vec::new()),
+            ("h.txt", Vec::new()),
+        ];
+        let paths: Vec<PathBuf> = files
+            .iter()
+            .map(|(n, data)| {
+                let p = dir.path().join(n);
+                fs::write(&p, data).unwrap();
+                p
+            })
+            .collect();
+
+        let found = find_duplicates(infos(&paths));
+        assert!(found.errors.is_empty());
+        assert_eq!(found.groups.len(), 2);
+        assert_eq!(names(&found.groups[0]), ["a.bin", "b.bin"]);
+        assert_eq!(found.groups[0].wasted(), 10_000);
+        assert_eq!(found.groups[0].sha256, sha256_file(&paths[0]).unwrap());
+        assert_eq!(names(&found.groups[1]), ["d.txt", "e.txt"]);
+        assert_eq!(found.groups[1].sha256, sha256_file(&paths[3]).unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn hard_links_and_symlinks_are_not_duplicates() {
+        let dir = tempdir().unwrap();
+        let a = dir.path().join("a");
+        fs::write(&a, b"same").unwrap();
+        fs::hard_link(&a, dir.path().join("b")).unwrap();
+        std::os::unix::fs::symlink(&a, dir.path().join("c")).unwrap();
+        let paths = ["a", "b", "c"].map(|n| dir.path().join(n));
+        assert!(find_duplicates(infos(&paths)).groups.is_empty());
+
+        fs::write(dir.path().join("d"), b"same").unwrap();
+        let found = find_duplicates(infos(&[paths[0].clone(), paths[1].clone(), dir.path().join("d")]));
+        assert_eq!(names(&found.groups[0]), ["a", "d"]);
+    }
+
+    #[test]
+    fn keep_rules() {
+        let now = SystemTime::now();
+        let t = |secs| Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs));
+        let mut g = DupGroup {
+            size: 1,
+            sha256: String::new(),
+            files: vec![
+                PathInfo::from_parts(Path::new("/r/deep/copy.txt"), true, false, Some(1), t(100), now),
+                PathInfo::from_parts(Path::new("/r/b.txt"), true, false, Some(1), t(300), now),
+                PathInfo::from_parts(Path::new("/r/a.txt"), true, false, Some(1), None, now),
+            ],
+        };
+        g.sort_by(Keep::Oldest);
+        assert_eq!(names(&g), ["copy.txt", "b.txt", "a.txt"]);
+        g.sort_by(Keep::Newest);
+        assert_eq!(names(&g), ["b.txt", "copy.txt", "a.txt"]);
+        g.sort_by(Keep::Shortest);
+        assert_eq!(names(&g), ["a.txt", "b.txt", "copy.txt"]);
+        assert!("largest".parse::<Keep>().is_err());
+    }
+}
@@ -1,18 +1,224 @@
-use std::fs;
-use std::path::{Path, PathBuf};
-
-pub fn file_name(path: &str) -> &str {
-    path.rsplit_once('/').map(|(_, base)| base).unwrap_or(path)
-}
-
-pub fn normalize(path: &str) -> PathBuf {
-    Path::new(path).to_path_buf()
-}
-
-pub fn move_one(src: &str, dst: &str) -> std::io::Result<()> {
-    fs::rename(Path::new(src), Path::new(dst))
-}
-
-pub fn remove_one(path: &str) -> std::io::Result<()> {
-    fs::remove_file(Path::new(path))
-}
\ No newline at end of file
+use std::fs::{self, File, Metadata};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use filetime::FileTime;
+use serde::{Deserialize, Serialize};
+
+pub fn file_name(path: &str) -> &str {
+    path.rsplit_once('/').map(|(_, base)| base).unwrap_or(path)
+}
+
+pub fn normalize(path: &str) -> PathBuf {
+    Path::new(path).to_path_buf()
+}
+
+/// Move `src` to `dst`. Falls back to copy + fsync + delete when `rename`
+/// cannot cross filesystems (EXDEV).
+pub fn move_one(src: &Path, dst: &Path) -> io::Result<()> {
+    move_with(src, dst, |a, b| fs::rename(a, b))
+}
+
+/// `move_one` with the rename step supplied by the caller.
+fn move_with<R>(src: &Path, dst: &Path, rename: R) -> io::Result<()>
+where
+    R: Fn(&Path, &Path) -> io::Result<()>,
+{
+    match rename(src, dst) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            let opts = CopyOptions { sync: true, ..CopyOptions::default() };
+            copy_tree(src, dst, &opts)?;
+            if fs::symlink_metadata(src)?.is_dir() {
+                fs::remove_dir_all(src)
+            } else {
+                fs::remove_file(src)
+            }
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Replace `path` with a hard link to `target`. The link is created under
+/// a temporary name beside `path` and renamed over it, so `path` never
+/// goes missing.
+pub fn replace_with_hard_link(path: &Path, target: &Path) -> io::Result<()> {
+    let name = path.file_name().ok_or_else(|| io::Error::other("path has no file name"))?;
+    let mut tmp_name = std::ffi::OsString::from(".");
+    tmp_name.push(name);
+    tmp_name.push(".file-rs-link");
+    let tmp = path.with_file_name(tmp_name);
+    fs::hard_link(target, &tmp)?;
+    fs::rename(&tmp, path).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })
+}
+
+pub fn remove_one(path: &str) -> std::io::Result<()> {
+    fs::remove_file(Path::new(path))
+}
+
+/// What to carry over from the source when copying.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CopyOptions {
+    /// mtime / atime
+    pub times: bool,
+    /// Permission bits
+    pub mode: bool,
+    /// uid / gid (usually needs root)
+    pub owner: bool,
+    /// Extended attributes
+    pub xattrs: bool,
+    /// fsync every copied file before returning
+    pub sync: bool,
+}
+
+impl Default for CopyOptions {
+    fn default() -> Self {
+        Self { times: true, mode: true, owner: false, xattrs: false, sync: false }
+    }
+}
+
+/// Copy a file, symlink or directory tree from `src` to `dst`.
+/// Symlinks are recreated, not followed. Returns the number of bytes copied.
+pub fn copy_tree(src: &Path, dst: &Path, opts: &CopyOptions) -> io::Result<u64> {
+    let meta = fs::symlink_metadata(src)?;
+    let ty = meta.file_type();
+
+    // Replace an existing link (or, for a link, any non-directory) rather
+    // than writing through it or failing with EEXIST.
+    if let Ok(existing) = fs::symlink_metadata(dst) {
+        if existing.file_type().is_symlink() || (ty.is_symlink() && !existing.is_dir()) {
+            fs::remove_file(dst)?;
+        }
+    }
+
+    if ty.is_symlink() {
+        let target = fs::read_link(src)?;
+        symlink(&target, dst)?;
+        preserve(src, dst, &meta, opts)?;
+        return Ok(0);
+    }
+
+    if ty.is_dir() {
+        fs::create_dir_all(dst)?;
+        let mut bytes = 0u64;
+        for entry in fs::read_dir(src)? {
+            let entry = entry?;
+            bytes += copy_tree(&entry.path(), &dst.join(entry.file_name()), opts)?;
+        }
+        // Directory times last, after children stopped touching it.
+        preserve(src, dst, &meta, opts)?;
+        return Ok(bytes);
+    }
+
+    if !ty.is_file() {
+        return Err(io::Error::other(format!(
+            "unsupported file type (special): {}",
+            src.display()
+        )));
+    }
+
+    let bytes = fs::copy(src, dst)?;
+    if opts.sync {
+        File::open(dst)?.sync_all()?;
+    }
+    preserve(src, dst, &meta, opts)?;
+    Ok(bytes)
+}
+
+/// Apply the metadata selected in `opts` from `src` to `dst`.
+fn preserve(src: &Path, dst: &Path, meta: &Metadata, opts: &CopyOptions) -> io::Result<()> {
+    let is_link = meta.file_type().is_symlink();
+
+    if opts.owner {
+        set_owner(dst, meta)?;
+    }
+    if opts.xattrs && !is_link {
+        copy_xattrs(src, dst)?;
+    }
+    // Mode after owner: chown may clear setuid/setgid bits.
+    if opts.mode && !is_link {
+        fs::set_permissions(dst, meta.permissions())?;
+    }
+    if opts.times {
+        let atime = FileTime::from_last_access_time(meta);
+        let mtime = FileTime::from_last_modification_time(meta);
+        if is_link {
+            filetime::set_symlink_file_times(dst, atime, mtime)?;
+        } else {
+            filetime::set_file_times(dst, atime, mtime)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(not(unix))]
+fn symlink(_target: &Path, _link: &Path) -> io::Result<()> {
+    Err(io::Error::other("symlinks are not supported on this platform"))
+}
+
+#[cfg(unix)]
+fn set_owner(dst: &Path, meta: &Metadata) -> io::Result<()> {
+    use std::os::unix::fs::MetadataExt;
+    std::os::unix::fs::lchown(dst, Some(meta.uid()), Some(meta.gid()))
+}
+
+#[cfg(not(unix))]
+fn set_owner(_dst: &Path, _meta: &Metadata) -> io::Result<()> {
+    Err(io::Error::other("preserving ownership is not supported on this platform"))
+}
+
+#[cfg(unix)]
+fn copy_xattrs(src: &Path, dst: &Path) -> io::Result<()> {
+    for name in xattr::list(src)? {
+        if let Some(value) = xattr::get(src, &name)? {
+            xattr::set(dst, &name, &value)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn copy_xattrs(_src: &Path, _dst: &Path) -> io::Result<()> {
+    Err(io::Error::other("preserving xattrs is not supported on this platform"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn cross_device_rename_falls_back_to_copy_and_delete() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(src.join("nested")).unwrap();
+        fs::write(src.join("a.txt"), b"a").unwrap();
+        fs::write(src.join("nested/b.txt"), b"b").unwrap();
+        let dst = dir.path().join("dst");
+        let exdev = |_: &Path, _: &Path| Err(io::Error::from(io::ErrorKind::CrossesDevices));
+
+        move_with(&src, &dst, exdev).unwrap();
+        assert!(!src.exists());
+        assert_eq!(fs::read(dst.join("a.txt")).unwrap(), b"a");
+        assert_eq!(fs::read(dst.join("nested/b.txt")).unwrap(), b"b");
+
+        let file = dir.path().join("c.txt");
+        fs::write(&file, b"c").unwrap();
+        move_with(&file, &dir.path().join("d.txt"), exdev).unwrap();
+        assert!(!file.exists());
+        assert_eq!(fs::read(dir.path().join("d.txt")).unwrap(), b"c");
+
+        // Other errors are passed through and leave the source alone.
+        let denied = |_: &Path, _: &Path| Err(io::Error::from(io::ErrorKind::PermissionDenied));
+        let err = move_with(&dst, &dir.path().join("e"), denied).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        assert!(dst.exists());
+    }
+}
@@ -1,276 +1,615 @@
-use super::pathinfo;
-
-#[derive(Default, Clone)]
-pub struct Filter {
-    pub contains: Vec<String>,
-    pub prefix:   Vec<String>,
-    pub suffix:   Vec<String>,
-    pub exts:     Vec<String>, // lowercased without dot
-    pub ty_file:  bool,
-    pub ty_dir:   bool,
-    pub min_age_secs: Option<u64>,
-    pub max_age_secs: Option<u64>,
-    pub min_size: Option<u64>,
-    pub max_size: Option<u64>,
-    pub include_hidden: bool,
-}
-
-pub trait Matcher {
-    fn matches(&self, info: &pathinfo::PathInfo) -> bool;
-}
-
-impl Matcher for Filter {
-    fn matches(&self, info: &pathinfo::PathInfo) -> bool {
-        // type
-        if self.ty_file && !info.is_file { return false; }
-        if self.ty_dir  && !info.is_dir  { return false; }
-
-        // hidden
-        if !self.include_hidden && info.hidden { return false; }
-
-        // name-based
-        if !self.contains.is_empty()
-            && !self.contains.iter().any(|s| info.file_name.contains(&s.to_ascii_lowercase())) {
-            return false;
-        }
-        if !self.prefix.is_empty()
-            && !self.prefix.iter().any(|p| info.file_name.starts_with(&p.to_ascii_lowercase())) {
-            return false;
-        }
-        if !self.suffix.is_empty()
-            && !self.suffix.iter().any(|s| info.file_name.ends_with(&s.to_ascii_lowercase())) {
-            return false;
-        }
-
-        // ext
-        if !self.exts.is_empty() {
-            if let Some(ext) = &info.ext {
-                if !self.exts.iter().any(|e| e == ext) { return false; }
-            } else { return false; }
-        }
-
-        // size
-        if let Some(min) = self.min_size {
-            if info.size.map_or(true, |sz| sz < min) { return false; }
-        }
-        if let Some(max) = self.max_size {
-            if info.size.map_or(true, |sz| sz > max) { return false; }
-        }
-
-        // age
-        if let Some(min_age) = self.min_age_secs {
-            if info.age_secs.map_or(true, |a| a < min_age) { return false; }
-        }
-        if let Some(max_age) = self.max_age_secs {
-            if info.age_secs.map_or(true, |a| a > max_age) { return false; }
-        }
-
-        true
-    }
-}
-
-pub fn merge(mut a: Filter, b: Filter) -> Filter {
-    a.contains.extend(b.contains);
-    a.prefix.extend(b.prefix);
-    a.suffix.extend(b.suffix);
-    a.exts.extend(b.exts);
-    if b.ty_file || b.ty_dir { a.ty_file = b.ty_file; a.ty_dir = b.ty_dir; }
-    if b.min_age_secs.is_some() { a.min_age_secs = b.min_age_secs; }
-    if b.max_age_secs.is_some() { a.max_age_secs = b.max_age_secs; }
-    if b.min_size.is_some()     { a.min_size = b.min_size; }
-    if b.max_size.is_some()     { a.max_size = b.max_size; }
-    if b.include_hidden         { a.include_hidden = true; }
-    a
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::path::PathBuf;
-
-    fn mk_info(
-        name: &str,
-        ext: Option<&str>,
-        is_file: bool,
-        size: Option<u64>,
-        age_secs: Option<u64>,
-        hidden: bool,
-    ) -> pathinfo::PathInfo {
-        pathinfo::PathInfo {
-            path: PathBuf::from(format!("/tmp/{}", name)),
-            file_name: name.to_ascii_lowercase(),
-            ext: ext.map(|e| e.to_ascii_lowercase()),
-            is_file,
-            is_dir: !is_file,
-            size,
-            age_secs,
-            hidden,
-        }
-    }
-
-    #[test]
-    fn name_contains_prefix_suffix() {
-        let mut f = Filter::default();
-        f.contains = vec!["report".into()];
-        f.prefix   = vec!["2024_".into()];
-        f.suffix   = vec![".txt".into()];
-
-        let ok = mk_info("2024_report.txt", Some("txt"), true, Some(10), Some(100), false);
-        assert!(f.matches(&ok));
-
-        let miss_contains = mk_info("2024_log.txt", Some("txt"), true, Some(10), Some(100), false);
-        assert!(!f.matches(&miss_contains));
-
-        let miss_prefix = mk_info("backup_report.txt", Some("txt"), true, Some(10), Some(100), false);
-        assert!(!f.matches(&miss_prefix));
-
-        let miss_suffix = mk_info("2024_report.md", Some("md"), true, Some(10), Some(100), false);
-        assert!(!f.matches(&miss_suffix));
-    }
-
-    #[test]
-    fn ext_filter() {
-        let mut f = Filter::default();
-        f.exts = vec!["txt".into(), "md".into()];
-
-        let txt = mk_info("note.txt", Some("TXT"), true, Some(1), Some(1), false);
-        let md  = mk_info("readme.md", Some("md"), true, Some(1), Some(1), false);
-        let log = mk_info("app.log", Some("log"), true, Some(1), Some(1), false);
-        let noext = mk_info("LICENSE", None, true, Some(1), Some(1), false);
-
-        assert!(f.matches(&txt));
-        assert!(f.matches(&md));
-        assert!(!f.matches(&log));
-        assert!(!f.matches(&noext));
-    }
-
-    #[test]
-    fn type_filters() {
-        let mut only_file = Filter::default();
-        only_file.ty_file = true;
-
-        let f1 = mk_info("a.txt", Some("txt"), true, Some(1), Some(1), false);
-        let d1 = mk_info("dir", None, false, None, None, false);
-
-        assert!(only_file.matches(&f1));
-        assert!(!only_file.matches(&d1));
-
-        // dir만 허용
-        let mut only_dir = Filter::default();
-        only_dir.ty_dir = true;
-
-        assert!(!only_dir.matches(&f1));
-        assert!(only_dir.matches(&d1));
-    }
-
-    #[test]
-    fn hidden_behavior() {
-        let f = Filter::default();
-        let hidden_file = mk_info(".env", Some("env"), true, Some(1), Some(1), true);
-        let visible_file = mk_info("env", Some("env"), true, Some(1), Some(1), false);
-
-        assert!(!f.matches(&hidden_file));
-        assert!(f.matches(&visible_file));
-
-        let mut f2 = Filter::default();
-        f2.include_hidden = true;
-        assert!(f2.matches(&hidden_file));
-    }
-
-    #[test]
-    fn size_bounds() {
-        let mut f = Filter::default();
-        f.min_size = Some(10);
-        f.max_size = Some(100);
-
-        let s5   = mk_info("a", Some("txt"), true, Some(5),   Some(1), false);
-        let s10  = mk_info("b", Some("txt"), true, Some(10),  Some(1), false);
-        let s50  = mk_info("c", Some("txt"), true, Some(50),  Some(1), false);
-        let s100 = mk_info("d", Some("txt"), true, Some(100), Some(1), false);
-        let s200 = mk_info("e", Some("txt"), true, Some(200), Some(1), false);
-
-        assert!(!f.matches(&s5));
-        assert!(f.matches(&s10));
-        assert!(f.matches(&s50));
-        assert!(f.matches(&s100));
-        assert!(!f.matches(&s200));
-
-        let dir = mk_info("dir", None, false, None, None, false);
-        assert!(!f.matches(&dir));
-    }
-
-    #[test]
-    fn age_bounds() {
-        let mut f = Filter::default();
-        f.min_age_secs = Some(60);
-        f.max_age_secs = Some(3600);
-
-        let a30   = mk_info("new",  Some("txt"), true, Some(1),  Some(30),   false);
-        let a60   = mk_info("ok1",  Some("txt"), true, Some(1),  Some(60),   false);
-        let a120  = mk_info("ok2",  Some("txt"), true, Some(1),  Some(120),  false);
-        let a3600 = mk_info("ok3",  Some("txt"), true, Some(1),  Some(3600), false);
-        let a7200 = mk_info("old",  Some("txt"), true, Some(1),  Some(7200), false);
-
-        assert!(!f.matches(&a30));
-        assert!(f.matches(&a60));
-        assert!(f.matches(&a120));
-        assert!(f.matches(&a3600));
-        assert!(!f.matches(&a7200));
-
-        let unknown_age = mk_info("x", Some("txt"), true, Some(1), None, false);
-        assert!(!f.matches(&unknown_age));
-    }
-
-    #[test]
-    fn merge_semantics() {
-        let mut a = Filter::default();
-        a.contains = vec!["log".into()];
-        a.min_size = Some(10);
-
-        let mut b = Filter::default();
-        b.contains = vec!["error".into()];
-        b.exts = vec!["txt".into()];
-        b.max_size = Some(100);
-        b.include_hidden = true;
-        b.ty_file = true;
-
-        let m = merge(a, b);
-        assert_eq!(m.contains, vec!["log".to_string(), "error".to_string()]);
-        assert_eq!(m.exts, vec!["txt".to_string()]);
-        assert_eq!(m.min_size, Some(10));
-        assert_eq!(m.max_size, Some(100));
-        assert!(m.include_hidden);
-        assert!(m.ty_file);
-        assert!(!m.ty_dir);
-    }
-
-    #[test]
-    fn combined_filters_pass_and_fail() {
-        let mut f = Filter::default();
-        f.ty_file = true;
-        f.exts = vec!["txt".into()];
-        f.contains = vec!["report".into()];
-        f.min_size = Some(1);
-        f.max_size = Some(100);
-        f.min_age_secs = Some(10);
-        f.max_age_secs = Some(1000);
-
-        let ok = mk_info("monthly_report.txt", Some("txt"), true, Some(50), Some(200), false);
-        assert!(f.matches(&ok));
-
-        let bad_ext = mk_info("monthly_report.md", Some("md"), true, Some(50), Some(200), false);
-        assert!(!f.matches(&bad_ext));
-
-        let bad_name = mk_info("monthly_summary.txt", Some("txt"), true, Some(50), Some(200), false);
-        assert!(!f.matches(&bad_name));
-
-        let bad_size = mk_info("monthly_report.txt", Some("txt"), true, Some(1000), Some(200), false);
-        assert!(!f.matches(&bad_size));
-
-        let bad_age = mk_info("monthly_report.txt", Some("txt"), true, Some(50), Some(5), false);
-        assert!(!f.matches(&bad_age));
-
-        let hidden = mk_info(".monthly_report.txt", Some("txt"), true, Some(50), Some(200), true);
-        assert!(!f.matches(&hidden));
-    }
+use std::path::{Path, PathBuf};
+
+use globset::{GlobBuilder, GlobMatcher};
+use regex::{Regex, RegexBuilder};
+
+use super::content::ContentSearch;
+use super::pathinfo;
+use super::select::Expr;
+
+#[derive(Default, Clone)]
+pub struct Filter {
+    pub contains: Vec<String>,
+    pub prefix:   Vec<String>,
+    pub suffix:   Vec<String>,
+    pub exts:     Vec<String>, // lowercased without dot
+    pub globs:    Vec<GlobPattern>,
+    /// Entries matching these globs are skipped, directories with their contents.
+    pub exclude:  Vec<GlobPattern>,
+    /// Directory names that are never descended into.
+    pub prune:    Vec<String>,
+    pub name_regex: Vec<Regex>, // against the file name
+    pub path_regex: Vec<Regex>, // against the path relative to `root`
+    /// Walk root; relative-path patterns are matched below it.
+    pub root: Option<PathBuf>,
+    /// Parsed `--select` expression, ANDed with the fields above.
+    pub expr: Option<Expr>,
+    /// Searched in file contents once every other test has passed.
+    pub content: Vec<ContentSearch>,
+    pub ty_file:  bool,
+    pub ty_dir:   bool,
+    pub min_age_secs: Option<u64>,
+    pub max_age_secs: Option<u64>,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    pub include_hidden: bool,
+}
+
+/// A compiled, case-insensitive glob (`*`, `?`, `[...]`, `{a,b}`, `**`).
+/// Patterns containing `/` match the path relative to the walk root;
+/// the others match the file name alone.
+#[derive(Clone, Debug)]
+pub struct GlobPattern {
+    matcher: GlobMatcher,
+    relative: bool,
+}
+
+impl GlobPattern {
+    pub fn new(pattern: &str) -> Result<Self, globset::Error> {
+        let matcher = GlobBuilder::new(pattern)
+            .case_insensitive(true)
+            .literal_separator(true)
+            .build()?
+            .compile_matcher();
+        Ok(Self { matcher, relative: pattern.contains('/') })
+    }
+
+    pub fn is_match(&self, path: &Path, root: Option<&Path>) -> bool {
+        if self.relative {
+            let rel = root.and_then(|r| path.strip_prefix(r).ok()).unwrap_or(path);
+            self.matcher.is_match(rel)
+        } else {
+            path.file_name().is_some_and(|n| self.matcher.is_match(n))
+        }
+    }
+}
+
+/// Whether `pattern` uses glob syntax rather than a plain name fragment.
+pub fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '[', '{'])
+}
+
+/// Compile a user regex, optionally case-insensitive.
+pub fn build_regex(pattern: &str, ignore_case: bool) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(pattern).case_insensitive(ignore_case).build()
+}
+
+pub trait Matcher {
+    fn matches(&self, info: &pathinfo::PathInfo) -> bool;
+
+    /// Whether nothing at or below this entry can match, so a walk may skip
+    /// the whole subtree. Defaults to false.
+    fn prune(&self, _info: &pathinfo::PathInfo) -> bool {
+        false
+    }
+
+    /// Match when both `self` and `other` match.
+    fn and<M: Matcher>(self, other: M) -> And<Self, M>
+    where
+        Self: Sized,
+    {
+        And(self, other)
+    }
+
+    /// Match when `self` or `other` matches.
+    fn or<M: Matcher>(self, other: M) -> Or<Self, M>
+    where
+        Self: Sized,
+    {
+        Or(self, other)
+    }
+
+    /// Match when `self` does not.
+    fn not(self) -> Not<Self>
+    where
+        Self: Sized,
+    {
+        Not(self)
+    }
+}
+
+/// Both matchers must match (the second is skipped if the first fails).
+#[derive(Clone, Debug)]
+pub struct And<A, B>(pub A, pub B);
+
+/// Either matcher must match (the second is skipped if the first matches).
+#[derive(Clone, Debug)]
+pub struct Or<A, B>(pub A, pub B);
+
+/// Inverts a matcher.
+#[derive(Clone, Debug)]
+pub struct Not<M>(pub M);
+
+impl<A: Matcher, B: Matcher> Matcher for And<A, B> {
+    fn matches(&self, info: &pathinfo::PathInfo) -> bool {
+        self.0.matches(info) && self.1.matches(info)
+    }
+
+    fn prune(&self, info: &pathinfo::PathInfo) -> bool {
+        self.0.prune(info) || self.1.prune(info)
+    }
+}
+
+impl<A: Matcher, B: Matcher> Matcher for Or<A, B> {
+    fn matches(&self, info: &pathinfo::PathInfo) -> bool {
+        self.0.matches(info) || self.1.matches(info)
+    }
+
+    fn prune(&self, info: &pathinfo::PathInfo) -> bool {
+        self.0.prune(info) && self.1.prune(info)
+    }
+}
+
+impl<M: Matcher> Matcher for Not<M> {
+    fn matches(&self, info: &pathinfo::PathInfo) -> bool {
+        !self.0.matches(info)
+    }
+}
+
+/// Any `Fn(&PathInfo) -> bool` closure is a matcher.
+impl<F: Fn(&pathinfo::PathInfo) -> bool> Matcher for F {
+    fn matches(&self, info: &pathinfo::PathInfo) -> bool {
+        self(info)
+    }
+}
+
+/// Boxed matchers, for selectors assembled at runtime.
+impl Matcher for Box<dyn Matcher> {
+    fn matches(&self, info: &pathinfo::PathInfo) -> bool {
+        (**self).matches(info)
+    }
+
+    fn prune(&self, info: &pathinfo::PathInfo) -> bool {
+        (**self).prune(info)
+    }
+}
+
+impl Matcher for Box<dyn Matcher + Send + Sync> {
+    fn matches(&self, info: &pathinfo::PathInfo) -> bool {
+        (**self).matches(info)
+    }
+
+    fn prune(&self, info: &pathinfo::PathInfo) -> bool {
+        (**self).prune(info)
+    }
+}
+
+impl Filter {
+    /// Scan limits for every content search, including those in `expr`.
+    pub fn set_scan_limits(&mut self, max_bytes: u64, binary: bool) {
+        for c in &mut self.content {
+            c.max_bytes = max_bytes;
+            c.binary = binary;
+        }
+        if let Some(expr) = &mut self.expr {
+            expr.set_scan_limits(max_bytes, binary);
+        }
+    }
+
+    fn rel_path<'a>(&self, path: &'a Path) -> &'a Path {
+        self.root.as_deref().and_then(|r| path.strip_prefix(r).ok()).unwrap_or(path)
+    }
+
+    /// A directory between the root and this entry is pruned; only matters
+    /// when the entries do not come from a pruning walk (e.g. archive members).
+    fn under_pruned_dir(&self, path: &Path) -> bool {
+        !self.prune.is_empty()
+            && self
+                .rel_path(path)
+                .parent()
+                .is_some_and(|p| p.iter().any(|c| self.prune.iter().any(|n| c == n.as_str())))
+    }
+}
+
+impl Matcher for Filter {
+    fn prune(&self, info: &pathinfo::PathInfo) -> bool {
+        let pruned_dir = info.is_dir
+            && info.path.file_name().is_some_and(|n| self.prune.iter().any(|p| n == p.as_str()));
+        pruned_dir || self.exclude.iter().any(|g| g.is_match(&info.path, self.root.as_deref()))
+    }
+
+    fn matches(&self, info: &pathinfo::PathInfo) -> bool {
+        if self.prune(info) || self.under_pruned_dir(&info.path) { return false; }
+
+        // type
+        if self.ty_file && !info.is_file { return false; }
+        if self.ty_dir  && !info.is_dir  { return false; }
+
+        // hidden
+        if !self.include_hidden && info.hidden { return false; }
+
+        // name-based
+        if !self.contains.is_empty()
+            && !self.contains.iter().any(|s| info.file_name.contains(&s.to_ascii_lowercase())) {
+            return false;
+        }
+        if !self.prefix.is_empty()
+            && !self.prefix.iter().any(|p| info.file_name.starts_with(&p.to_ascii_lowercase())) {
+            return false;
+        }
+        if !self.suffix.is_empty()
+            && !self.suffix.iter().any(|s| info.file_name.ends_with(&s.to_ascii_lowercase())) {
+            return false;
+        }
+
+        if !self.globs.is_empty() && !self.globs.iter().any(|g| g.is_match(&info.path, self.root.as_deref())) {
+            return false;
+        }
+
+        // regex
+        if !self.name_regex.is_empty() {
+            let name = info.path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+            if !self.name_regex.iter().any(|re| re.is_match(&name)) { return false; }
+        }
+        if !self.path_regex.is_empty() {
+            let rel = self.rel_path(&info.path).to_string_lossy();
+            if !self.path_regex.iter().any(|re| re.is_match(&rel)) { return false; }
+        }
+
+        // ext
+        if !self.exts.is_empty() {
+            if let Some(ext) = &info.ext {
+                if !self.exts.iter().any(|e| e == ext) { return false; }
+            } else { return false; }
+        }
+
+        // size
+        if let Some(min) = self.min_size {
+            if info.size.is_none_or(|sz| sz < min) { return false; }
+        }
+        if let Some(max) = self.max_size {
+            if info.size.is_none_or(|sz| sz > max) { return false; }
+        }
+
+        // age
+        if let Some(min_age) = self.min_age_secs {
+            if info.age_secs.is_none_or(|a| a < min_age) { return false; }
+        }
+        if let Some(max_age) = self.max_age_secs {
+            if info.age_secs.is_none_or(|a| a > max_age) { return false; }
+        }
+
+        // expression last: it may read file contents
+        if let Some(expr) = &self.expr {
+            if !expr.eval(info, self.root.as_deref()) { return false; }
+        }
+
+        // contents
+        if !self.content.is_empty() {
+            if !info.is_file { return false; }
+            if !self.content.iter().all(|c| c.is_match(&info.path)) { return false; }
+        }
+
+        true
+    }
+}
+
+pub fn merge(mut a: Filter, b: Filter) -> Filter {
+    a.contains.extend(b.contains);
+    a.prefix.extend(b.prefix);
+    a.suffix.extend(b.suffix);
+    a.exts.extend(b.exts);
+    a.globs.extend(b.globs);
+    a.exclude.extend(b.exclude);
+    a.prune.extend(b.prune);
+    a.name_regex.extend(b.name_regex);
+    a.path_regex.extend(b.path_regex);
+    a.content.extend(b.content);
+    if b.root.is_some()         { a.root = b.root; }
+    a.expr = match (a.expr, b.expr) {
+        (Some(x), Some(y)) => Some(Expr::And(vec![x, y])),
+        (x, y) => x.or(y),
+    };
+    if b.ty_file || b.ty_dir { a.ty_file = b.ty_file; a.ty_dir = b.ty_dir; }
+    if b.min_age_secs.is_some() { a.min_age_secs = b.min_age_secs; }
+    if b.max_age_secs.is_some() { a.max_age_secs = b.max_age_secs; }
+    if b.min_size.is_some()     { a.min_size = b.min_size; }
+    if b.max_size.is_some()     { a.max_size = b.max_size; }
+    if b.include_hidden         { a.include_hidden = true; }
+    a
+}
+
+#[cfg(test)]
+#[allow(clippy::field_reassign_with_default)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn mk_info(
+        name: &str,
+        ext: Option<&str>,
+        is_file: bool,
+        size: Option<u64>,
+        age_secs: Option<u64>,
+        hidden: bool,
+    ) -> pathinfo::PathInfo {
+        pathinfo::PathInfo {
+            path: PathBuf::from(format!("/tmp/{}", name)),
+            file_name: name.to_ascii_lowercase(),
+            ext: ext.map(|e| e.to_ascii_lowercase()),
+            is_file,
+            is_dir: !is_file,
+            size,
+            mtime: None,
+            age_secs,
+            hidden,
+        }
+    }
+
+    #[test]
+    fn name_contains_prefix_suffix() {
+        let mut f = Filter::default();
+        f.contains = vec!["report".into()];
+        f.prefix   = vec!["2024_".into()];
+        f.suffix   = vec![".txt".into()];
+
+        let ok = mk_info("2024_report.txt", Some("txt"), true, Some(10), Some(100), false);
+        assert!(f.matches(&ok));
+
+        let miss_contains = mk_info("2024_log.txt", Some("txt"), true, Some(10), Some(100), false);
+        assert!(!f.matches(&miss_contains));
+
+        let miss_prefix = mk_info("backup_report.txt", Some("txt"), true, Some(10), Some(100), false);
+        assert!(!f.matches(&miss_prefix));
+
+        let miss_suffix = mk_info("2024_report.md", Some("md"), true, Some(10), Some(100), false);
+        assert!(!f.matches(&miss_suffix));
+    }
+
+    #[test]
+    fn glob_patterns() {
+        let root = Path::new("/tmp");
+        let mut f = Filter::default();
+        f.globs = vec![GlobPattern::new("report-202?-*.csv").unwrap()];
+        f.root = Some(root.to_path_buf());
+
+        assert!(f.matches(&mk_info("Report-2024-q1.CSV", Some("csv"), true, Some(1), Some(1), false)));
+        assert!(!f.matches(&mk_info("report-2019-q1.csv", Some("csv"), true, Some(1), Some(1), false)));
+        assert!(!f.matches(&mk_info("summary.csv", Some("csv"), true, Some(1), Some(1), false)));
+
+        let braces = GlobPattern::new("*.{jpg,png}").unwrap();
+        assert!(braces.is_match(Path::new("/tmp/a/b/x.png"), Some(root)));
+        assert!(!braces.is_match(Path::new("/tmp/x.gif"), Some(root)));
+
+        let class = GlobPattern::new("img_[0-9][0-9].*").unwrap();
+        assert!(class.is_match(Path::new("/tmp/img_07.jpg"), Some(root)));
+        assert!(!class.is_match(Path::new("/tmp/img_7a.jpg"), Some(root)));
+
+        let deep = GlobPattern::new("src/**/*.rs").unwrap();
+        assert!(deep.is_match(Path::new("/tmp/src/main.rs"), Some(root)));
+        assert!(deep.is_match(Path::new("/tmp/src/a/b/lib.rs"), Some(root)));
+        assert!(!deep.is_match(Path::new("/tmp/tests/a.rs"), Some(root)));
+
+        let shallow = GlobPattern::new("src/*.rs").unwrap();
+        assert!(!shallow.is_match(Path::new("/tmp/src/a/lib.rs"), Some(root)));
+
+        assert!(GlobPattern::new("a[").is_err());
+        assert!(is_glob("*.csv") && is_glob("{a,b}") && !is_glob(".log"));
+    }
+
+    #[test]
+    fn regex_name_and_path() {
+        let mut f = Filter::default();
+        f.name_regex = vec![build_regex(r"^backup-\d{8}\.tar$", false).unwrap()];
+        let mut info = mk_info("backup-20240131.tar", Some("tar"), true, Some(1), Some(1), false);
+        assert!(f.matches(&info));
+        info.path = PathBuf::from("/tmp/Backup-20240131.tar");
+        assert!(!f.matches(&info));
+        f.name_regex = vec![build_regex(r"^backup-\d{8}\.tar$", true).unwrap()];
+        assert!(f.matches(&info));
+
+        let mut p = Filter::default();
+        p.path_regex = vec![build_regex(r"^daily/\d{4}/", false).unwrap()];
+        p.root = Some(PathBuf::from("/srv/bk"));
+        let mut deep = mk_info("x.tar", Some("tar"), true, Some(1), Some(1), false);
+        deep.path = PathBuf::from("/srv/bk/daily/2024/x.tar");
+        assert!(p.matches(&deep));
+        deep.path = PathBuf::from("/srv/bk/weekly/2024/x.tar");
+        assert!(!p.matches(&deep));
+
+        assert!(build_regex("(", false).is_err());
+    }
+
+    #[test]
+    fn combinators_closures_and_boxes() {
+        let mut txt = Filter::default();
+        txt.exts = vec!["txt".into()];
+        let big = |i: &pathinfo::PathInfo| i.size.is_some_and(|s| s > 100);
+
+        let small_txt = mk_info("a.txt", Some("txt"), true, Some(10), Some(1), false);
+        let big_txt = mk_info("b.txt", Some("txt"), true, Some(1000), Some(1), false);
+        let big_md = mk_info("c.md", Some("md"), true, Some(1000), Some(1), false);
+
+        let m = txt.clone().and(big);
+        assert!(m.matches(&big_txt) && !m.matches(&small_txt) && !m.matches(&big_md));
+        let m = Or(txt.clone(), big);
+        assert!(m.matches(&small_txt) && m.matches(&big_md));
+        let m = Not(big).and(txt.clone());
+        assert!(m.matches(&small_txt) && !m.matches(&big_txt));
+
+        // Built at runtime from a list.
+        let parts: Vec<Box<dyn Matcher>> = vec![Box::new(txt), Box::new(big)];
+        let any = parts
+            .into_iter()
+            .reduce(|a, b| Box::new(a.or(b)))
+            .unwrap();
+        assert!(any.matches(&big_md) && any.matches(&small_txt));
+        let none: Box<dyn Matcher + Send + Sync> = Box::new(|_: &pathinfo::PathInfo| false);
+        assert!(!none.matches(&big_md));
+    }
+
+    #[test]
+    fn exclude_and_prune() {
+        let mut f = Filter::default();
+        f.root = Some(PathBuf::from("/tmp"));
+        f.exclude = vec![GlobPattern::new("*.min.js").unwrap(), GlobPattern::new("vendor/**").unwrap()];
+        f.prune = vec!["node_modules".into()];
+
+        let mut nm = mk_info("node_modules", None, false, None, Some(1), false);
+        assert!(f.prune(&nm));
+        nm.is_dir = false;
+        nm.is_file = true;
+        assert!(!f.prune(&nm));
+
+        let min = mk_info("app.min.js", Some("js"), true, Some(1), Some(1), false);
+        assert!(f.prune(&min) && !f.matches(&min));
+        let mut vendored = mk_info("x.js", Some("js"), true, Some(1), Some(1), false);
+        vendored.path = PathBuf::from("/tmp/vendor/x.js");
+        assert!(!f.matches(&vendored));
+        let mut nested = mk_info("y.js", Some("js"), true, Some(1), Some(1), false);
+        nested.path = PathBuf::from("/tmp/a/node_modules/b/y.js");
+        assert!(!f.prune(&nested) && !f.matches(&nested));
+        assert!(f.matches(&mk_info("app.js", Some("js"), true, Some(1), Some(1), false)));
+
+        // Or only prunes when both sides agree.
+        let never = |_: &pathinfo::PathInfo| true;
+        assert!(!Or(f.clone(), never).prune(&min));
+        assert!(And(f, never).prune(&min));
+    }
+
+    #[test]
+    fn ext_filter() {
+        let mut f = Filter::default();
+        f.exts = vec!["txt".into(), "md".into()];
+
+        let txt = mk_info("note.txt", Some("TXT"), true, Some(1), Some(1), false);
+        let md  = mk_info("readme.md", Some("md"), true, Some(1), Some(1), false);
+        let log = mk_info("app.log", Some("log"), true, Some(1), Some(1), false);
+        let noext = mk_info("LICENSE", None, true, Some(1), Some(1), false);
+
+        assert!(f.matches(&txt));
+        assert!(f.matches(&md));
+        assert!(!f.matches(&log));
+        assert!(!f.matches(&noext));
+    }
+
+    #[test]
+    fn type_filters() {
+        let mut only_file = Filter::default();
+        only_file.ty_file = true;
+
+        let f1 = mk_info("a.txt", Some("txt"), true, Some(1), Some(1), false);
+        let d1 = mk_info("dir", None, false, None, None, false);
+
+        assert!(only_file.matches(&f1));
+        assert!(!only_file.matches(&d1));
+
+        // dir만 허용
+        let mut only_dir = Filter::default();
+        only_dir.ty_dir = true;
+
+        assert!(!only_dir.matches(&f1));
+        assert!(only_dir.matches(&d1));
+    }
+
+    #[test]
+    fn hidden_behavior() {
+        let f = Filter::default();
+        let hidden_file = mk_info(".env", Some("env"), true, Some(1), Some(1), true);
+        let visible_file = mk_info("env", Some("env"), true, Some(1), Some(1), false);
+
+        assert!(!f.matches(&hidden_file));
+        assert!(f.matches(&visible_file));
+
+        let mut f2 = Filter::default();
+        f2.include_hidden = true;
+        assert!(f2.matches(&hidden_file));
+    }
+
+    #[test]
+    fn size_bounds() {
+        let mut f = Filter::default();
+        f.min_size = Some(10);
+        f.max_size = Some(100);
+
+        let s5   = mk_info("a", Some("txt"), true, Some(5),   Some(1), false);
+        let s10  = mk_info("b", Some("txt"), true, Some(10),  Some(1), false);
+        let s50  = mk_info("c", Some("txt"), true, Some(50),  Some(1), false);
+        let s100 = mk_info("d", Some("txt"), true, Some(100), Some(1), false);
+        let s200 = mk_info("e", Some("txt"), true, Some(200), Some(1), false);
+
+        assert!(!f.matches(&s5));
+        assert!(f.matches(&s10));
+        assert!(f.matches(&s50));
+        assert!(f.matches(&s100));
+        assert!(!f.matches(&s200));
+
+        let dir = mk_info("dir", None, false, None, None, false);
+        assert!(!f.matches(&dir));
+    }
+
+    #[test]
+    fn age_bounds() {
+        let mut f = Filter::default();
+        f.min_age_secs = Some(60);
+        f.max_age_secs = Some(3600);
+
+        let a30   = mk_info("new",  Some("txt"), true, Some(1),  Some(30),   false);
+        let a60   = mk_info("ok1",  Some("txt"), true, Some(1),  Some(60),   false);
+        let a120  = mk_info("ok2",  Some("txt"), true, Some(1),  Some(120),  false);
+        let a3600 = mk_info("ok3",  Some("txt"), true, Some(1),  Some(3600), false);
+        let a7200 = mk_info("old",  Some("txt"), true, Some(1),  Some(7200), false);
+
+        assert!(!f.matches(&a30));
+        assert!(f.matches(&a60));
+        assert!(f.matches(&a120));
+        assert!(f.matches(&a3600));
+        assert!(!f.matches(&a7200));
+
+        let unknown_age = mk_info("x", Some("txt"), true, Some(1), None, false);
+        assert!(!f.matches(&unknown_age));
+    }
+
+    #[test]
+    fn merge_semantics() {
+        let mut a = Filter::default();
+        a.contains = vec!["log".into()];
+        a.min_size = Some(10);
+
+        let mut b = Filter::default();
+        b.contains = vec!["error".into()];
+        b.exts = vec!["txt".into()];
+        b.max_size = Some(100);
+        b.include_hidden = true;
+        b.ty_file = true;
+
+        let m = merge(a, b);
+        assert_eq!(m.contains, vec!["log".to_string(), "error".to_string()]);
+        assert_eq!(m.exts, vec!["txt".to_string()]);
+        assert_eq!(m.min_size, Some(10));
+        assert_eq!(m.max_size, Some(100));
+        assert!(m.include_hidden);
+        assert!(m.ty_file);
+        assert!(!m.ty_dir);
+    }
+
+    #[test]
+    fn combined_filters_pass_and_fail() {
+        let mut f = Filter::default();
+        f.ty_file = true;
+        f.exts = vec!["txt".into()];
+        f.contains = vec!["report".into()];
+        f.min_size = Some(1);
+        f.max_size = Some(100);
+        f.min_age_secs = Some(10);
+        f.max_age_secs = Some(1000);
+
+        let ok = mk_info("monthly_report.txt", Some("txt"), true, Some(50), Some(200), false);
+        assert!(f.matches(&ok));
+
+        let bad_ext = mk_info("monthly_report.md", Some("md"), true, Some(50), Some(200), false);
+        assert!(!f.matches(&bad_ext));
+
+        let bad_name = mk_info("monthly_summary.txt", Some("txt"), true, Some(50), Some(200), false);
+        assert!(!f.matches(&bad_name));
+
+        let bad_size = mk_info("monthly_report.txt", Some("txt"), true, Some(1000), Some(200), false);
+        assert!(!f.matches(&bad_size));
+
+        let bad_age = mk_info("monthly_report.txt", Some("txt"), true, Some(50), Some(5), false);
+        assert!(!f.matches(&bad_age));
+
+        let hidden = mk_info(".monthly_report.txt", Some("txt"), true, Some(50), Some(200), true);
+        assert!(!f.matches(&hidden));
+    }
 }
\ No newline at end of file
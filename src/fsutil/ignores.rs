@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+/// Per-directory ignore files, highest precedence last.
+const IGNORE_FILES: [&str; 2] = [".gitignore", ".ignore"];
+
+/// Gitignore-style rules for a walk, shared by the sequential and the
+/// parallel walker.
+///
+/// Rules come from `.gitignore` and `.ignore` in every directory (deeper
+/// files win, `.ignore` beats `.gitignore`), `.git/info/exclude` of the
+/// enclosing repository, and git's global excludes file. `!pattern`
+/// re-includes as in git, except under an already ignored directory,
+/// which the walkers never descend into. Each directory's files are read
+/// once, on first use, so entries may be checked in any order.
+#[derive(Debug)]
+pub struct IgnoreRules {
+    root: PathBuf,
+    /// Rules of directories below and including the root, by directory.
+    dirs: Mutex<HashMap<PathBuf, Option<Arc<Vec<Gitignore>>>>>,
+    /// Ancestors above the walk root, then `info/exclude` and global rules.
+    base: Vec<Gitignore>,
+}
+
+impl IgnoreRules {
+    /// Rules for a walk starting at `root`, including ignore files of
+    /// ancestor directories up to the enclosing git repository.
+    pub fn new(root: &Path) -> Self {
+        let root = absolute(root);
+        let mut base = Vec::new();
+        if let Some(repo) = root.ancestors().find(|d| d.join(".git").exists()) {
+            let above: Vec<&Path> = root.ancestors().skip(1).take_while(|d| d.starts_with(repo)).collect();
+            base.extend(above.into_iter().filter_map(dir_rules));
+            base.extend(file_rules(repo, &repo.join(".git/info/exclude")));
+        }
+        let (global, _) = Gitignore::global();
+        if !global.is_empty() {
+            base.push(global);
+        }
+        Self { root, dirs: Mutex::new(HashMap::new()), base }
+    }
+
+    /// Whether `path`, an entry below the root, is ignored.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let path = absolute(path);
+        if is_dir && path.file_name().is_some_and(|n| n == ".git") {
+            return true;
+        }
+        for dir in path.ancestors().skip(1).take_while(|d| d.starts_with(&self.root)) {
+            let Some(ms) = self.rules(dir) else { continue };
+            for m in ms.iter() {
+                let hit = m.matched(&path, is_dir);
+                if hit.is_ignore() {
+                    return true;
+                }
+                if hit.is_whitelist() {
+                    return false;
+                }
+            }
+        }
+        for m in &self.base {
+            let hit = m.matched(&path, is_dir);
+            if hit.is_ignore() {
+                return true;
+            }
+            if hit.is_whitelist() {
+                return false;
+            }
+        }
+        false
+    }
+
+    /// The ignore files of directory `dir`, loaded on first use.
+    fn rules(&self, dir: &Path) -> Option<Arc<Vec<Gitignore>>> {
+        if let Some(ms) = self.dirs.lock().unwrap().get(dir) {
+            return ms.clone();
+        }
+        let mut ms: Vec<Gitignore> = dir_rules(dir).into_iter().collect();
+        if dir != self.root && dir.join(".git").exists() {
+            ms.extend(file_rules(dir, &dir.join(".git/info/exclude")));
+        }
+        let ms = (!ms.is_empty()).then(|| Arc::new(ms));
+        self.dirs.lock().unwrap().entry(dir.to_path_buf()).or_insert(ms).clone()
+    }
+}
+
+/// `.gitignore` + `.ignore` of `dir` as one matcher (later lines win).
+fn dir_rules(dir: &Path) -> Option<Gitignore> {
+    let mut b = GitignoreBuilder::new(dir);
+    let mut any = false;
+    for name in IGNORE_FILES {
+        let f = dir.join(name);
+        if f.is_file() {
+            // Invalid lines are skipped; the rest of the file still applies.
+            let _ = b.add(&f);
+            any = true;
+        }
+    }
+    any.then(|| b.build().ok()).flatten().filter(|g| !g.is_empty())
+}
+
+fn file_rules(root: &Path, file: &Path) -> Option<Gitignore> {
+    if !file.is_file() {
+        return None;
+    }
+    let mut b = GitignoreBuilder::new(root);
+    let _ = b.add(file);
+    b.build().ok().filter(|g| !g.is_empty())
+}
+
+fn absolute(p: &Path) -> PathBuf {
+    std::path::absolute(p).unwrap_or_else(|_| p.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn nested_rules_and_negation() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join(".git/info")).unwrap();
+        fs::write(root.join(".git/info/exclude"), "*.tmp\n").unwrap();
+        fs::write(root.join(".gitignore"), "target/\n*.log\n").unwrap();
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("sub/.gitignore"), "!keep.log\n").unwrap();
+        fs::write(root.join("sub/.ignore"), "local.txt\n").unwrap();
+
+        let rules = IgnoreRules::new(root);
+        assert!(rules.is_ignored(&root.join("target"), true));
+        assert!(!rules.is_ignored(&root.join("target"), false));
+        assert!(rules.is_ignored(&root.join("a.log"), false));
+        assert!(rules.is_ignored(&root.join("x.tmp"), false));
+        assert!(rules.is_ignored(&root.join(".git"), true));
+
+        assert!(!rules.is_ignored(&root.join("sub"), true));
+        assert!(!rules.is_ignored(&root.join("sub/keep.log"), false));
+        assert!(rules.is_ignored(&root.join("sub/other.log"), false));
+        assert!(rules.is_ignored(&root.join("sub/local.txt"), false));
+
+        // Rules of `sub` do not apply outside it, in any order.
+        assert!(!rules.is_ignored(&root.join("local.txt"), false));
+        assert!(rules.is_ignored(&root.join("keep.log"), false));
+    }
+
+    #[test]
+    fn ancestors_inside_the_repo_apply() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(root.join(".gitignore"), "*.o\n").unwrap();
+        fs::create_dir_all(root.join("src/deep")).unwrap();
+
+        let start = root.join("src/deep");
+        let rules = IgnoreRules::new(&start);
+        assert!(rules.is_ignored(&start.join("a.o"), false));
+        assert!(!rules.is_ignored(&start.join("a.c"), false));
+    }
+}
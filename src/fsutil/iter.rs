@@ -1,54 +1,337 @@
-use std::{path::PathBuf, time::SystemTime};
-use walkdir::WalkDir;
-
-use super::filter::Matcher;
-use super::pathinfo::{PathInfo};
-/// Iterator over filesystem entries applying a Matcher on normalized PathInfo.
-
-pub struct FindIter<M: Matcher> {
-    it: walkdir::IntoIter,
-    matcher: M,
-    now: SystemTime,
-}
-
-impl<M: Matcher> FindIter<M> {
-    /// Create a new iterator rooted at `root` using the provided matcher.
-    pub fn new(root: &str, matcher: M) -> Self {
-        Self {
-            it: WalkDir::new(root).into_iter(),
-            matcher,
-            now: SystemTime::now(),
-        }
-    }
-
-    /// Create a new iterator with an injected `now` (useful for tests).
-    pub fn with_now(root: &str, matcher: M, now: SystemTime) -> Self {
-        Self {
-            it: WalkDir::new(root).into_iter(),
-            matcher,
-            now,
-        }
-    }
-
-    /// Access the internal matcher (read-only).
-    pub fn matcher(&self) -> &M {
-        &self.matcher
-    }
-}
-
-impl<M: Matcher> Iterator for FindIter<M> {
-    type Item = PathBuf;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        while let Some(Ok(entry)) = self.it.next() {
-            let path = entry.path();
-            // Build PathInfo once and reuse; skip entries we cannot stat.
-            if let Some(info) = PathInfo::from_fs(path, self.now) {
-                if self.matcher.matches(&info) {
-                    return Some(info.path);
-                }
-            }
-        }
-        None
-    }
-}
\ No newline at end of file
+use std::{fmt, fs, io, path::{Path, PathBuf}, sync::Arc, time::SystemTime};
+use walkdir::WalkDir;
+
+use super::filter::Matcher;
+use super::ignores::IgnoreRules;
+use super::parallel::ParWalk;
+use super::pathinfo::{PathInfo};
+
+/// Traversal settings that do not depend on the entries themselves.
+#[derive(Clone, Debug, Default)]
+pub struct WalkOptions {
+    /// Skip whatever `.gitignore`, `.ignore` and git excludes ignore.
+    pub respect_ignore: bool,
+    /// Only yield entries at least this deep (the root is depth 0).
+    pub min_depth: usize,
+    /// Do not descend below this depth.
+    pub max_depth: Option<usize>,
+    /// Descend into symlinked directories and match symlink targets.
+    pub follow_symlinks: bool,
+    /// Do not cross into other filesystems (mount points).
+    pub one_file_system: bool,
+    /// Worker threads for `FindIter::parallel` (0 = one per CPU); `None`
+    /// walks on the calling thread.
+    pub threads: Option<usize>,
+    /// Yield matches and walk errors in path order, whichever walker runs.
+    pub sorted: bool,
+}
+
+/// An entry the walk could not examine; the walk continues past it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WalkError {
+    pub path: PathBuf,
+    pub kind: WalkErrorKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WalkErrorKind {
+    /// A followed symlink pointing back at one of its ancestors.
+    Loop { ancestor: PathBuf },
+    /// Reading the directory or the entry's metadata failed.
+    Io { kind: io::ErrorKind, msg: String },
+}
+
+impl fmt::Display for WalkErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Loop { ancestor } => write!(f, "symlink loop back to {}", ancestor.display()),
+            Self::Io { msg, .. } => f.write_str(msg),
+        }
+    }
+}
+
+impl WalkError {
+    /// `None` for dangling symlinks, which have nothing to examine.
+    pub(crate) fn io(path: PathBuf, e: &io::Error) -> Option<Self> {
+        if e.kind() == io::ErrorKind::NotFound && fs::symlink_metadata(&path).is_ok_and(|m| m.is_symlink()) {
+            return None;
+        }
+        Some(Self { path, kind: WalkErrorKind::Io { kind: e.kind(), msg: e.to_string() } })
+    }
+}
+
+/// Iterator over filesystem entries applying a Matcher on normalized PathInfo.
+pub struct FindIter<M: Matcher> {
+    it: Walk,
+    matcher: Arc<M>,
+    now: SystemTime,
+    ignores: Option<IgnoreRules>,
+    min_depth: usize,
+    skipped: Vec<WalkError>,
+}
+
+impl<M: Matcher> FindIter<M> {
+    /// Create a new iterator rooted at `root` using the provided matcher.
+    pub fn new(root: &str, matcher: M) -> Self {
+        Self::with_options(root, matcher, WalkOptions::default())
+    }
+
+    /// Create a new iterator with explicit traversal settings.
+    pub fn with_options(root: &str, matcher: M, opts: WalkOptions) -> Self {
+        let mut walk = WalkDir::new(root)
+            .follow_links(opts.follow_symlinks)
+            .same_file_system(opts.one_file_system);
+        if let Some(d) = opts.max_depth {
+            walk = walk.max_depth(d);
+        }
+        if opts.sorted {
+            // Name order per directory is path order for the whole walk.
+            walk = walk.sort_by_file_name();
+        }
+        Self {
+            it: Walk::Seq(walk.into_iter()),
+            matcher: Arc::new(matcher),
+            now: SystemTime::now(),
+            ignores: opts.respect_ignore.then(|| IgnoreRules::new(Path::new(root))),
+            // Applied here rather than by walkdir so that shallower
+            // directories are still pruned and their ignore files read.
+            min_depth: opts.min_depth,
+            skipped: Vec::new(),
+        }
+    }
+
+    /// Create a new iterator with an injected `now` (useful for tests).
+    pub fn with_now(root: &str, matcher: M, now: SystemTime) -> Self {
+        Self { now, ..Self::new(root, matcher) }
+    }
+
+    /// Access the internal matcher (read-only).
+    pub fn matcher(&self) -> &M {
+        &self.matcher
+    }
+
+    /// Entries skipped so far because they could not be examined.
+    pub fn skipped(&self) -> &[WalkError] {
+        &self.skipped
+    }
+
+    /// Matches (with their metadata) and traversal errors as they occur.
+    /// Errors are also kept in `skipped`; iterating `FindIter` itself
+    /// yields only the matching paths.
+    pub fn results(&mut self) -> impl Iterator<Item = Result<PathInfo, WalkError>> + '_ {
+        std::iter::from_fn(move || self.next_result())
+    }
+
+    fn next_result(&mut self) -> Option<Result<PathInfo, WalkError>> {
+        let it = match &mut self.it {
+            Walk::Seq(it) => it,
+            Walk::Par(par) => {
+                let res = par.next()?;
+                if let Err(e) = &res {
+                    self.skipped.push(e.clone());
+                }
+                return Some(res);
+            }
+        };
+        loop {
+            let entry = match it.next()? {
+                Ok(entry) => entry,
+                Err(e) => {
+                    let path = e.path().map(Path::to_path_buf).unwrap_or_default();
+                    let err = match (e.loop_ancestor(), e.io_error()) {
+                        (Some(ancestor), _) => {
+                            Some(WalkError { path, kind: WalkErrorKind::Loop { ancestor: ancestor.to_path_buf() } })
+                        }
+                        (None, Some(io)) => WalkError::io(path, io),
+                        (None, None) => None,
+                    };
+                    match err {
+                        Some(err) => {
+                            self.skipped.push(err.clone());
+                            return Some(Err(err));
+                        }
+                        None => continue,
+                    }
+                }
+            };
+            let path = entry.path();
+            if let Some(ignores) = &self.ignores {
+                let is_dir = entry.file_type().is_dir();
+                if entry.depth() > 0 && ignores.is_ignored(path, is_dir) {
+                    if is_dir {
+                        it.skip_current_dir();
+                    }
+                    continue;
+                }
+            }
+            // Build PathInfo once and reuse.
+            let info = match PathInfo::try_from_fs(path, self.now) {
+                Ok(info) => info,
+                Err(e) => match WalkError::io(path.to_path_buf(), &e) {
+                    Some(err) => {
+                        self.skipped.push(err.clone());
+                        return Some(Err(err));
+                    }
+                    None => continue,
+                },
+            };
+            // Never prune the root itself.
+            if entry.depth() > 0 && self.matcher.prune(&info) {
+                if entry.file_type().is_dir() {
+                    it.skip_current_dir();
+                }
+                continue;
+            }
+            if entry.depth() >= self.min_depth && self.matcher.matches(&info) {
+                return Some(Ok(info));
+            }
+        }
+    }
+}
+
+impl<M: Matcher + Send + Sync + 'static> FindIter<M> {
+    /// Walk on `opts.threads` worker threads. Matches arrive in arbitrary
+    /// order unless `opts.sorted`, which buffers them all first.
+    pub fn parallel(root: &str, matcher: M, opts: WalkOptions) -> Self {
+        let matcher = Arc::new(matcher);
+        let now = SystemTime::now();
+        let par = ParWalk::spawn(root, Arc::clone(&matcher), &opts, opts.threads.unwrap_or(0), now);
+        Self { it: Walk::Par(par), matcher, now, ignores: None, min_depth: opts.min_depth, skipped: Vec::new() }
+    }
+}
+
+enum Walk {
+    Seq(walkdir::IntoIter),
+    Par(ParWalk),
+}
+
+impl<M: Matcher> Iterator for FindIter<M> {
+    type Item = PathBuf;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Errors are recorded in `skipped`; keep walking past them.
+        loop {
+            if let Ok(info) = self.next_result()? {
+                return Some(info.path);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsutil::filter::Filter;
+    use std::cell::RefCell;
+    use std::fs;
+    use tempfile::tempdir;
+
+    /// Matches everything and records which entries it was asked about.
+    struct Spy<'a> {
+        inner: Filter,
+        seen: &'a RefCell<Vec<PathBuf>>,
+    }
+
+    impl Matcher for Spy<'_> {
+        fn matches(&self, info: &PathInfo) -> bool {
+            self.seen.borrow_mut().push(info.path.clone());
+            self.inner.matches(info)
+        }
+
+        fn prune(&self, info: &PathInfo) -> bool {
+            self.inner.prune(info)
+        }
+    }
+
+    #[test]
+    fn pruned_directories_are_not_descended() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("a/node_modules/pkg")).unwrap();
+        fs::write(root.join("a/node_modules/pkg/index.js"), b"").unwrap();
+        fs::write(root.join("a/app.js"), b"").unwrap();
+
+        let seen = RefCell::new(Vec::new());
+        let inner = Filter { prune: vec!["node_modules".into()], ty_file: true, ..Filter::default() };
+        let found: Vec<PathBuf> = FindIter::new(&root.to_string_lossy(), Spy { inner, seen: &seen }).collect();
+
+        assert_eq!(found, vec![root.join("a/app.js")]);
+        assert!(seen.borrow().iter().all(|p| !p.starts_with(root.join("a/node_modules"))));
+    }
+
+    #[test]
+    fn respect_ignore_is_opt_in() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("target/debug")).unwrap();
+        fs::write(root.join("target/debug/app"), b"").unwrap();
+        fs::write(root.join("main.rs"), b"").unwrap();
+        fs::write(root.join(".ignore"), "target/\n").unwrap();
+
+        let files = Filter { ty_file: true, ..Filter::default() };
+        let all: Vec<PathBuf> = FindIter::new(&root.to_string_lossy(), files.clone()).collect();
+        assert_eq!(all.len(), 2);
+
+        let opts = WalkOptions { respect_ignore: true, ..WalkOptions::default() };
+        let kept: Vec<PathBuf> = FindIter::with_options(&root.to_string_lossy(), files, opts).collect();
+        assert_eq!(kept, vec![root.join("main.rs")]);
+    }
+
+    #[test]
+    fn depth_limits() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("a/b/c")).unwrap();
+        let depths = |min_depth, max_depth| -> Vec<usize> {
+            let opts = WalkOptions { min_depth, max_depth, ..WalkOptions::default() };
+            FindIter::with_options(&root.to_string_lossy(), Filter { include_hidden: true, ..Filter::default() }, opts)
+                .map(|p| p.strip_prefix(root).unwrap().components().count())
+                .collect()
+        };
+        assert_eq!(depths(0, None), vec![0, 1, 2, 3]);
+        assert_eq!(depths(2, None), vec![2, 3]);
+        assert_eq!(depths(1, Some(2)), vec![1, 2]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_loops_are_reported() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("a")).unwrap();
+        fs::write(root.join("a/f.txt"), b"").unwrap();
+        std::os::unix::fs::symlink(root, root.join("a/up")).unwrap();
+
+        let files = Filter { ty_file: true, ..Filter::default() };
+        let mut it = FindIter::new(&root.to_string_lossy(), files.clone());
+        assert_eq!(it.by_ref().count(), 1);
+        assert!(it.skipped().is_empty());
+
+        let opts = WalkOptions { follow_symlinks: true, ..WalkOptions::default() };
+        let mut it = FindIter::with_options(&root.to_string_lossy(), files, opts);
+        assert_eq!(it.by_ref().collect::<Vec<_>>(), vec![root.join("a/f.txt")]);
+        let looped = WalkError { path: root.join("a/up"), kind: WalkErrorKind::Loop { ancestor: root.to_path_buf() } };
+        assert_eq!(it.skipped(), [looped]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn errors_are_yielded_and_the_walk_continues() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("a")).unwrap();
+        fs::create_dir_all(root.join("b")).unwrap();
+        fs::write(root.join("b/f.txt"), b"").unwrap();
+        std::os::unix::fs::symlink(root, root.join("a/up")).unwrap();
+        std::os::unix::fs::symlink(root.join("missing"), root.join("a/dangling")).unwrap();
+
+        let files = Filter { ty_file: true, ..Filter::default() };
+        let opts = WalkOptions { follow_symlinks: true, sorted: true, ..WalkOptions::default() };
+        let mut it = FindIter::with_options(&root.to_string_lossy(), files, opts);
+        let results: Vec<_> = it.results().collect();
+        assert_eq!(results.len(), 2);
+        assert!(matches!(&results[0], Err(WalkError { kind: WalkErrorKind::Loop { .. }, .. })));
+        assert_eq!(results[1].as_ref().map(|i| &i.path), Ok(&root.join("b/f.txt")));
+        assert_eq!(it.skipped().len(), 1);
+    }
+}
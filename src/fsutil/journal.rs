@@ -0,0 +1,250 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+use super::hash::sha256_file;
+
+/// Kind of a journaled operation.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum JournalOp {
+    Rm,
+    Trash,
+    Mv,
+    Copy,
+    Compress,
+    /// A duplicate replaced by a hard link to `dst`.
+    Link,
+    Restore,
+    /// Marker: the run in `undoes` has been fully undone.
+    Undo,
+}
+
+/// One line of the journal: an operation that was actually applied.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct JournalEntry {
+    /// Id shared by all operations of one invocation.
+    pub run: String,
+    /// Local time, RFC 3339.
+    pub ts: String,
+    pub op: JournalOp,
+    /// Absolute source (or removed) path.
+    pub src: PathBuf,
+    /// Absolute destination, for moves, copies, archives and trashed items.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dst: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Modification time in nanoseconds since the epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mtime_ns: Option<i64>,
+    /// Set on operations performed by `undo`: the run being reversed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub undoes: Option<String>,
+}
+
+impl JournalEntry {
+    pub fn fingerprint(&self) -> Fingerprint {
+        Fingerprint { size: self.size, sha256: self.sha256.clone(), mtime_ns: self.mtime_ns }
+    }
+}
+
+/// Size plus checksum or mtime of a regular file, used to verify it before
+/// undoing. Copies and trashed files are hashed; moves keep their mtime, so
+/// size and mtime are enough there and much cheaper.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Fingerprint {
+    pub size: Option<u64>,
+    pub sha256: Option<String>,
+    pub mtime_ns: Option<i64>,
+}
+
+impl Fingerprint {
+    /// Size and checksum of a regular file; empty for directories, symlinks or on error.
+    pub fn of(path: &Path) -> Self {
+        match fs::symlink_metadata(path) {
+            Ok(md) if md.is_file() => Self { size: Some(md.len()), sha256: sha256_file(path).ok(), mtime_ns: None },
+            _ => Self::default(),
+        }
+    }
+
+    /// Size and mtime of a regular file, without reading it; empty for
+    /// directories, symlinks or on error.
+    pub fn stat(path: &Path) -> Self {
+        match fs::symlink_metadata(path) {
+            Ok(md) if md.is_file() => Self { size: Some(md.len()), sha256: None, mtime_ns: mtime_ns(&md) },
+            _ => Self::default(),
+        }
+    }
+
+    /// Whether `path` still has the recorded contents. Empty fingerprints
+    /// only require the path to exist.
+    pub fn matches(&self, path: &Path) -> bool {
+        if *self == Self::default() {
+            return fs::symlink_metadata(path).is_ok();
+        }
+        let Ok(md) = fs::symlink_metadata(path) else { return false };
+        md.is_file()
+            && self.size.is_none_or(|s| md.len() == s)
+            && self.mtime_ns.is_none_or(|t| mtime_ns(&md) == Some(t))
+            && self.sha256.as_ref().is_none_or(|h| sha256_file(path).is_ok_and(|now| now == *h))
+    }
+}
+
+fn mtime_ns(md: &fs::Metadata) -> Option<i64> {
+    let t = md.modified().ok()?;
+    match t.duration_since(std::time::UNIX_EPOCH) {
+        Ok(d) => i64::try_from(d.as_nanos()).ok(),
+        Err(e) => i64::try_from(e.duration().as_nanos()).ok().map(|n| -n),
+    }
+}
+
+/// Append-only JSON-lines log of applied operations.
+#[derive(Debug, Clone)]
+pub struct Journal {
+    path: PathBuf,
+    run: String,
+}
+
+impl Journal {
+    /// `$FILE_RS_JOURNAL`, else `$XDG_STATE_HOME/file-rs/journal.jsonl`,
+    /// falling back to `~/.local/state/file-rs/journal.jsonl`.
+    pub fn from_env() -> io::Result<Self> {
+        if let Some(p) = std::env::var_os("FILE_RS_JOURNAL").filter(|p| !p.is_empty()) {
+            return Ok(Self::at(p));
+        }
+        let state_home = std::env::var_os("XDG_STATE_HOME")
+            .map(PathBuf::from)
+            .filter(|p| p.is_absolute())
+            .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".local/state")))
+            .ok_or_else(|| io::Error::other("neither XDG_STATE_HOME nor HOME is set"))?;
+        Ok(Self::at(state_home.join("file-rs").join("journal.jsonl")))
+    }
+
+    /// Journal stored at `path`, with a fresh run id.
+    pub fn at(path: impl Into<PathBuf>) -> Self {
+        static SEQ: AtomicU32 = AtomicU32::new(0);
+        let mut run = format!("{}-{}", Local::now().format("%Y%m%dT%H%M%S"), std::process::id());
+        let seq = SEQ.fetch_add(1, Ordering::Relaxed);
+        if seq > 0 {
+            run.push_str(&format!(".{seq}"));
+        }
+        Self { path: path.into(), run }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn run_id(&self) -> &str {
+        &self.run
+    }
+
+    /// Build an entry for this run with absolute paths.
+    pub fn entry(&self, op: JournalOp, src: &Path, dst: Option<&Path>, fp: Fingerprint) -> JournalEntry {
+        JournalEntry {
+            run: self.run.clone(),
+            ts: Local::now().to_rfc3339(),
+            op,
+            src: absolute(src),
+            dst: dst.map(absolute),
+            size: fp.size,
+            sha256: fp.sha256,
+            mtime_ns: fp.mtime_ns,
+            undoes: None,
+        }
+    }
+
+    /// Journal one applied operation.
+    pub fn record(&self, op: JournalOp, src: &Path, dst: Option<&Path>, fp: Fingerprint) -> io::Result<()> {
+        self.append(&self.entry(op, src, dst, fp))
+    }
+
+    /// Append one line; each entry is a single `write` on an `O_APPEND` file.
+    pub fn append(&self, entry: &JournalEntry) -> io::Result<()> {
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let mut line = serde_json::to_vec(entry).map_err(io::Error::other)?;
+        line.push(b'\n');
+        OpenOptions::new().create(true).append(true).open(&self.path)?.write_all(&line)
+    }
+
+    /// All entries, oldest first. A missing journal is empty; lines that do
+    /// not parse (e.g. cut short by a crash) are skipped.
+    pub fn read(&self) -> io::Result<Vec<JournalEntry>> {
+        let file = match File::open(&self.path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut out = Vec::new();
+        for line in BufReader::new(file).lines() {
+            if let Ok(entry) = serde_json::from_str(&line?) {
+                out.push(entry);
+            }
+        }
+        Ok(out)
+    }
+}
+
+fn absolute(p: &Path) -> PathBuf {
+    std::path::absolute(p).unwrap_or_else(|_| p.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn append_and_read_back() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("a.txt");
+        fs::write(&file, b"abc").unwrap();
+
+        let j = Journal::at(dir.path().join("state/journal.jsonl"));
+        j.record(JournalOp::Mv, Path::new("rel/a.txt"), Some(&file), Fingerprint::of(&file)).unwrap();
+        j.record(JournalOp::Rm, &file, None, Fingerprint::default()).unwrap();
+        fs::OpenOptions::new().append(true).open(j.path()).unwrap().write_all(b"{\"run\":").unwrap();
+
+        let entries = j.read().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].run, j.run_id());
+        assert!(entries[0].src.is_absolute());
+        assert_eq!(entries[0].size, Some(3));
+        assert_eq!(
+            entries[0].sha256.as_deref(),
+            Some("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+        );
+        assert_eq!(entries[1].op, JournalOp::Rm);
+        assert_eq!(entries[1].dst, None);
+    }
+
+    #[test]
+    fn fingerprint_detects_changes() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("a.txt");
+        fs::write(&file, b"abc").unwrap();
+        let fp = Fingerprint::of(&file);
+        assert!(fp.matches(&file));
+        fs::write(&file, b"abd").unwrap();
+        assert!(!fp.matches(&file));
+
+        let stat = Fingerprint::stat(&file);
+        assert!(stat.sha256.is_none() && stat.matches(&file));
+        filetime::set_file_mtime(&file, filetime::FileTime::from_unix_time(1_000_000_000, 0)).unwrap();
+        assert!(!stat.matches(&file));
+
+        let empty = Fingerprint::of(dir.path());
+        assert_eq!(empty, Fingerprint::default());
+        assert!(empty.matches(dir.path()));
+        assert!(!empty.matches(&dir.path().join("missing")));
+    }
+}
@@ -1,6 +1,17 @@
-pub mod input;
-pub mod file;
-pub mod filter;
-pub mod pathinfo;
-pub mod iter;
-pub mod util;
+pub mod input;
+pub mod file;
+pub mod filter;
+pub mod select;
+pub mod pathinfo;
+pub mod iter;
+pub mod ignores;
+pub mod parallel;
+pub mod order;
+pub mod retention;
+pub mod content;
+pub mod dupes;
+pub mod util;
+pub mod archive;
+pub mod hash;
+pub mod trash;
+pub mod journal;
@@ -0,0 +1,259 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::str::FromStr;
+
+use super::pathinfo::PathInfo;
+
+/// What results are sorted by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortKey {
+    Name,
+    Size,
+    Mtime,
+    Path,
+    Ext,
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "name" => Ok(Self::Name),
+            "size" => Ok(Self::Size),
+            "mtime" | "time" => Ok(Self::Mtime),
+            "path" => Ok(Self::Path),
+            "ext" => Ok(Self::Ext),
+            _ => Err(format!("unknown sort key: {s} (expected name, size, mtime, path or ext)")),
+        }
+    }
+}
+
+/// Sort key and direction. Ascending is a-z, smallest and oldest first.
+/// Entries without the size or mtime being sorted on (e.g. directories)
+/// come last in either direction; ties are broken by path so the order is
+/// deterministic.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Order {
+    pub key: SortKey,
+    pub reverse: bool,
+}
+
+impl Order {
+    pub fn cmp(&self, a: &PathInfo, b: &PathInfo) -> Ordering {
+        let missing = match self.key {
+            SortKey::Size => (a.size.is_none(), b.size.is_none()),
+            SortKey::Mtime => (a.mtime.is_none(), b.mtime.is_none()),
+            _ => (false, false),
+        };
+        if missing.0 != missing.1 {
+            return missing.0.cmp(&missing.1);
+        }
+        let by_key = match self.key {
+            SortKey::Name => a.file_name.cmp(&b.file_name),
+            SortKey::Size => a.size.cmp(&b.size),
+            SortKey::Mtime => a.mtime.cmp(&b.mtime),
+            SortKey::Path => Ordering::Equal,
+            SortKey::Ext => a.ext.cmp(&b.ext),
+        };
+        let ord = by_key.then_with(|| a.path.cmp(&b.path));
+        if self.reverse {
+            ord.reverse()
+        } else {
+            ord
+        }
+    }
+}
+
+/// Which results to keep: optionally ordered, then `skip` dropped and at
+/// most `limit` returned.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Ranking {
+    pub order: Option<Order>,
+    pub skip: usize,
+    pub limit: Option<usize>,
+}
+
+impl Ranking {
+    /// Apply to a stream of results; errors are passed through as they
+    /// arrive. Without an order nothing is buffered and the source is not
+    /// read past the limit. With an order and a limit only the best
+    /// `skip + limit` entries are kept (a bounded heap); with an order and
+    /// no limit every entry is buffered and sorted.
+    pub fn apply<I, E>(self, iter: I) -> Ranked<I>
+    where
+        I: Iterator<Item = Result<PathInfo, E>>,
+    {
+        Ranked { src: iter, rank: self, seen: 0, heap: BinaryHeap::new(), sorted: None }
+    }
+}
+
+/// Iterator returned by `Ranking::apply`.
+pub struct Ranked<I> {
+    src: I,
+    rank: Ranking,
+    /// Matches read from `src` (unordered mode only).
+    seen: usize,
+    heap: BinaryHeap<ByOrder>,
+    sorted: Option<std::vec::IntoIter<PathInfo>>,
+}
+
+impl<I, E> Iterator for Ranked<I>
+where
+    I: Iterator<Item = Result<PathInfo, E>>,
+{
+    type Item = Result<PathInfo, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Some(order) = self.rank.order else {
+            let end = self.rank.limit.map(|l| self.rank.skip.saturating_add(l));
+            loop {
+                if end.is_some_and(|end| self.seen >= end) {
+                    return None;
+                }
+                match self.src.next()? {
+                    Ok(info) => {
+                        self.seen += 1;
+                        if self.seen > self.rank.skip {
+                            return Some(Ok(info));
+                        }
+                    }
+                    Err(e) => return Some(Err(e)),
+                }
+            }
+        };
+        if self.sorted.is_none() {
+            let cap = self.rank.limit.map(|l| self.rank.skip.saturating_add(l));
+            for item in self.src.by_ref() {
+                match item {
+                    Ok(info) => {
+                        if cap == Some(0) {
+                            continue;
+                        }
+                        self.heap.push(ByOrder { info, order });
+                        if cap.is_some_and(|cap| self.heap.len() > cap) {
+                            // The heap's top is the entry that ranks last.
+                            self.heap.pop();
+                        }
+                    }
+                    Err(e) => return Some(Err(e)),
+                }
+            }
+            // At most `limit` entries remain after the skip.
+            let ranked: Vec<PathInfo> =
+                std::mem::take(&mut self.heap).into_sorted_vec().into_iter().map(|k| k.info).collect();
+            let mut it = ranked.into_iter();
+            if self.rank.skip > 0 {
+                it.nth(self.rank.skip - 1);
+            }
+            self.sorted = Some(it);
+        }
+        self.sorted.as_mut()?.next().map(Ok)
+    }
+}
+
+/// Heap entry ordered by `order`, so the max-heap's top ranks last.
+struct ByOrder {
+    info: PathInfo,
+    order: Order,
+}
+
+impl PartialEq for ByOrder {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for ByOrder {}
+
+impl PartialOrd for ByOrder {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ByOrder {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.order.cmp(&self.info, &other.info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use std::time::{Duration, SystemTime};
+
+    fn infos() -> Vec<PathInfo> {
+        let now = SystemTime::now();
+        let t = |secs| Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs));
+        vec![
+            PathInfo::from_parts(Path::new("/r/b.log"), true, false, Some(30), t(300), now),
+            PathInfo::from_parts(Path::new("/r/a.txt"), true, false, Some(10), t(100), now),
+            PathInfo::from_parts(Path::new("/r/d.bin"), true, false, Some(40), t(200), now),
+            PathInfo::from_parts(Path::new("/r/c.log"), true, false, Some(20), t(400), now),
+        ]
+    }
+
+    fn names(rank: Ranking) -> Vec<String> {
+        let src = infos().into_iter().map(Ok::<_, ()>);
+        rank.apply(src).map(|r| r.unwrap().file_name).collect()
+    }
+
+    fn by(key: SortKey, reverse: bool) -> Option<Order> {
+        Some(Order { key, reverse })
+    }
+
+    #[test]
+    fn sorts_by_each_key() {
+        let all = |order| names(Ranking { order, ..Ranking::default() });
+        assert_eq!(all(by(SortKey::Name, false)), ["a.txt", "b.log", "c.log", "d.bin"]);
+        assert_eq!(all(by(SortKey::Size, true)), ["d.bin", "b.log", "c.log", "a.txt"]);
+        assert_eq!(all(by(SortKey::Mtime, false)), ["a.txt", "d.bin", "b.log", "c.log"]);
+        // Equal extensions fall back to the path.
+        assert_eq!(all(by(SortKey::Ext, false)), ["d.bin", "b.log", "c.log", "a.txt"]);
+        assert_eq!(all(None), ["b.log", "a.txt", "d.bin", "c.log"]);
+
+        let now = SystemTime::now();
+        let dir = PathInfo::from_parts(Path::new("/r/dir"), false, true, None, None, now);
+        let file = &infos()[0];
+        for reverse in [false, true] {
+            let o = Order { key: SortKey::Size, reverse };
+            assert_eq!(o.cmp(&dir, file), Ordering::Greater);
+        }
+    }
+
+    #[test]
+    fn top_n_skip_and_limit() {
+        let largest = Ranking { order: by(SortKey::Size, true), limit: Some(2), ..Ranking::default() };
+        assert_eq!(names(largest), ["d.bin", "b.log"]);
+
+        // "All but the 2 newest".
+        let rest = Ranking { order: by(SortKey::Mtime, true), skip: 2, limit: None };
+        assert_eq!(names(rest), ["d.bin", "a.txt"]);
+
+        let window = Ranking { order: by(SortKey::Name, false), skip: 1, limit: Some(2) };
+        assert_eq!(names(window), ["b.log", "c.log"]);
+
+        let unordered = Ranking { order: None, skip: 1, limit: Some(2) };
+        assert_eq!(names(unordered), ["a.txt", "d.bin"]);
+        assert!(names(Ranking { limit: Some(0), ..largest }).is_empty());
+    }
+
+    #[test]
+    fn stops_reading_at_the_limit_and_passes_errors() {
+        let mut read = 0;
+        let src = (0..100).map(|i| {
+            read += 1;
+            if i == 1 {
+                Err("boom")
+            } else {
+                Ok(PathInfo::from_parts(Path::new("/r/x"), true, false, None, None, SystemTime::now()))
+            }
+        });
+        let out: Vec<_> = Ranking { limit: Some(2), ..Ranking::default() }.apply(src).collect();
+        assert_eq!(out.len(), 3);
+        assert!(out[1].is_err());
+        assert_eq!(read, 3);
+    }
+}
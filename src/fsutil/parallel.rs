@@ -0,0 +1,239 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use ignore::{WalkBuilder, WalkState};
+
+use super::filter::Matcher;
+use super::ignores::IgnoreRules;
+use super::iter::{WalkError, WalkErrorKind, WalkOptions};
+use super::pathinfo::PathInfo;
+
+/// Results buffered between the walker threads and the consumer.
+const CHANNEL_CAPACITY: usize = 4096;
+
+/// Multi-threaded walk feeding matches to `FindIter`.
+///
+/// Directories are distributed over worker threads with work stealing
+/// (`ignore::WalkParallel`); each worker stats entries and runs the matcher
+/// itself. Output order is arbitrary unless `WalkOptions::sorted` is set, in
+/// which case all results are collected first and yielded by path, errors
+/// included, as the sequential sorted walk does.
+pub(crate) struct ParWalk {
+    rx: Receiver<WalkResult>,
+    sorted: Option<std::vec::IntoIter<WalkResult>>,
+    sort: bool,
+}
+
+type WalkResult = Result<PathInfo, WalkError>;
+
+impl ParWalk {
+    /// Start walking `root` on `threads` workers (0 = one per CPU).
+    pub(crate) fn spawn<M>(root: &str, matcher: Arc<M>, opts: &WalkOptions, threads: usize, now: SystemTime) -> Self
+    where
+        M: Matcher + Send + Sync + 'static,
+    {
+        let mut b = WalkBuilder::new(root);
+        b.standard_filters(false)
+            .threads(threads)
+            .max_depth(opts.max_depth)
+            .follow_links(opts.follow_symlinks)
+            .same_file_system(opts.one_file_system);
+        if opts.respect_ignore {
+            // The same rules as the sequential walk, not `ignore`'s own.
+            let rules = IgnoreRules::new(Path::new(root));
+            b.filter_entry(move |e| {
+                e.depth() == 0 || !rules.is_ignored(e.path(), e.file_type().is_some_and(|t| t.is_dir()))
+            });
+        }
+        let walker = b.build_parallel();
+
+        let (tx, rx) = mpsc::sync_channel(CHANNEL_CAPACITY);
+        let min_depth = opts.min_depth;
+        std::thread::spawn(move || {
+            walker.run(|| {
+                let tx = tx.clone();
+                let matcher = Arc::clone(&matcher);
+                Box::new(move |res| {
+                    let out = match res {
+                        Ok(entry) => match PathInfo::try_from_fs(entry.path(), now) {
+                            Ok(info) => {
+                                // Never prune the root itself.
+                                if entry.depth() > 0 && matcher.prune(&info) {
+                                    return if info.is_dir { WalkState::Skip } else { WalkState::Continue };
+                                }
+                                if entry.depth() < min_depth || !matcher.matches(&info) {
+                                    return WalkState::Continue;
+                                }
+                                Ok(info)
+                            }
+                            Err(e) => match WalkError::io(entry.into_path(), &e) {
+                                Some(err) => Err(err),
+                                None => return WalkState::Continue,
+                            },
+                        },
+                        Err(e) => match walk_error(e, None) {
+                            Some(err) => Err(err),
+                            None => return WalkState::Continue,
+                        },
+                    };
+                    if tx.send(out).is_err() {
+                        // The consumer is gone; stop all workers.
+                        return WalkState::Quit;
+                    }
+                    WalkState::Continue
+                })
+            });
+        });
+        Self { rx, sorted: None, sort: opts.sorted }
+    }
+
+    pub(crate) fn next(&mut self) -> Option<WalkResult> {
+        if !self.sort {
+            return self.rx.recv().ok();
+        }
+        let rx = &self.rx;
+        self.sorted
+            .get_or_insert_with(|| {
+                let mut all: Vec<WalkResult> = rx.iter().collect();
+                all.sort_by(|a, b| result_path(a).cmp(result_path(b)));
+                all.into_iter()
+            })
+            .next()
+    }
+}
+
+fn result_path(r: &WalkResult) -> &Path {
+    match r {
+        Ok(info) => &info.path,
+        Err(e) => &e.path,
+    }
+}
+
+/// Traversal errors as `WalkError`; problems in ignore files are not
+/// entries we failed to examine and are dropped.
+fn walk_error(e: ignore::Error, path: Option<PathBuf>) -> Option<WalkError> {
+    match e {
+        ignore::Error::Loop { ancestor, child } => Some(WalkError { path: child, kind: WalkErrorKind::Loop { ancestor } }),
+        ignore::Error::WithPath { path, err } => walk_error(*err, Some(path)),
+        ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => walk_error(*err, path),
+        ignore::Error::Io(err) => WalkError::io(path.unwrap_or_default(), &err),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fsutil::filter::Filter;
+    use crate::fsutil::iter::{FindIter, WalkError, WalkErrorKind, WalkOptions};
+    use std::fs;
+    use std::path::PathBuf;
+    use tempfile::tempdir;
+
+    #[test]
+    fn parallel_matches_sequential() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        for d in ["a/b/c", "a/node_modules/x", "d/target", "e"] {
+            fs::create_dir_all(root.join(d)).unwrap();
+        }
+        for f in ["a/1.txt", "a/b/2.txt", "a/b/c/3.txt", "a/node_modules/x/4.txt", "d/target/5.txt", "e/6.txt"] {
+            fs::write(root.join(f), b"x").unwrap();
+        }
+        fs::write(root.join("d/.ignore"), "target/\n").unwrap();
+
+        let filter = Filter { include_hidden: true, prune: vec!["node_modules".into()], ..Filter::default() };
+        let opts = WalkOptions { respect_ignore: true, min_depth: 1, max_depth: Some(3), sorted: true, ..WalkOptions::default() };
+        let seq: Vec<PathBuf> = FindIter::with_options(&root.to_string_lossy(), filter.clone(), opts.clone()).collect();
+        let par: Vec<PathBuf> =
+            FindIter::parallel(&root.to_string_lossy(), filter, WalkOptions { threads: Some(4), ..opts }).collect();
+        assert_eq!(par, seq);
+        assert!(seq.contains(&root.join("a/b/2.txt")));
+        assert!(!seq.contains(&root.join("a/b/c/3.txt")));
+        assert!(!seq.iter().any(|p| p.ends_with("4.txt") || p.ends_with("5.txt")));
+        assert!(!seq.contains(&root.to_path_buf()));
+    }
+
+    #[test]
+    fn ignore_rules_match_sequential() {
+        let dir = tempdir().unwrap();
+        // Rules above the enclosing repository do not apply.
+        fs::write(dir.path().join(".gitignore"), "*.md\n").unwrap();
+        let root = &dir.path().join("repo");
+        for d in [".git/info", "build/out", "src/gen", "src/keep", "vendor/lib/.git/info", "docs"] {
+            fs::create_dir_all(root.join(d)).unwrap();
+        }
+        for f in [
+            "a.log", "a.tmp", "main.rs", "build/out/x.o", "src/gen/g.rs", "src/keep/k.log", "src/keep/k.rs",
+            "src/local.txt", "vendor/lib/l.rs", "vendor/lib/l.bak", "vendor/lib/l.log", "docs/d.md", ".git/info/HEAD",
+        ] {
+            fs::write(root.join(f), b"x").unwrap();
+        }
+        fs::write(root.join(".git/info/exclude"), "*.tmp\n").unwrap();
+        fs::write(root.join(".gitignore"), "build/\n*.log\n").unwrap();
+        fs::write(root.join("src/.gitignore"), "gen/\n!keep/*.log\n").unwrap();
+        fs::write(root.join("src/.ignore"), "local.txt\n").unwrap();
+        fs::write(root.join("vendor/lib/.git/info/exclude"), "*.bak\n").unwrap();
+
+        let filter = Filter { include_hidden: true, ..Filter::default() };
+        let opts = WalkOptions { respect_ignore: true, ..WalkOptions::default() };
+        let mut seq: Vec<PathBuf> = FindIter::with_options(&root.to_string_lossy(), filter.clone(), opts.clone()).collect();
+        let mut par: Vec<PathBuf> =
+            FindIter::parallel(&root.to_string_lossy(), filter, WalkOptions { threads: Some(4), ..opts }).collect();
+        seq.sort();
+        par.sort();
+        assert_eq!(par, seq);
+
+        let rel: Vec<&str> = seq.iter().filter_map(|p| p.strip_prefix(root).ok()?.to_str()).collect();
+        assert_eq!(
+            rel,
+            [
+                "", ".gitignore", "docs", "docs/d.md", "main.rs", "src", "src/.gitignore", "src/.ignore", "src/keep",
+                "src/keep/k.log", "src/keep/k.rs", "vendor", "vendor/lib", "vendor/lib/l.rs",
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn parallel_reports_errors() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("a")).unwrap();
+        std::os::unix::fs::symlink(root, root.join("a/up")).unwrap();
+
+        let opts = WalkOptions { follow_symlinks: true, threads: Some(2), ..WalkOptions::default() };
+        let mut it = FindIter::parallel(&root.to_string_lossy(), Filter::default(), opts);
+        it.by_ref().for_each(drop);
+        let looped = WalkError { path: root.join("a/up"), kind: WalkErrorKind::Loop { ancestor: root.to_path_buf() } };
+        assert_eq!(it.skipped(), [looped]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sorted_parallel_orders_errors_like_sequential() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("a")).unwrap();
+        fs::create_dir_all(root.join("b")).unwrap();
+        fs::write(root.join("a/1.txt"), b"x").unwrap();
+        fs::write(root.join("b/2.txt"), b"x").unwrap();
+        std::os::unix::fs::symlink(root, root.join("a/up")).unwrap();
+
+        let opts = WalkOptions { follow_symlinks: true, sorted: true, ..WalkOptions::default() };
+        let paths = |mut it: FindIter<Filter>| -> Vec<(PathBuf, bool)> {
+            it.results()
+                .map(|r| match r {
+                    Ok(info) => (info.path, true),
+                    Err(e) => (e.path, false),
+                })
+                .collect()
+        };
+        let seq = paths(FindIter::with_options(&root.to_string_lossy(), Filter::default(), opts.clone()));
+        let par = paths(FindIter::parallel(&root.to_string_lossy(), Filter::default(), WalkOptions { threads: Some(2), ..opts }));
+        assert_eq!(par, seq);
+        assert!(seq.contains(&(root.join("a/up"), false)));
+        assert!(seq.contains(&(root.join("b/2.txt"), true)));
+    }
+}
@@ -0,0 +1,279 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use chrono::{DateTime, Datelike, Local};
+use regex::Regex;
+
+use super::pathinfo::PathInfo;
+
+/// How matches are grouped before the keep policy is applied to each group.
+#[derive(Debug, Clone)]
+pub enum GroupBy {
+    /// One group per parent directory.
+    Parent,
+    /// Per parent directory and file name up to its first digit, so
+    /// `db-2024-05-01.tar` and `web-2024-05-01.tar` rotate separately.
+    Prefix,
+    /// By the first capture group of a regex on the file name (the whole
+    /// match without groups). Names that do not match are always kept.
+    Capture(Regex),
+}
+
+impl GroupBy {
+    fn key(&self, info: &PathInfo) -> Option<String> {
+        let parent = || info.path.parent().map(|p| p.to_string_lossy().into_owned()).unwrap_or_default();
+        let name = info.path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+        match self {
+            Self::Parent => Some(parent()),
+            Self::Prefix => {
+                let end = name.find(|c: char| c.is_ascii_digit()).unwrap_or(name.len());
+                Some(format!("{}/{}", parent(), &name[..end]))
+            }
+            Self::Capture(re) => {
+                let caps = re.captures(&name)?;
+                Some(caps.get(1).or_else(|| caps.get(0))?.as_str().to_string())
+            }
+        }
+    }
+}
+
+/// GFS-style keep counts. Within a group, sorted newest first, `last`
+/// keeps the newest entries outright; each other rule keeps the newest
+/// entry of each of that many distinct calendar periods (local date, ISO
+/// week, year-month, year) that have one. Periods are fixed to the
+/// calendar, so running the policy again tomorrow keeps what it kept today.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Policy {
+    pub last: usize,
+    pub daily: usize,
+    pub weekly: usize,
+    pub monthly: usize,
+    pub yearly: usize,
+}
+
+/// Calendar period an entry falls into for one of the keep rules.
+#[derive(Debug, Clone, Copy)]
+enum Period {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+impl Period {
+    fn of(self, t: SystemTime) -> (i32, u32) {
+        let t = DateTime::<Local>::from(t);
+        match self {
+            Self::Day => (t.year(), t.ordinal()),
+            Self::Week => (t.iso_week().year(), t.iso_week().week()),
+            Self::Month => (t.year(), t.month()),
+            Self::Year => (t.year(), 0),
+        }
+    }
+}
+
+/// Selects the matches a keep policy does not retain.
+#[derive(Debug, Clone)]
+pub struct Retention {
+    pub policy: Policy,
+    pub group_by: GroupBy,
+}
+
+impl Retention {
+    /// Entries outside the policy, in input order. Entries without an age
+    /// (no mtime, or modified in the future) are always kept.
+    pub fn expired(&self, infos: Vec<PathInfo>) -> Vec<PathInfo> {
+        let mut groups: HashMap<String, Vec<(SystemTime, &PathBuf)>> = HashMap::new();
+        for info in &infos {
+            if let (Some(key), Some(mtime), Some(_)) = (self.group_by.key(info), info.mtime, info.age_secs) {
+                groups.entry(key).or_default().push((mtime, &info.path));
+            }
+        }
+
+        let mut expired: HashSet<PathBuf> = HashSet::new();
+        for mut members in groups.into_values() {
+            members.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+            let keep = self.keep(&members);
+            expired.extend(members.iter().enumerate().filter(|(i, _)| !keep.contains(i)).map(|(_, (_, p))| (*p).clone()));
+        }
+        infos.into_iter().filter(|i| expired.contains(&i.path)).collect()
+    }
+
+    /// Indices of `members` (newest first) to keep.
+    fn keep(&self, members: &[(SystemTime, &PathBuf)]) -> HashSet<usize> {
+        let p = self.policy;
+        let mut keep: HashSet<usize> = (0..p.last.min(members.len())).collect();
+        for (count, period) in [(p.daily, Period::Day), (p.weekly, Period::Week), (p.monthly, Period::Month), (p.yearly, Period::Year)] {
+            let mut last_bucket = None;
+            let mut kept = 0;
+            for (i, (mtime, _)) in members.iter().enumerate() {
+                if kept == count {
+                    break;
+                }
+                let bucket = period.of(*mtime);
+                if last_bucket != Some(bucket) {
+                    last_bucket = Some(bucket);
+                    keep.insert(i);
+                    kept += 1;
+                }
+            }
+        }
+        keep
+    }
+
+    /// Apply to a stream of results: errors pass through as they arrive,
+    /// matches are buffered and the expired ones yielded at the end.
+    pub fn apply<I, E>(self, iter: I) -> Expired<I>
+    where
+        I: Iterator<Item = Result<PathInfo, E>>,
+    {
+        Expired { src: iter, retention: self, buf: Vec::new(), out: None }
+    }
+}
+
+/// Iterator returned by `Retention::apply`.
+pub struct Expired<I> {
+    src: I,
+    retention: Retention,
+    buf: Vec<PathInfo>,
+    out: Option<std::vec::IntoIter<PathInfo>>,
+}
+
+impl<I, E> Iterator for Expired<I>
+where
+    I: Iterator<Item = Result<PathInfo, E>>,
+{
+    type Item = Result<PathInfo, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.out.is_none() {
+            for item in self.src.by_ref() {
+                match item {
+                    Ok(info) => self.buf.push(info),
+                    Err(e) => return Some(Err(e)),
+                }
+            }
+            let buf = std::mem::take(&mut self.buf);
+            self.out = Some(self.retention.expired(buf).into_iter());
+        }
+        self.out.as_mut()?.next().map(Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, TimeZone};
+    use std::path::Path;
+    use std::time::Duration;
+
+    const DAY: u64 = 86_400;
+
+    /// Local noon on `date`, so each file sits well inside its day.
+    fn noon(date: NaiveDate) -> SystemTime {
+        Local.from_local_datetime(&date.and_hms_opt(12, 0, 0).unwrap()).earliest().unwrap().into()
+    }
+
+    /// Sunday, 2024-03-31.
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, 31).unwrap()
+    }
+
+    fn backups(dir: &str, prefix: &str, days: impl IntoIterator<Item = u64>) -> Vec<PathInfo> {
+        let now = noon(today()) + Duration::from_secs(DAY / 4);
+        days.into_iter()
+            .map(|d| {
+                let path = format!("{dir}/{prefix}-{d:03}.tar");
+                let mtime = noon(today() - chrono::Days::new(d));
+                PathInfo::from_parts(Path::new(&path), true, false, Some(1), Some(mtime), now)
+            })
+            .collect()
+    }
+
+    fn expired_days(r: &Retention, infos: Vec<PathInfo>) -> Vec<String> {
+        r.expired(infos).into_iter().map(|i| i.file_name).collect()
+    }
+
+    #[test]
+    fn daily_weekly_monthly() {
+        let policy = Policy { daily: 7, weekly: 4, monthly: 2, ..Policy::default() };
+        let r = Retention { policy, group_by: GroupBy::Parent };
+        let expired = expired_days(&r, backups("/b", "db", 0..40));
+        // Kept: the last 7 days, the Sundays ending the three weeks before
+        // (days 7, 14, 21) and the last day of February (day 31).
+        let kept: Vec<u64> = (0..40).filter(|d| !expired.contains(&format!("db-{d:03}.tar"))).collect();
+        assert_eq!(kept, [0, 1, 2, 3, 4, 5, 6, 7, 14, 21, 31]);
+    }
+
+    #[test]
+    fn repeated_runs_keep_calendar_periods() {
+        // One backup per day for 400 days, pruned after each one as a cron job would.
+        let policy = Policy { daily: 7, weekly: 4, monthly: 12, ..Policy::default() };
+        let r = Retention { policy, group_by: GroupBy::Parent };
+        let start = NaiveDate::from_ymd_opt(2023, 1, 1).unwrap();
+        let date = |i: u64| start + chrono::Days::new(i);
+        let info = |i: u64, now: SystemTime| {
+            let path = format!("/b/db-{}.tar", date(i));
+            PathInfo::from_parts(Path::new(&path), true, false, Some(1), Some(noon(date(i))), now)
+        };
+
+        let mut kept: Vec<u64> = Vec::new();
+        for day in 0..400 {
+            kept.push(day);
+            let now = noon(date(day)) + Duration::from_secs(DAY / 4);
+            let expired: HashSet<String> = r
+                .expired(kept.iter().map(|&i| info(i, now)).collect())
+                .into_iter()
+                .map(|i| i.file_name)
+                .collect();
+            kept.retain(|&i| !expired.contains(&format!("db-{}.tar", date(i))));
+        }
+
+        // Same result as pruning the full history once.
+        let now = noon(date(399)) + Duration::from_secs(DAY / 4);
+        let once: Vec<u64> = {
+            let expired: HashSet<String> =
+                r.expired((0..400).map(|i| info(i, now)).collect()).into_iter().map(|i| i.file_name).collect();
+            (0..400).filter(|&i| !expired.contains(&format!("db-{}.tar", date(i)))).collect()
+        };
+        assert_eq!(kept, once);
+
+        // Month ends reach back a year, well beyond the daily and weekly ones.
+        let months: HashSet<(i32, u32)> = kept.iter().map(|&i| (date(i).year(), date(i).month())).collect();
+        assert_eq!(months.len(), 12);
+        let new_year_eve = (NaiveDate::from_ymd_opt(2023, 12, 31).unwrap() - start).num_days() as u64;
+        assert!(kept.contains(&new_year_eve));
+        assert!(kept.len() < 7 + 4 + 12);
+    }
+
+    #[test]
+    fn keep_last_and_grouping() {
+        let policy = Policy { last: 2, ..Policy::default() };
+        let mut infos = backups("/b", "db", [0, 1, 2]);
+        infos.extend(backups("/b", "web", [5, 6, 7]));
+        infos.extend(backups("/c", "db", [9]));
+
+        let by_dir = Retention { policy, group_by: GroupBy::Parent };
+        assert_eq!(expired_days(&by_dir, infos.clone()), ["db-002.tar", "web-005.tar", "web-006.tar", "web-007.tar"]);
+
+        let by_prefix = Retention { policy, group_by: GroupBy::Prefix };
+        assert_eq!(expired_days(&by_prefix, infos.clone()), ["db-002.tar", "web-007.tar"]);
+
+        let by_capture = Retention { policy, group_by: GroupBy::Capture(Regex::new(r"^(web)-").unwrap()) };
+        assert_eq!(expired_days(&by_capture, infos), ["web-007.tar"]);
+    }
+
+    #[test]
+    fn streams_errors_and_keeps_ageless_entries() {
+        let now = SystemTime::now();
+        let mut infos = backups("/b", "db", [0, 1]);
+        infos.push(PathInfo::from_parts(Path::new("/b/db-x.tar"), true, false, None, None, now));
+        let src = infos.into_iter().map(Ok).chain([Err("boom")]);
+        let r = Retention { policy: Policy { last: 1, ..Policy::default() }, group_by: GroupBy::Parent };
+        let out: Vec<_> = r.apply(src).collect();
+        assert!(out[0].is_err());
+        assert_eq!(out[1].as_ref().map(|i| i.file_name.as_str()), Ok("db-001.tar"));
+        assert_eq!(out.len(), 2);
+    }
+}
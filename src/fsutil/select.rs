@@ -0,0 +1,671 @@
+use std::fmt;
+use std::path::Path;
+
+use regex::{Regex, RegexBuilder};
+
+use super::content::ContentSearch;
+use super::filter::{GlobPattern, Matcher};
+use super::pathinfo::PathInfo;
+use super::util::{parse_human_age, parse_human_size};
+
+/// Boolean expression over single-field predicates, built by `parse`.
+#[derive(Clone, Debug)]
+pub enum Expr {
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Not(Box<Expr>),
+    Pred(Pred),
+}
+
+/// One condition on a path. Name tests are case-insensitive, like `Filter`.
+#[derive(Clone, Debug)]
+pub enum Pred {
+    Contains(String),
+    Prefix(String),
+    Suffix(String),
+    Ext(String),
+    IsFile,
+    IsDir,
+    /// Age in seconds compared against the value.
+    Age(Cmp, u64),
+    /// Size in bytes compared against the value.
+    Size(Cmp, u64),
+    /// Regex against the file name.
+    Name(Regex),
+    /// Regex against the path relative to the walk root.
+    Path(Regex),
+    /// Text or regex searched for in the file's lines.
+    Content(ContentSearch),
+}
+
+/// Comparison of an `age` or `size` predicate: `>`, `>=`, `<` or `<=`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cmp {
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+impl Cmp {
+    fn parse(op: &str) -> Option<Self> {
+        match op {
+            ">" => Some(Cmp::Gt),
+            ">=" => Some(Cmp::Ge),
+            "<" => Some(Cmp::Lt),
+            "<=" => Some(Cmp::Le),
+            _ => None,
+        }
+    }
+
+    fn holds(self, actual: u64, value: u64) -> bool {
+        match self {
+            Cmp::Gt => actual > value,
+            Cmp::Ge => actual >= value,
+            Cmp::Lt => actual < value,
+            Cmp::Le => actual <= value,
+        }
+    }
+}
+
+impl Expr {
+    /// Evaluate against `info`; `root` is stripped before `path~` tests.
+    pub fn eval(&self, info: &PathInfo, root: Option<&Path>) -> bool {
+        match self {
+            Expr::And(v) => v.iter().all(|e| e.eval(info, root)),
+            Expr::Or(v) => v.iter().any(|e| e.eval(info, root)),
+            Expr::Not(e) => !e.eval(info, root),
+            Expr::Pred(p) => p.eval(info, root),
+        }
+    }
+
+    /// Whether evaluating this needs to read file contents.
+    pub fn reads_content(&self) -> bool {
+        match self {
+            Expr::And(v) | Expr::Or(v) => v.iter().any(Expr::reads_content),
+            Expr::Not(e) => e.reads_content(),
+            Expr::Pred(p) => matches!(p, Pred::Content(_)),
+        }
+    }
+
+    /// Apply scan limits to every content search in the expression.
+    pub fn set_scan_limits(&mut self, max_bytes: u64, binary: bool) {
+        match self {
+            Expr::And(v) | Expr::Or(v) => v.iter_mut().for_each(|e| e.set_scan_limits(max_bytes, binary)),
+            Expr::Not(e) => e.set_scan_limits(max_bytes, binary),
+            Expr::Pred(Pred::Content(c)) => {
+                c.max_bytes = max_bytes;
+                c.binary = binary;
+            }
+            Expr::Pred(_) => {}
+        }
+    }
+}
+
+/// Reorder `and`/`or` operands so content searches run after the cheap
+/// tests; operands have no side effects, so the result is the same.
+fn cheap_first(e: Expr) -> Expr {
+    let reorder = |v: Vec<Expr>| {
+        let (mut cheap, costly): (Vec<_>, Vec<_>) = v.into_iter().map(cheap_first).partition(|e| !e.reads_content());
+        cheap.extend(costly);
+        cheap
+    };
+    match e {
+        Expr::And(v) => Expr::And(reorder(v)),
+        Expr::Or(v) => Expr::Or(reorder(v)),
+        Expr::Not(e) => Expr::Not(Box::new(cheap_first(*e))),
+        p => p,
+    }
+}
+
+impl Matcher for Expr {
+    fn matches(&self, info: &PathInfo) -> bool {
+        self.eval(info, None)
+    }
+}
+
+impl Pred {
+    fn eval(&self, info: &PathInfo, root: Option<&Path>) -> bool {
+        match self {
+            Pred::Contains(s) => info.file_name.contains(s.as_str()),
+            Pred::Prefix(s) => info.file_name.starts_with(s.as_str()),
+            Pred::Suffix(s) => info.file_name.ends_with(s.as_str()),
+            Pred::Ext(e) => info.ext.as_deref() == Some(e.as_str()),
+            Pred::IsFile => info.is_file,
+            Pred::IsDir => info.is_dir,
+            Pred::Age(cmp, v) => info.age_secs.is_some_and(|a| cmp.holds(a, *v)),
+            Pred::Size(cmp, v) => info.size.is_some_and(|s| cmp.holds(s, *v)),
+            Pred::Name(re) => {
+                let name = info.path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+                re.is_match(&name)
+            }
+            Pred::Path(re) => {
+                let rel = root.and_then(|r| info.path.strip_prefix(r).ok()).unwrap_or(&info.path);
+                re.is_match(&rel.to_string_lossy())
+            }
+            Pred::Content(c) => info.is_file && c.is_match(&info.path),
+        }
+    }
+}
+
+/// Result of parsing a `--select` expression.
+#[derive(Clone, Debug, Default)]
+pub struct Selection {
+    /// `None` for an empty expression (matches everything).
+    pub expr: Option<Expr>,
+    /// Set by a top-level `hidden:` setting.
+    pub include_hidden: bool,
+    /// `exclude:<glob>` settings: skip matching entries and their subtrees.
+    pub exclude: Vec<GlobPattern>,
+    /// `prune:<dirname>` settings: do not descend into directories so named.
+    pub prune: Vec<String>,
+}
+
+/// Walk settings that are written like conditions but are not tests.
+enum Setting {
+    Hidden(bool),
+    Exclude(GlobPattern),
+    Prune(String),
+}
+
+/// Parse error with a 1-based character column into the expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub col: usize,
+    pub msg: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "column {}: {}", self.col, self.msg)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn err<T>(col: usize, msg: impl Into<String>) -> Result<T, ParseError> {
+    Err(ParseError { col, msg: msg.into() })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Tok {
+    LParen,
+    RParen,
+    And,
+    Or,
+    Not,
+    /// `key<op>value`; `flags` only for `~/re/flags`.
+    Atom { key: String, op: String, value: String, flags: String, vcol: usize },
+}
+
+/// Parse the `--select` language:
+///
+/// ```text
+/// expr  := or
+/// or    := and ("or" and)*
+/// and   := unary (["and"] unary)*
+/// unary := "not" unary | "(" expr ")" | atom
+/// atom  := key ":" value            contains name prefix suffix ext type
+///                                   hidden exclude prune (settings)
+///        | ("age" | "size") (">" | "<") value
+///        | ("name" | "path" | "content") "~" "/" regex "/" ["i"]
+///        | "content" ":" text       file contents, case-sensitive
+/// ```
+///
+/// Values may be double-quoted to include spaces, parentheses or keywords.
+/// `=` is accepted in place of `:`. `hidden:true|false`, `exclude:<glob>` and
+/// `prune:<dirname>` are walk settings, not tests, so they may only appear as
+/// top-level conditions.
+pub fn parse(src: &str) -> Result<Selection, ParseError> {
+    let toks = lex(src)?;
+    let end = src.chars().count() + 1;
+    let mut p = Parser { toks, pos: 0, end, settings: Vec::new(), depth: 0 };
+    if p.toks.is_empty() {
+        return Ok(Selection::default());
+    }
+    let expr = p.parse_or()?;
+    if let Some((col, _)) = p.toks.get(p.pos) {
+        return err(*col, "unexpected `)`");
+    }
+
+    // Top-level settings apply to the walk; anywhere else they are ambiguous.
+    let mut sel = Selection::default();
+    let top_level = !matches!(expr, Expr::Or(_));
+    for (col, depth, setting) in p.settings {
+        if depth > 0 || !top_level {
+            return err(col, "settings (hidden, exclude, prune) cannot be used inside `or`, `not` or parentheses");
+        }
+        match setting {
+            Setting::Hidden(v) => sel.include_hidden = v,
+            Setting::Exclude(g) => sel.exclude.push(g),
+            Setting::Prune(n) => sel.prune.push(n),
+        }
+    }
+    sel.expr = match expr {
+        Expr::And(v) => {
+            let v: Vec<Expr> = v.into_iter().filter(|e| !is_setting_marker(e)).collect();
+            (!v.is_empty()).then_some(Expr::And(v))
+        }
+        e if is_setting_marker(&e) => None,
+        e => Some(e),
+    }
+    .map(cheap_first);
+    Ok(sel)
+}
+
+/// Settings parse to an empty `And` placeholder that `parse` strips.
+fn is_setting_marker(e: &Expr) -> bool {
+    matches!(e, Expr::And(v) if v.is_empty())
+}
+
+struct Parser {
+    toks: Vec<(usize, Tok)>,
+    pos: usize,
+    end: usize,
+    /// `(column, nesting depth, setting)` of every setting seen.
+    settings: Vec<(usize, usize, Setting)>,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Tok> {
+        self.toks.get(self.pos).map(|(_, t)| t)
+    }
+
+    fn col(&self) -> usize {
+        self.toks.get(self.pos).map_or(self.end, |(c, _)| *c)
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, ParseError> {
+        let mut items = vec![self.parse_and()?];
+        while self.peek() == Some(&Tok::Or) {
+            self.pos += 1;
+            items.push(self.parse_and()?);
+        }
+        Ok(if items.len() == 1 { items.pop().unwrap() } else { Expr::Or(items) })
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, ParseError> {
+        let mut items = vec![self.parse_unary()?];
+        loop {
+            match self.peek() {
+                Some(Tok::And) => {
+                    self.pos += 1;
+                    items.push(self.parse_unary()?);
+                }
+                Some(Tok::Not | Tok::LParen | Tok::Atom { .. }) => items.push(self.parse_unary()?),
+                _ => break,
+            }
+        }
+        Ok(if items.len() == 1 { items.pop().unwrap() } else { Expr::And(items) })
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, ParseError> {
+        let col = self.col();
+        let Some((_, tok)) = self.toks.get(self.pos).cloned() else {
+            return err(col, "unexpected end of expression");
+        };
+        self.pos += 1;
+        match tok {
+            Tok::Not => {
+                self.depth += 1;
+                let e = self.parse_unary()?;
+                self.depth -= 1;
+                Ok(Expr::Not(Box::new(e)))
+            }
+            Tok::LParen => {
+                self.depth += 1;
+                let e = self.parse_or()?;
+                self.depth -= 1;
+                if self.peek() != Some(&Tok::RParen) {
+                    return err(col, "unclosed `(`");
+                }
+                self.pos += 1;
+                Ok(e)
+            }
+            Tok::RParen => err(col, "unexpected `)`"),
+            Tok::And => err(col, "expected a condition before `and`"),
+            Tok::Or => err(col, "expected a condition before `or`"),
+            Tok::Atom { key, op, value, flags, vcol } => {
+                if let Some(setting) = setting(&key, &op, &value, col, vcol)? {
+                    self.settings.push((col, self.depth, setting));
+                    return Ok(Expr::And(Vec::new()));
+                }
+                atom(&key, &op, &value, &flags, col, vcol).map(Expr::Pred)
+            }
+        }
+    }
+}
+
+fn setting(key: &str, op: &str, value: &str, col: usize, vcol: usize) -> Result<Option<Setting>, ParseError> {
+    let setting = match key {
+        "hidden" => {
+            check_op(key, op, &[":", "="], col)?;
+            let v = parse_bool(value).ok_or(ParseError {
+                col: vcol,
+                msg: format!("expected true or false, found `{value}`"),
+            })?;
+            Setting::Hidden(v)
+        }
+        "exclude" => {
+            check_op(key, op, &[":", "="], col)?;
+            let g = GlobPattern::new(value)
+                .map_err(|e| ParseError { col: vcol, msg: format!("invalid glob: {e}") })?;
+            Setting::Exclude(g)
+        }
+        "prune" => {
+            check_op(key, op, &[":", "="], col)?;
+            Setting::Prune(value.to_string())
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(setting))
+}
+
+fn atom(key: &str, op: &str, value: &str, flags: &str, col: usize, vcol: usize) -> Result<Pred, ParseError> {
+    let lower = value.to_lowercase();
+    match key {
+        "contains" | "name" if op != "~" => {
+            check_op(key, op, &[":", "="], col)?;
+            Ok(Pred::Contains(lower))
+        }
+        "prefix" => check_op(key, op, &[":", "="], col).map(|_| Pred::Prefix(lower)),
+        "suffix" => check_op(key, op, &[":", "="], col).map(|_| Pred::Suffix(lower)),
+        "ext" => check_op(key, op, &[":", "="], col).map(|_| Pred::Ext(lower.trim_start_matches('.').to_string())),
+        "type" => {
+            check_op(key, op, &[":", "="], col)?;
+            match lower.as_str() {
+                "file" | "f" => Ok(Pred::IsFile),
+                "dir" | "d" => Ok(Pred::IsDir),
+                _ => err(vcol, format!("expected file or dir, found `{value}`")),
+            }
+        }
+        "age" | "size" => {
+            check_op(key, op, &[">", ">=", "<", "<="], col)?;
+            let cmp = Cmp::parse(op).expect("checked above");
+            let n = if key == "age" { parse_human_age(value) } else { parse_human_size(value) };
+            let Some(n) = n else {
+                let what = if key == "age" { "an age like 2d" } else { "a size like 10MB" };
+                return err(vcol, format!("expected {what}, found `{value}`"));
+            };
+            Ok(if key == "age" { Pred::Age(cmp, n) } else { Pred::Size(cmp, n) })
+        }
+        "content" => {
+            let search = if op == "~" {
+                ContentSearch::new(value, false, regex_flags(flags, vcol)?)
+            } else {
+                check_op(key, op, &[":", "=", "~"], col)?;
+                ContentSearch::new(value, true, false)
+            };
+            search.map(Pred::Content).map_err(|e| ParseError { col: vcol, msg: format!("invalid regex: {e}") })
+        }
+        "name" | "path" => {
+            let ignore_case = regex_flags(flags, vcol)?;
+            let re = RegexBuilder::new(value)
+                .case_insensitive(ignore_case)
+                .build()
+                .map_err(|e| ParseError { col: vcol, msg: format!("invalid regex: {e}") })?;
+            Ok(if key == "name" { Pred::Name(re) } else { Pred::Path(re) })
+        }
+        _ => err(col, format!("unknown key `{key}`")),
+    }
+}
+
+/// `i` makes a `/regex/` case-insensitive; nothing else is supported.
+fn regex_flags(flags: &str, vcol: usize) -> Result<bool, ParseError> {
+    match flags {
+        "" => Ok(false),
+        "i" => Ok(true),
+        _ => err(vcol, format!("unknown regex flag(s) `{flags}`; only `i` is supported")),
+    }
+}
+
+fn check_op(key: &str, op: &str, allowed: &[&str], col: usize) -> Result<(), ParseError> {
+    if allowed.contains(&op) {
+        return Ok(());
+    }
+    let list = allowed.iter().map(|a| format!("`{a}`")).collect::<Vec<_>>().join(" or ");
+    err(col, format!("`{key}` expects {list}, found `{op}`"))
+}
+
+fn parse_bool(v: &str) -> Option<bool> {
+    match v.to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+fn is_op_char(c: char) -> bool {
+    matches!(c, ':' | '=' | '<' | '>' | '~')
+}
+
+fn is_boundary(c: char) -> bool {
+    c.is_whitespace() || c == '(' || c == ')'
+}
+
+/// Split into tokens, each tagged with its 1-based column.
+fn lex(src: &str) -> Result<Vec<(usize, Tok)>, ParseError> {
+    let cs: Vec<char> = src.chars().collect();
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < cs.len() {
+        let c = cs[i];
+        let col = i + 1;
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if c == '(' || c == ')' {
+            out.push((col, if c == '(' { Tok::LParen } else { Tok::RParen }));
+            i += 1;
+            continue;
+        }
+        if c == '"' {
+            return err(col, "quoted value needs a key, e.g. contains:\"...\"");
+        }
+
+        let start = i;
+        while i < cs.len() && !is_boundary(cs[i]) && !is_op_char(cs[i]) && cs[i] != '"' {
+            i += 1;
+        }
+        let key: String = cs[start..i].iter().collect();
+        if i == cs.len() || is_boundary(cs[i]) {
+            let kw = match key.to_ascii_lowercase().as_str() {
+                "and" => Tok::And,
+                "or" => Tok::Or,
+                "not" => Tok::Not,
+                _ => return err(col, format!("expected `key:value`, found `{key}`")),
+            };
+            out.push((col, kw));
+            continue;
+        }
+        if cs[i] == '"' {
+            return err(i + 1, format!("expected an operator after `{key}`"));
+        }
+        if key.is_empty() {
+            return err(col, format!("missing key before `{}`", cs[i]));
+        }
+
+        let mut op = cs[i].to_string();
+        i += 1;
+        if (op == ">" || op == "<") && cs.get(i) == Some(&'=') {
+            op.push('=');
+            i += 1;
+        }
+
+        let vcol = i + 1;
+        let mut flags = String::new();
+        let value = if op == "~" {
+            if cs.get(i) != Some(&'/') {
+                return err(vcol, format!("expected `/regex/` after `{key}~`"));
+            }
+            let (v, next) = read_delimited(&cs, i, '/')?;
+            i = next;
+            while i < cs.len() && cs[i].is_ascii_alphabetic() {
+                flags.push(cs[i]);
+                i += 1;
+            }
+            v
+        } else if cs.get(i) == Some(&'"') {
+            let (v, next) = read_delimited(&cs, i, '"')?;
+            i = next;
+            v
+        } else {
+            let s = i;
+            while i < cs.len() && !is_boundary(cs[i]) {
+                i += 1;
+            }
+            cs[s..i].iter().collect()
+        };
+        if i < cs.len() && !is_boundary(cs[i]) {
+            return err(i + 1, format!("unexpected `{}` after value", cs[i]));
+        }
+        if value.is_empty() && op != "~" {
+            return err(vcol, format!("missing value for `{key}`"));
+        }
+        out.push((col, Tok::Atom { key: key.to_ascii_lowercase(), op, value, flags, vcol }));
+    }
+    Ok(out)
+}
+
+/// Read `<delim>...<delim>` starting at `start`; `\<delim>` escapes the
+/// delimiter and, in quotes, `\\` a backslash. Returns the text and the
+/// index after the closing delimiter.
+fn read_delimited(cs: &[char], start: usize, delim: char) -> Result<(String, usize), ParseError> {
+    let mut out = String::new();
+    let mut i = start + 1;
+    while i < cs.len() {
+        match cs[i] {
+            '\\' if cs.get(i + 1) == Some(&delim) => {
+                out.push(delim);
+                i += 2;
+            }
+            '\\' if delim == '"' && cs.get(i + 1) == Some(&'\\') => {
+                out.push('\\');
+                i += 2;
+            }
+            c if c == delim => return Ok((out, i + 1)),
+            c => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+    let what = if delim == '"' { "quote" } else { "regex" };
+    err(start + 1, format!("unterminated {what}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::time::{Duration, SystemTime};
+
+    fn info(path: &str, is_file: bool, size: u64, age: u64) -> PathInfo {
+        let now = SystemTime::now();
+        let mtime = now - Duration::from_secs(age);
+        PathInfo::from_parts(&PathBuf::from(path), is_file, !is_file, Some(size), Some(mtime), now)
+    }
+
+    fn matches(src: &str, i: &PathInfo) -> bool {
+        parse(src).unwrap().expr.is_none_or(|e| e.eval(i, Some(Path::new("/r"))))
+    }
+
+    #[test]
+    fn precedence_and_grouping() {
+        let jpg = info("/r/a/Holiday.JPG", true, 10, 100);
+        let txt = info("/r/b/notes.txt", true, 5000, 100);
+
+        assert!(matches("ext:jpg or ext:png", &jpg));
+        assert!(!matches("ext:jpg and ext:png", &jpg));
+        // `and` binds tighter than `or`.
+        assert!(matches("ext:txt size>1KB or ext:jpg", &jpg));
+        assert!(!matches("ext:txt (size>1KB or ext:jpg)", &jpg));
+        assert!(matches("not ext:txt", &jpg));
+        assert!(matches("not (ext:jpg or ext:png) and size>1KB", &txt));
+        assert!(matches("NOT not type:file", &txt));
+        assert!(matches(r#"contains:"holiday" path~/^a\//"#, &jpg));
+        assert!(matches("", &jpg));
+    }
+
+    #[test]
+    fn strict_and_inclusive_comparisons() {
+        let f = info("/r/a.bin", true, 1024, 7200);
+        assert!(!matches("size>1KB", &f));
+        assert!(matches("size>=1KB", &f));
+        assert!(!matches("size<1KB", &f));
+        assert!(matches("size<=1KB", &f));
+        assert!(matches("size>1023 and size<1025", &f));
+        assert!(!matches("size<0", &info("/r/empty", true, 0, 1)));
+        assert!(matches("age>1h and age<3h", &f));
+        assert!(matches("age>=1h", &f));
+    }
+
+    #[test]
+    fn quoted_values_and_regexes() {
+        let f = info("/r/x/Annual Report (final).pdf", true, 1, 1);
+        assert!(matches(r#"contains:"annual report (final)""#, &f));
+        assert!(matches(r#"name="and""#, &info("/r/band.txt", true, 1, 1)));
+        assert!(matches(r"name~/^annual .* \(final\)/i", &f));
+        assert!(!matches(r"name~/^annual/", &f));
+        assert!(matches(r"path~/^x\/Annual/", &f));
+    }
+
+    #[test]
+    fn hidden_is_a_top_level_setting() {
+        let sel = parse("ext:txt hidden:true").unwrap();
+        assert!(sel.include_hidden);
+        assert!(matches!(sel.expr, Some(Expr::And(ref v)) if v.len() == 1));
+        let only = parse("hidden:yes").unwrap();
+        assert!(only.include_hidden && only.expr.is_none());
+
+        assert_eq!(parse("ext:txt or hidden:true").unwrap_err().col, 12);
+        assert_eq!(parse("not hidden:true").unwrap_err().col, 5);
+
+        let walk = parse("exclude:*.min.js prune:node_modules ext:js").unwrap();
+        assert_eq!(walk.exclude.len(), 1);
+        assert_eq!(walk.prune, vec!["node_modules".to_string()]);
+        assert!(walk.expr.is_some());
+        assert_eq!(parse("(prune:target)").unwrap_err().col, 2);
+        assert_eq!(parse("exclude:a[").unwrap_err().col, 9);
+    }
+
+    #[test]
+    fn content_runs_after_cheap_checks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        std::fs::write(&path, "one\nTODO: two\n").unwrap();
+        let f = PathInfo::from_fs(&path, SystemTime::now()).unwrap();
+
+        assert!(matches(r"content~/^todo:/i ext:txt", &f));
+        assert!(matches(r#"content:"TODO""#, &f));
+        assert!(!matches(r#"content:"todo""#, &f));
+        assert!(!matches(r"ext:md or content~/three/", &f));
+
+        let sel = parse(r"content~/x/ or (ext:txt content~/y/) or size>1KB").unwrap();
+        let Some(Expr::Or(v)) = sel.expr else { panic!("expected or") };
+        assert!(!v[0].reads_content() && v[1].reads_content() && v[2].reads_content());
+        // Costly operands keep their relative order.
+        assert!(matches!(&v[2], Expr::And(inner) if !inner[0].reads_content()));
+        assert_eq!(parse("content~/a/q").unwrap_err().col, 9);
+    }
+
+    #[test]
+    fn errors_have_columns() {
+        let e = |s: &str| parse(s).unwrap_err();
+        assert_eq!(e("ext:txt bogus:1"), ParseError { col: 9, msg: "unknown key `bogus`".into() });
+        assert_eq!(e("ext:txt foo").col, 9);
+        assert_eq!(e("(ext:txt or ext:md").col, 1);
+        assert_eq!(e("ext:txt)").col, 8);
+        assert_eq!(e("ext:txt or").col, 11);
+        assert_eq!(e("or ext:txt").col, 1);
+        assert_eq!(e("size>lots").col, 6);
+        assert_eq!(e("age:2d").msg, "`age` expects `>` or `>=` or `<` or `<=`, found `:`");
+        assert_eq!(e(r#"contains:"open"#).col, 10);
+        assert_eq!(e("name~/(/").col, 6);
+        assert_eq!(e("name~/a/x").col, 6);
+        assert_eq!(e("type:link").col, 6);
+        assert_eq!(e("ext:").col, 5);
+    }
+}
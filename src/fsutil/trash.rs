@@ -0,0 +1,427 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{Local, NaiveDateTime};
+
+const DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// One item sitting in a trash directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrashEntry {
+    /// The trash directory holding `files/` and `info/`.
+    pub trash_dir: PathBuf,
+    /// Name inside `files/` (and `info/<name>.trashinfo`).
+    pub name: String,
+    /// Absolute original location.
+    pub original: PathBuf,
+    /// Local deletion time, if the info file had a valid one.
+    pub deleted: Option<NaiveDateTime>,
+}
+
+impl TrashEntry {
+    pub fn file_path(&self) -> PathBuf {
+        self.trash_dir.join("files").join(&self.name)
+    }
+
+    pub fn info_path(&self) -> PathBuf {
+        self.trash_dir.join("info").join(format!("{}.trashinfo", self.name))
+    }
+
+    /// Seconds since deletion, relative to `now`.
+    pub fn age_secs(&self, now: NaiveDateTime) -> Option<u64> {
+        let d = now.signed_duration_since(self.deleted?);
+        u64::try_from(d.num_seconds()).ok()
+    }
+}
+
+/// Handle on the user's trash (freedesktop.org Trash specification).
+/// Items on the same filesystem as the home trash go there; items on other
+/// mounts go to `$topdir/.Trash/$uid` or `$topdir/.Trash-$uid`.
+#[derive(Debug, Clone)]
+pub struct Trash {
+    home: PathBuf,
+}
+
+impl Trash {
+    /// Home trash from `$XDG_DATA_HOME/Trash`, falling back to `~/.local/share/Trash`.
+    pub fn from_env() -> io::Result<Self> {
+        let data_home = std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .filter(|p| p.is_absolute())
+            .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".local/share")))
+            .ok_or_else(|| io::Error::other("neither XDG_DATA_HOME nor HOME is set"))?;
+        Ok(Self::at(data_home.join("Trash")))
+    }
+
+    /// Use `home` as the home trash directory.
+    pub fn at(home: impl Into<PathBuf>) -> Self {
+        Self { home: home.into() }
+    }
+
+    pub fn home_dir(&self) -> &Path {
+        &self.home
+    }
+
+    /// Move `path` into the appropriate trash directory.
+    pub fn put(&self, path: &Path) -> io::Result<TrashEntry> {
+        let abs = absolute_unresolved(path)?;
+        fs::symlink_metadata(&abs)?;
+
+        let (trash_dir, info_path) = if same_device(&abs, &self.home)? {
+            (self.home.clone(), abs.clone())
+        } else {
+            let top = mount_top(&abs)?;
+            let dir = mount_trash_dir(&top)?;
+            let rel = abs.strip_prefix(&top).map(Path::to_path_buf).unwrap_or_else(|_| abs.clone());
+            (dir, rel)
+        };
+        fs::create_dir_all(trash_dir.join("files"))?;
+        fs::create_dir_all(trash_dir.join("info"))?;
+
+        let base = abs
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .ok_or_else(|| io::Error::other("cannot trash a path without a file name"))?;
+        let now = Local::now().naive_local();
+
+        // Reserve the name by creating the info file first (O_EXCL), as the spec asks.
+        for n in 0u32.. {
+            let name = if n == 0 { base.clone() } else { format!("{}.{}", base, n) };
+            let entry = TrashEntry { trash_dir: trash_dir.clone(), name, original: abs.clone(), deleted: Some(now) };
+            let mut info = match OpenOptions::new().write(true).create_new(true).open(entry.info_path()) {
+                Ok(f) => f,
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            };
+            if fs::symlink_metadata(entry.file_path()).is_ok() {
+                drop(info);
+                fs::remove_file(entry.info_path())?;
+                continue;
+            }
+            let body = format!(
+                "[Trash Info]\nPath={}\nDeletionDate={}\n",
+                percent_encode(&info_path),
+                now.format(DATE_FORMAT)
+            );
+            if let Err(e) = info.write_all(body.as_bytes()).and_then(|_| fs::rename(&abs, entry.file_path())) {
+                let _ = fs::remove_file(entry.info_path());
+                return Err(e);
+            }
+            return Ok(entry);
+        }
+        unreachable!("unbounded counter")
+    }
+
+    /// Every entry in the home trash and in per-mount trash directories.
+    pub fn list(&self) -> io::Result<Vec<TrashEntry>> {
+        let mut out = Vec::new();
+        read_trash_dir(&self.home, None, &mut out)?;
+        for (top, dir) in mounted_trash_dirs() {
+            if dir != self.home {
+                read_trash_dir(&dir, Some(&top), &mut out)?;
+            }
+        }
+        out.sort_by(|a, b| a.deleted.cmp(&b.deleted).then_with(|| a.name.cmp(&b.name)));
+        Ok(out)
+    }
+
+    /// Move an entry back to `to` (default: its original location).
+    /// Refuses to overwrite anything already there.
+    pub fn restore(&self, entry: &TrashEntry, to: Option<&Path>) -> io::Result<PathBuf> {
+        let dst = to.unwrap_or(&entry.original).to_path_buf();
+        if fs::symlink_metadata(&dst).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("restore target exists: {}", dst.display()),
+            ));
+        }
+        if let Some(parent) = dst.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        super::file::move_one(&entry.file_path(), &dst)?;
+        fs::remove_file(entry.info_path())?;
+        Ok(dst)
+    }
+
+    /// Permanently delete an entry (contents first, info file last).
+    pub fn purge(&self, entry: &TrashEntry) -> io::Result<()> {
+        let file = entry.file_path();
+        match fs::symlink_metadata(&file) {
+            Ok(m) if m.is_dir() => fs::remove_dir_all(&file)?,
+            Ok(_) => fs::remove_file(&file)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        fs::remove_file(entry.info_path())
+    }
+}
+
+fn read_trash_dir(dir: &Path, top: Option<&Path>, out: &mut Vec<TrashEntry>) -> io::Result<()> {
+    let info_dir = dir.join("info");
+    let rd = match fs::read_dir(&info_dir) {
+        Ok(rd) => rd,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    for ent in rd {
+        let ent = ent?;
+        let fname = ent.file_name().to_string_lossy().into_owned();
+        let Some(name) = fname.strip_suffix(".trashinfo") else { continue };
+        let Ok(text) = fs::read_to_string(ent.path()) else { continue };
+        let Some((path, deleted)) = parse_trashinfo(&text) else { continue };
+        let original = match top {
+            Some(top) if path.is_relative() => top.join(path),
+            _ => path,
+        };
+        out.push(TrashEntry { trash_dir: dir.to_path_buf(), name: name.to_string(), original, deleted });
+    }
+    Ok(())
+}
+
+/// Parse a `.trashinfo` body into (decoded Path, DeletionDate).
+pub fn parse_trashinfo(text: &str) -> Option<(PathBuf, Option<NaiveDateTime>)> {
+    let mut in_group = false;
+    let mut path = None;
+    let mut date = None;
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_group = line == "[Trash Info]";
+        } else if in_group {
+            if let Some(v) = line.strip_prefix("Path=") {
+                path = Some(percent_decode(v));
+            } else if let Some(v) = line.strip_prefix("DeletionDate=") {
+                date = NaiveDateTime::parse_from_str(v, DATE_FORMAT).ok();
+            }
+        }
+    }
+    path.map(|p| (p, date))
+}
+
+/// Current local time, as used for `DeletionDate`.
+pub fn now_local() -> NaiveDateTime {
+    Local::now().naive_local()
+}
+
+/// Absolute path with the parent resolved but the last component left alone,
+/// so a symlink is trashed as a link.
+fn absolute_unresolved(path: &Path) -> io::Result<PathBuf> {
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => {
+            let parent = if parent.as_os_str().is_empty() { Path::new(".") } else { parent };
+            Ok(fs::canonicalize(parent)?.join(name))
+        }
+        _ => fs::canonicalize(path),
+    }
+}
+
+/// RFC 2396 escaping of everything except unreserved characters and `/`.
+pub fn percent_encode(path: &Path) -> String {
+    let bytes = path.as_os_str().as_encoded_bytes();
+    let mut out = String::with_capacity(bytes.len());
+    for &b in bytes {
+        if b.is_ascii_alphanumeric() || b"-_.~/".contains(&b) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+pub fn percent_decode(s: &str) -> PathBuf {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            if let Some(b) = s.get(i + 1..i + 3).and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                out.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    PathBuf::from(String::from_utf8_lossy(&out).into_owned())
+}
+
+#[cfg(unix)]
+fn device_of(path: &Path) -> io::Result<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Ok(fs::symlink_metadata(path)?.dev())
+}
+
+/// Nearest existing ancestor (the home trash may not exist yet).
+fn existing_ancestor(path: &Path) -> &Path {
+    path.ancestors().find(|p| p.exists()).unwrap_or(Path::new("/"))
+}
+
+#[cfg(unix)]
+fn same_device(item: &Path, home_trash: &Path) -> io::Result<bool> {
+    Ok(device_of(item)? == device_of(existing_ancestor(home_trash))?)
+}
+
+#[cfg(not(unix))]
+fn same_device(_item: &Path, _home_trash: &Path) -> io::Result<bool> {
+    Ok(true)
+}
+
+/// Topmost directory of the mount containing `path`.
+#[cfg(unix)]
+fn mount_top(path: &Path) -> io::Result<PathBuf> {
+    let dev = device_of(path)?;
+    let mut top = path.parent().unwrap_or(path).to_path_buf();
+    while let Some(parent) = top.parent() {
+        if device_of(parent)? != dev {
+            break;
+        }
+        top = parent.to_path_buf();
+    }
+    Ok(top)
+}
+
+#[cfg(not(unix))]
+fn mount_top(_path: &Path) -> io::Result<PathBuf> {
+    Err(io::Error::other("per-mount trash is not supported on this platform"))
+}
+
+#[cfg(unix)]
+fn uid() -> u32 {
+    // SAFETY: getuid has no preconditions and cannot fail.
+    unsafe { libc::getuid() }
+}
+
+/// `$top/.Trash/$uid` when an admin-provided sticky `.Trash` exists,
+/// otherwise `$top/.Trash-$uid` (created 0700).
+#[cfg(unix)]
+fn mount_trash_dir(top: &Path) -> io::Result<PathBuf> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+    let shared = top.join(".Trash");
+    if let Ok(m) = fs::symlink_metadata(&shared) {
+        let sticky = m.permissions().mode() & 0o1000 != 0;
+        if m.is_dir() && sticky {
+            let dir = shared.join(uid().to_string());
+            fs::DirBuilder::new().recursive(true).mode(0o700).create(&dir)?;
+            return Ok(dir);
+        }
+    }
+    let dir = top.join(format!(".Trash-{}", uid()));
+    fs::DirBuilder::new().recursive(true).mode(0o700).create(&dir)?;
+    if fs::symlink_metadata(&dir)?.file_type().is_symlink() {
+        return Err(io::Error::other(format!("{} is a symlink", dir.display())));
+    }
+    Ok(dir)
+}
+
+#[cfg(not(unix))]
+fn mount_trash_dir(_top: &Path) -> io::Result<PathBuf> {
+    Err(io::Error::other("per-mount trash is not supported on this platform"))
+}
+
+/// (topdir, trash dir) pairs for every mount that has a trash for this user.
+#[cfg(target_os = "linux")]
+fn mounted_trash_dirs() -> Vec<(PathBuf, PathBuf)> {
+    let Ok(mounts) = fs::read_to_string("/proc/self/mounts") else { return Vec::new() };
+    let uid = uid();
+    let mut out = Vec::new();
+    for line in mounts.lines() {
+        let Some(mnt) = line.split_whitespace().nth(1) else { continue };
+        let top = PathBuf::from(unescape_mount(mnt));
+        for dir in [top.join(".Trash").join(uid.to_string()), top.join(format!(".Trash-{}", uid))] {
+            if dir.join("info").is_dir() {
+                out.push((top.clone(), dir));
+            }
+        }
+    }
+    out
+}
+
+#[cfg(not(target_os = "linux"))]
+fn mounted_trash_dirs() -> Vec<(PathBuf, PathBuf)> {
+    Vec::new()
+}
+
+/// `/proc/mounts` escapes whitespace as octal (`\040`).
+#[cfg(target_os = "linux")]
+fn unescape_mount(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find('\\') {
+        out.push_str(&rest[..i]);
+        let code = rest.get(i + 1..i + 4).and_then(|o| u8::from_str_radix(o, 8).ok());
+        match code {
+            Some(b) => {
+                out.push(b as char);
+                rest = &rest[i + 4..];
+            }
+            None => {
+                out.push('\\');
+                rest = &rest[i + 1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn percent_roundtrip() {
+        let p = Path::new("/tmp/a b/ü%.txt");
+        let enc = percent_encode(p);
+        assert_eq!(enc, "/tmp/a%20b/%C3%BC%25.txt");
+        assert_eq!(percent_decode(&enc), p);
+    }
+
+    #[test]
+    fn parses_trashinfo() {
+        let text = "[Trash Info]\nPath=/home/u/a%20b.txt\nDeletionDate=2024-01-02T03:04:05\n";
+        let (path, date) = parse_trashinfo(text).unwrap();
+        assert_eq!(path, Path::new("/home/u/a b.txt"));
+        assert_eq!(date.unwrap().format(DATE_FORMAT).to_string(), "2024-01-02T03:04:05");
+        assert!(parse_trashinfo("[Other]\nPath=/x\n").is_none());
+    }
+
+    #[test]
+    fn put_list_restore_purge() {
+        let dir = tempdir().unwrap();
+        let trash = Trash::at(dir.path().join("data/Trash"));
+        let work = dir.path().join("work");
+        fs::create_dir_all(work.join("sub")).unwrap();
+        fs::write(work.join("a.txt"), b"one").unwrap();
+        fs::write(work.join("sub/x"), b"x").unwrap();
+
+        let first = trash.put(&work.join("a.txt")).unwrap();
+        assert!(!work.join("a.txt").exists());
+        assert_eq!(fs::read(first.file_path()).unwrap(), b"one");
+        let info = fs::read_to_string(first.info_path()).unwrap();
+        assert!(info.starts_with("[Trash Info]\nPath=/"));
+
+        // Same name again gets a unique trash name.
+        fs::write(work.join("a.txt"), b"two").unwrap();
+        let second = trash.put(&work.join("a.txt")).unwrap();
+        assert_eq!(second.name, "a.txt.1");
+
+        let dir_entry = trash.put(&work.join("sub")).unwrap();
+
+        let listed = trash.list().unwrap();
+        assert_eq!(listed.len(), 3);
+        assert!(listed.iter().all(|e| e.original.starts_with(fs::canonicalize(&work).unwrap())));
+
+        let back = trash.restore(&first, None).unwrap();
+        assert_eq!(fs::read(&back).unwrap(), b"one");
+        assert!(trash.restore(&second, None).is_err());
+
+        trash.purge(&dir_entry).unwrap();
+        trash.purge(&second).unwrap();
+        assert!(trash.list().unwrap().is_empty());
+    }
+}
@@ -1,9 +1,12 @@
-mod cmd;
-mod fsutil;
-
 use clap::Parser;
+use file_rs::cmd;
 
 fn main() {
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_target(false)
+        .init();
+
     let cli = cmd::Cli::parse();
     cmd::run(cli);
-}
\ No newline at end of file
+}